@@ -0,0 +1,118 @@
+//! Conversions for array-shaped arguments and results: numpy arrays with the
+//! `numpy` feature, lists otherwise.
+
+use pyo3::prelude::*;
+
+#[cfg(feature = "numpy")]
+pub type Matrix<'py, T = i32> = Bound<'py, numpy::PyArray2<T>>;
+#[cfg(not(feature = "numpy"))]
+pub type Matrix<'py, T = i32> = Vec<Vec<T>>;
+
+/// Wraps row-major `data` of shape `(rows, cols)` for Python.
+#[cfg(feature = "numpy")]
+pub fn matrix<T: numpy::Element>(
+    py: Python<'_>,
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+) -> PyResult<Matrix<'_, T>> {
+    use numpy::{PyArray1, PyArrayMethods};
+    PyArray1::from_vec(py, data).reshape([rows, cols])
+}
+
+/// Wraps row-major `data` of shape `(rows, cols)` for Python.
+#[cfg(not(feature = "numpy"))]
+pub fn matrix<T: Clone>(
+    _py: Python<'_>,
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+) -> PyResult<Matrix<'_, T>> {
+    debug_assert_eq!(data.len(), rows * cols);
+    if cols == 0 {
+        return Ok(vec![Vec::new(); rows]);
+    }
+    Ok(data.chunks(cols).map(<[T]>::to_vec).collect())
+}
+
+#[cfg(feature = "numpy")]
+pub type Vector<'py> = Bound<'py, numpy::PyArray1<i32>>;
+#[cfg(not(feature = "numpy"))]
+pub type Vector<'py> = Vec<i32>;
+
+/// Wraps `data` for Python as a 1D array.
+#[cfg(feature = "numpy")]
+pub fn vector(py: Python<'_>, data: Vec<i32>) -> Vector<'_> {
+    numpy::PyArray1::from_vec(py, data)
+}
+
+/// Wraps `data` for Python as a list.
+#[cfg(not(feature = "numpy"))]
+pub fn vector(_py: Python<'_>, data: Vec<i32>) -> Vector<'_> {
+    data
+}
+
+/// A matrix-shaped argument: a 2D `int32` array with the `numpy` feature.
+#[cfg(feature = "numpy")]
+pub type MatrixArg<'py> = numpy::PyReadonlyArray2<'py, i32>;
+/// A matrix-shaped argument: a list of equal-length rows.
+#[cfg(not(feature = "numpy"))]
+pub type MatrixArg<'py> = Vec<Vec<i32>>;
+
+/// Flattens `arg` to row-major data of shape `(rows, cols)`.
+#[cfg(feature = "numpy")]
+pub fn matrix_data(arg: MatrixArg<'_>) -> PyResult<(usize, usize, Vec<i32>)> {
+    use numpy::PyUntypedArrayMethods;
+    let shape = arg.shape();
+    Ok((shape[0], shape[1], arg.as_array().iter().copied().collect()))
+}
+
+/// Flattens `arg` to row-major data of shape `(rows, cols)`.
+#[cfg(not(feature = "numpy"))]
+pub fn matrix_data(arg: MatrixArg<'_>) -> PyResult<(usize, usize, Vec<i32>)> {
+    let cols = arg.first().map_or(0, Vec::len);
+    if arg.iter().any(|row| row.len() != cols) {
+        return Err(crate::error::AlignError::InvalidArgument(
+            "matrix rows must all have the same length".to_string(),
+        )
+        .into());
+    }
+    Ok((arg.len(), cols, arg.concat()))
+}
+
+/// A caller-owned score buffer: a 1D `int32` array with the `numpy` feature.
+#[cfg(feature = "numpy")]
+pub type ScoresOut<'py> = numpy::PyReadwriteArray1<'py, i32>;
+/// A caller-owned score buffer: a list, overwritten item by item.
+#[cfg(not(feature = "numpy"))]
+pub type ScoresOut<'py> = Bound<'py, pyo3::types::PyList>;
+
+/// Runs `fill` on `out`'s memory with the GIL released. The array must be
+/// contiguous.
+#[cfg(feature = "numpy")]
+pub fn fill_scores<F>(py: Python<'_>, mut out: ScoresOut<'_>, fill: F) -> PyResult<()>
+where
+    F: FnOnce(&mut [i32]) -> Result<(), crate::error::AlignError> + Send,
+{
+    let scores = out.as_slice_mut().map_err(|_| {
+        crate::error::AlignError::InvalidArgument("score buffer must be contiguous".to_string())
+    })?;
+    py.detach(|| fill(scores))?;
+    Ok(())
+}
+
+/// Runs `fill` on a scratch copy with the GIL released, then writes the
+/// results into `out`.
+#[cfg(not(feature = "numpy"))]
+pub fn fill_scores<F>(py: Python<'_>, out: ScoresOut<'_>, fill: F) -> PyResult<()>
+where
+    F: FnOnce(&mut [i32]) -> Result<(), crate::error::AlignError> + Send,
+{
+    use pyo3::types::PyListMethods;
+    let mut scores = vec![0i32; out.len()];
+    py.detach(|| fill(&mut scores))?;
+    for (index, score) in scores.into_iter().enumerate() {
+        out.set_item(index, score)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! Alignment restricted to a band around the main diagonal.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Alignment, Constraints, Scoring, Step, smith_waterman_path};
+
+/// A banded alignment plus whether it may have been cut short by the band.
+#[derive(Clone, Copy, Debug)]
+pub struct BandedAlignment {
+    pub alignment: Alignment,
+    /// The traceback path reaches a cell on the band's edge next to a cell the
+    /// band excluded, so the unbanded optimum may lie outside and a wider band
+    /// could score higher.
+    pub band_clipped: bool,
+}
+
+/// Aligns `seq1` against `seq2` filling only cells with `|j - i| <= band`.
+///
+/// Worth it when the two sequences are known to be roughly in register (e.g. a
+/// quote checked against the passage it was already located in). Check
+/// `band_clipped`: a flagged result should be rerun with a wider band.
+pub fn smith_waterman_banded<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    band: usize,
+) -> Result<BandedAlignment, AlignError> {
+    let constraints = Constraints {
+        band: Some(band),
+        ..Constraints::default()
+    };
+    let (alignment, path) = smith_waterman_path(seq1, seq2, scoring, &constraints)?;
+    let on_edge = |i: usize, j: usize| {
+        (j.checked_sub(i) == Some(band) && j < seq2.len())
+            || (i.checked_sub(j) == Some(band) && i < seq1.len())
+    };
+    let (mut i, mut j) = (alignment.query_start, alignment.token_start);
+    let mut band_clipped = false;
+    for step in path {
+        match step {
+            Step::Diagonal => (i, j) = (i + 1, j + 1),
+            Step::Up => i += 1,
+            Step::Left => j += 1,
+        }
+        band_clipped |= on_edge(i, j);
+    }
+    Ok(BandedAlignment {
+        alignment,
+        band_clipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn narrow_band_flags_a_path_along_its_edge() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Matches sit on the diagonal j - i = 1, then a reference insertion
+        // pushes the tail onto j - i = 2.
+        let seq1 = vec![1, 2, 3, 4, 5, 6];
+        let seq2 = vec![9, 1, 2, 3, 8, 4, 5, 6, 9];
+        let full = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!(full.score, 11);
+
+        let narrow = smith_waterman_banded(&seq1, &seq2, &params, 1).unwrap();
+        assert!(narrow.band_clipped);
+        assert!(narrow.alignment.score < full.score);
+
+        let wide = smith_waterman_banded(&seq1, &seq2, &params, 4).unwrap();
+        assert!(!wide.band_clipped);
+        assert_eq!(
+            (
+                wide.alignment.score,
+                wide.alignment.token_start,
+                wide.alignment.token_end
+            ),
+            (full.score, full.token_start, full.token_end)
+        );
+    }
+
+    #[test]
+    fn unbounded_band_equals_the_unbanded_dp() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4, 5, 6];
+        let seq2 = vec![9, 1, 2, 3, 8, 4, 5, 6, 9];
+        let full = smith_waterman(&seq1, &seq2, params).unwrap();
+        for band in [usize::MAX, usize::MAX / 2, isize::MAX as usize + 1] {
+            let banded = smith_waterman_banded(&seq1, &seq2, &params, band).unwrap();
+            assert!(!banded.band_clipped);
+            assert_eq!(
+                (
+                    banded.alignment.score,
+                    banded.alignment.token_start,
+                    banded.alignment.token_end
+                ),
+                (full.score, full.token_start, full.token_end)
+            );
+        }
+    }
+}
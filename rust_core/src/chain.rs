@@ -0,0 +1,241 @@
+//! Approximate local alignment of very long sequences: exact seed matches
+//! are chained collinearly and only the gaps between them are aligned.
+
+use std::collections::HashMap;
+
+use crate::error::AlignError;
+use crate::global::align_global;
+use crate::smith_waterman::{Alignment, Scoring, check_score_range, empty_alignment};
+
+/// A maximal exact match: `seq1[query_start..][..len]` equals
+/// `seq2[token_start..][..len]`, and neither extends it by one more token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Anchor {
+    query_start: usize,
+    token_start: usize,
+    len: usize,
+}
+
+impl Anchor {
+    fn query_end(&self) -> usize {
+        self.query_start + self.len
+    }
+
+    fn token_end(&self) -> usize {
+        self.token_start + self.len
+    }
+}
+
+/// Approximate `smith_waterman` for inputs too long for any full or banded
+/// DP, such as a quote located in a whole book.
+///
+/// Every run of at least `seed_len` identical tokens is an anchor. Anchors
+/// are chained in increasing order on both sequences, each link spanning at
+/// most `max_gap` tokens of either one; a link is estimated as mismatches
+/// along its shorter side plus gaps for the difference, and a chain restarts
+/// wherever that estimate would lose score. The best chain is then aligned
+/// exactly: anchors as runs of matches, the region between two anchors by a
+/// global DP of at most `max_gap` by `max_gap` cells.
+///
+/// This is a heuristic. The result starts at the first anchor and ends at the
+/// last, so it is not extended past them, and a match with no run of
+/// `seed_len` identical tokens is not found at all (the result is then empty).
+/// Seeds compare ids exactly, so wildcards and equivalences only count inside
+/// the gaps. The score is never below the chain's estimate, and never above
+/// `smith_waterman`. Pick `seed_len` long enough that seeds are rare: a common
+/// run produces a hit at each of its occurrences. `run_bonus`,
+/// `end_gap_discount` and affine gaps are not supported.
+pub fn align_pair_chained<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    seed_len: usize,
+    max_gap: usize,
+) -> Result<Alignment, AlignError> {
+    if seed_len == 0 {
+        return Err(AlignError::InvalidArgument(
+            "seed_len must be at least 1".to_string(),
+        ));
+    }
+    let params = scoring.params();
+    if params.run_bonus != 0 || params.end_gap_discount != 0 || params.is_affine() {
+        return Err(AlignError::InvalidParams(
+            "run_bonus, end_gap_discount and gap_open are not supported for chained alignment"
+                .to_string(),
+        ));
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    let anchors = find_anchors(seq1, seq2, seed_len);
+    let chain = best_chain(&anchors, seq1, scoring, max_gap);
+    let (Some(&first), Some(&last)) = (chain.first(), chain.last()) else {
+        return Ok(empty_alignment());
+    };
+
+    let mut alignment = Alignment {
+        score: 0,
+        query_start: anchors[first].query_start,
+        query_end: anchors[last].query_end(),
+        token_start: anchors[first].token_start,
+        token_end: anchors[last].token_end(),
+        matches: 0,
+    };
+    let mut previous: Option<Anchor> = None;
+    for &index in &chain {
+        let anchor = anchors[index];
+        if let Some(previous) = previous {
+            let (score, matches, _) = align_global(
+                &seq1[previous.query_end()..anchor.query_start],
+                &seq2[previous.token_end()..anchor.token_start],
+                scoring,
+            );
+            alignment.score += score;
+            alignment.matches += matches;
+        }
+        alignment.score += anchor_score(anchor, seq1, scoring);
+        alignment.matches += anchor.len;
+        previous = Some(anchor);
+    }
+    Ok(alignment)
+}
+
+/// Maximal exact matches of at least `seed_len` tokens, sorted by query and
+/// then reference start.
+fn find_anchors(seq1: &[u32], seq2: &[u32], seed_len: usize) -> Vec<Anchor> {
+    if seq1.len() < seed_len || seq2.len() < seed_len {
+        return Vec::new();
+    }
+    let mut seeds: HashMap<&[u32], Vec<usize>> = HashMap::new();
+    for (i, seed) in seq1.windows(seed_len).enumerate() {
+        seeds.entry(seed).or_default().push(i);
+    }
+    let mut anchors = Vec::new();
+    for (j, seed) in seq2.windows(seed_len).enumerate() {
+        for &i in seeds.get(seed).into_iter().flatten() {
+            // Only the leftmost seed of a run starts an anchor.
+            if i > 0 && j > 0 && seq1[i - 1] == seq2[j - 1] {
+                continue;
+            }
+            let len = seed_len
+                + seq1[i + seed_len..]
+                    .iter()
+                    .zip(&seq2[j + seed_len..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            anchors.push(Anchor {
+                query_start: i,
+                token_start: j,
+                len,
+            });
+        }
+    }
+    anchors.sort_by_key(|anchor| (anchor.query_start, anchor.token_start));
+    anchors
+}
+
+fn anchor_score<S: Scoring>(anchor: Anchor, seq1: &[u32], scoring: &S) -> i32 {
+    seq1[anchor.query_start..anchor.query_end()]
+        .iter()
+        .map(|&token| scoring.substitution(token, token))
+        .sum()
+}
+
+/// Indices into `anchors` of the highest-scoring collinear chain, in order.
+/// Ties keep the earliest predecessor and the earliest chain end.
+fn best_chain<S: Scoring>(
+    anchors: &[Anchor],
+    seq1: &[u32],
+    scoring: &S,
+    max_gap: usize,
+) -> Vec<usize> {
+    let params = scoring.params();
+    let link = |from: &Anchor, to: &Anchor| {
+        let query_gap = to.query_start - from.query_end();
+        let token_gap = to.token_start - from.token_end();
+        let (pairs, query_extra, token_extra) = (
+            query_gap.min(token_gap) as i32,
+            query_gap.saturating_sub(token_gap) as i32,
+            token_gap.saturating_sub(query_gap) as i32,
+        );
+        pairs * params.mismatch_score
+            + query_extra * params.gap_query
+            + token_extra * params.gap_ref
+    };
+
+    // Every predecessor ends at or before an anchor's start, so it starts
+    // strictly earlier and is already scored in `anchors` order.
+    let mut by_end: Vec<usize> = (0..anchors.len()).collect();
+    by_end.sort_by_key(|&index| anchors[index].query_end());
+    let mut scores = vec![0i32; anchors.len()];
+    let mut predecessors: Vec<Option<usize>> = vec![None; anchors.len()];
+    for (index, anchor) in anchors.iter().enumerate() {
+        let lo = by_end.partition_point(|&b| {
+            anchors[b].query_end() < anchor.query_start.saturating_sub(max_gap)
+        });
+        let hi = by_end.partition_point(|&b| anchors[b].query_end() <= anchor.query_start);
+        let mut best = (0, None);
+        for &from in &by_end[lo..hi] {
+            let before = &anchors[from];
+            let fits = before.token_end() <= anchor.token_start
+                && anchor.token_start - before.token_end() <= max_gap;
+            if !fits {
+                continue;
+            }
+            let carried = scores[from] + link(before, anchor);
+            if carried > best.0 {
+                best = (carried, Some(from));
+            }
+        }
+        scores[index] = best.0 + anchor_score(*anchor, seq1, scoring);
+        predecessors[index] = best.1;
+    }
+
+    let Some(mut end) =
+        (0..anchors.len()).max_by(|&a, &b| scores[a].cmp(&scores[b]).then(b.cmp(&a)))
+    else {
+        return Vec::new();
+    };
+    let mut chain = vec![end];
+    while let Some(previous) = predecessors[end] {
+        chain.push(previous);
+        end = previous;
+    }
+    chain.reverse();
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn chained_alignment_recovers_an_embedded_quote() {
+        let params = ScoreParams::new(2, -1, -1);
+        // A 60-token quote with one substitution and one deleted token.
+        let quote: Vec<u32> = (1..=60).collect();
+        let mut cited = quote.clone();
+        cited[20] = 999;
+        cited.remove(40);
+        // Embedded at 5_000 in a 10_000-token text of unrelated ids.
+        let mut text: Vec<u32> = (10_000..15_000).collect();
+        text.extend(&cited);
+        text.extend(20_000..25_000);
+
+        let chained = align_pair_chained(&quote, &text, &params, 8, 32).unwrap();
+        let exact = smith_waterman(&quote, &text, params).unwrap();
+        assert_eq!(
+            (chained.token_start, chained.token_end),
+            (5_000, 5_000 + cited.len())
+        );
+        assert_eq!((chained.query_start, chained.query_end), (0, 60));
+        assert_eq!(
+            (chained.score, chained.matches),
+            (exact.score, exact.matches)
+        );
+
+        // No run of 70 identical tokens exists, so nothing is found.
+        let missed = align_pair_chained(&quote, &text, &params, 70, 32).unwrap();
+        assert_eq!((missed.score, missed.matches, missed.token_end), (0, 0, 0));
+        assert!(align_pair_chained(&quote, &text, &params, 0, 32).is_err());
+    }
+}
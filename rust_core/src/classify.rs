@@ -0,0 +1,166 @@
+//! Categorical labels for how well a query matches a reference, so every
+//! caller draws the Exact/Strong/Partial lines the same way.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Alignment, Constraints, Scoring, smith_waterman_with};
+
+/// How well a query matches, from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchLabel {
+    /// The whole query appears verbatim: every token matched, no gaps.
+    Exact,
+    /// Passes the strong identity and coverage cutoffs.
+    Strong,
+    /// Passes the partial coverage cutoff only.
+    Partial,
+    /// Scores below `min_score` or covers too little of the query.
+    None,
+}
+
+impl MatchLabel {
+    /// Lowercase name, e.g. `"strong"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Strong => "strong",
+            Self::Partial => "partial",
+            Self::None => "none",
+        }
+    }
+}
+
+/// Cutoffs for `MatchThresholds::label`. Every comparison is inclusive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchThresholds {
+    /// Smallest score of any labelled match; lower is `None`.
+    pub min_score: i32,
+    /// Smallest percent identity (as `CandidateAlignment::percent_identity`,
+    /// `0.0..=100.0`) of a `Strong` match.
+    pub strong_identity: f64,
+    /// Smallest query coverage (`Alignment::query_coverage`) of a `Strong`
+    /// match.
+    pub strong_coverage: f64,
+    /// Smallest query coverage of a `Partial` match.
+    pub partial_coverage: f64,
+}
+
+impl Default for MatchThresholds {
+    fn default() -> Self {
+        Self {
+            min_score: 1,
+            strong_identity: 90.0,
+            strong_coverage: 0.8,
+            partial_coverage: 0.3,
+        }
+    }
+}
+
+impl MatchThresholds {
+    pub fn validate(&self) -> Result<(), AlignError> {
+        if !(0.0..=100.0).contains(&self.strong_identity) {
+            return Err(AlignError::InvalidArgument(format!(
+                "strong_identity must be between 0 and 100, got {}",
+                self.strong_identity
+            )));
+        }
+        for (name, coverage) in [
+            ("strong_coverage", self.strong_coverage),
+            ("partial_coverage", self.partial_coverage),
+        ] {
+            if !(0.0..=1.0).contains(&coverage) {
+                return Err(AlignError::InvalidArgument(format!(
+                    "{name} must be between 0 and 1, got {coverage}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Label of `alignment` for a query of `query_len` tokens.
+    ///
+    /// `Exact` needs no cutoff. Otherwise a match must reach `min_score`,
+    /// then `Strong` takes both strong cutoffs and `Partial` the partial
+    /// coverage; an empty alignment is always `None`.
+    pub fn label(&self, alignment: &Alignment, query_len: usize) -> MatchLabel {
+        let token_span = alignment.token_end - alignment.token_start;
+        if query_len > 0 && alignment.matches == query_len && token_span == query_len {
+            return MatchLabel::Exact;
+        }
+        if !alignment.is_match(0) || alignment.score < self.min_score {
+            return MatchLabel::None;
+        }
+        let span = (alignment.query_end - alignment.query_start).max(token_span);
+        let identity = 100.0 * alignment.matches as f64 / span as f64;
+        let coverage = alignment.query_coverage(query_len);
+        if identity >= self.strong_identity && coverage >= self.strong_coverage {
+            MatchLabel::Strong
+        } else if coverage >= self.partial_coverage {
+            MatchLabel::Partial
+        } else {
+            MatchLabel::None
+        }
+    }
+}
+
+/// The `smith_waterman_with` alignment of `query` in `reference` and its
+/// label under `thresholds`.
+pub fn classify_match<S: Scoring>(
+    query: &[u32],
+    reference: &[u32],
+    scoring: &S,
+    thresholds: &MatchThresholds,
+) -> Result<(MatchLabel, Alignment), AlignError> {
+    thresholds.validate()?;
+    let alignment = smith_waterman_with(query, reference, scoring, &Constraints::default())?;
+    Ok((thresholds.label(&alignment, query.len()), alignment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::ScoreParams;
+
+    #[test]
+    fn labels_follow_the_threshold_boundaries() {
+        let params = ScoreParams::new(2, -1, -1);
+        let thresholds = MatchThresholds::default();
+        let query: Vec<u32> = (1..=10).collect();
+        let label = |reference: &[u32], thresholds: &MatchThresholds| {
+            classify_match(&query, reference, &params, thresholds)
+                .unwrap()
+                .0
+        };
+
+        let embedded: Vec<u32> = [50].into_iter().chain(1..=10).chain([51]).collect();
+        assert_eq!(label(&embedded, &thresholds), MatchLabel::Exact);
+
+        // One substitution: 90% identity and 0.9 coverage, exactly strong.
+        let mut substituted = query.clone();
+        substituted[5] = 99;
+        assert_eq!(label(&substituted, &thresholds), MatchLabel::Strong);
+        let stricter = MatchThresholds {
+            strong_identity: 91.0,
+            ..thresholds
+        };
+        assert_eq!(label(&substituted, &stricter), MatchLabel::Partial);
+
+        // Three of ten query tokens is exactly the partial coverage; two is
+        // below it.
+        assert_eq!(label(&[1, 2, 3], &thresholds), MatchLabel::Partial);
+        assert_eq!(label(&[1, 2], &thresholds), MatchLabel::None);
+        let high_floor = MatchThresholds {
+            min_score: 7,
+            ..thresholds
+        };
+        assert_eq!(label(&[1, 2, 3], &high_floor), MatchLabel::None);
+
+        let (disjoint, alignment) =
+            classify_match(&query, &[20, 21, 22], &params, &thresholds).unwrap();
+        assert_eq!((disjoint, alignment.score), (MatchLabel::None, 0));
+        let invalid = MatchThresholds {
+            partial_coverage: 1.5,
+            ..thresholds
+        };
+        assert!(classify_match(&query, &query, &params, &invalid).is_err());
+    }
+}
@@ -0,0 +1,566 @@
+//! Alignment options bundled into one value, so new knobs don't widen every
+//! function signature.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::error::AlignError;
+use crate::prefix::smith_waterman_query_start;
+use crate::restart::smith_waterman_restart_floor;
+use crate::scoring::{CrossVocabularyScoring, EquivalenceScoring, HammingScoring, NumericScoring};
+use crate::smith_waterman::{
+    Alignment, CandidateAlignment, Constraints, ScoreParams, Scoring, TieBreak, align_reversed,
+    cmp_candidate, empty_alignment, smith_waterman_first_max, smith_waterman_tie_break,
+};
+use crate::tokenize::canonicalize;
+use crate::{GapScore, score_params};
+
+/// Scoring scheme plus search and filtering options for `align_pair` and
+/// `align_topk`. The default reproduces the plain positional calls.
+#[pyclass(name = "AlignConfig", frozen)]
+#[derive(Clone)]
+pub struct AlignConfig {
+    pub params: ScoreParams,
+    /// Restricts the DP to cells within `band` of the main diagonal.
+    #[pyo3(get)]
+    pub band: Option<usize>,
+    /// Half-open reference intervals no alignment may pass through (see
+    /// `Constraints::mask`).
+    pub mask: Vec<(usize, usize)>,
+    /// Alignments scoring below this are discarded.
+    #[pyo3(get)]
+    pub min_score: i32,
+    /// Alignments with fewer exact matches than this are discarded.
+    #[pyo3(get)]
+    pub min_matches: usize,
+    /// Worker count for corpus calls; `None` uses the global rayon pool.
+    #[pyo3(get)]
+    pub num_threads: Option<usize>,
+    /// Break score ties "first found" (see `smith_waterman_first_max`).
+    #[pyo3(get)]
+    pub first_max_only: bool,
+    /// Maps each token id to its canonical id before aligning, so variants
+    /// sharing an entry match (see `tokenize::canonicalize`).
+    pub canonical: Option<Vec<u32>>,
+    /// Token ids matched as equal to the id they map to (see
+    /// `EquivalenceScoring`).
+    pub equiv: Option<HashMap<u32, u32>>,
+    /// Numbers at most this far apart match (see `NumericScoring`); 0 keeps
+    /// exact matching.
+    #[pyo3(get)]
+    pub numeric_tolerance: u32,
+    /// Draws the end cell among tied maxima with this seed (see
+    /// `TieBreak::Random`).
+    #[pyo3(get)]
+    pub tie_seed: Option<u64>,
+    /// Aligns both sequences back to front (see `align_reversed`).
+    #[pyo3(get)]
+    pub reverse: bool,
+    /// Codes at most this many bits apart match (see `HammingScoring`).
+    #[pyo3(get)]
+    pub hamming_threshold: u32,
+    /// Query ids translated to reference ids before the match test (see
+    /// `CrossVocabularyScoring`).
+    pub cross_map: Option<HashMap<u32, u32>>,
+    /// Anchors the alignment at the query's first token (see
+    /// `smith_waterman_query_start`).
+    #[pyo3(get)]
+    pub require_query_start_zero: bool,
+    /// Holds a path that would reset at this score instead (see
+    /// `smith_waterman_restart_floor`); 0 disables it.
+    #[pyo3(get)]
+    pub restart_floor: i32,
+    /// Reports `token_end` and `query_end` as the last aligned tokens rather
+    /// than one past them; the binding applies it.
+    #[pyo3(get)]
+    pub inclusive_ends: bool,
+    /// Releases the GIL while aligning; the binding applies it.
+    #[pyo3(get)]
+    pub release_gil: bool,
+}
+
+impl Default for AlignConfig {
+    fn default() -> Self {
+        Self {
+            params: ScoreParams::default(),
+            band: None,
+            mask: Vec::new(),
+            min_score: 0,
+            min_matches: 0,
+            num_threads: None,
+            first_max_only: false,
+            canonical: None,
+            equiv: None,
+            numeric_tolerance: 0,
+            tie_seed: None,
+            reverse: false,
+            hamming_threshold: 0,
+            cross_map: None,
+            require_query_start_zero: false,
+            restart_floor: 0,
+            inclusive_ends: false,
+            release_gil: true,
+        }
+    }
+}
+
+impl AlignConfig {
+    /// Rejects option values and combinations the DP cannot honour.
+    pub fn validate(&self) -> Result<(), AlignError> {
+        if self.num_threads == Some(0) {
+            return Err(AlignError::InvalidArgument(
+                "num_threads must be at least 1".to_string(),
+            ));
+        }
+        let rules = [
+            self.equiv.as_ref().is_some_and(|map| !map.is_empty()),
+            self.numeric_tolerance > 0,
+            self.hamming_threshold > 0,
+            self.cross_map.as_ref().is_some_and(|map| !map.is_empty()),
+        ];
+        if rules.iter().filter(|&&set| set).count() > 1 {
+            return Err(AlignError::InvalidArgument(
+                "equiv, numeric_tolerance, hamming_threshold and cross_map cannot be combined"
+                    .to_string(),
+            ));
+        }
+        if self.tie_seed.is_some() && self.first_max_only {
+            return Err(AlignError::InvalidArgument(
+                "tie_seed and first_max_only cannot be combined".to_string(),
+            ));
+        }
+        if self.restart_floor < 0 {
+            return Err(AlignError::InvalidArgument(format!(
+                "restart_floor must be zero or positive, got {}",
+                self.restart_floor
+            )));
+        }
+        if self.require_query_start_zero && self.restart_floor != 0 {
+            return Err(AlignError::InvalidArgument(
+                "require_query_start_zero and restart_floor cannot be combined".to_string(),
+            ));
+        }
+        let own_dp = self.require_query_start_zero || self.restart_floor != 0;
+        if own_dp
+            && (self.band.is_some()
+                || !self.mask.is_empty()
+                || self.first_max_only
+                || self.tie_seed.is_some()
+                || self.reverse)
+        {
+            return Err(AlignError::InvalidArgument(
+                "require_query_start_zero and restart_floor cannot be combined with band, mask, first_max_only, tie_seed or reverse"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn constraints(&self) -> Constraints {
+        Constraints {
+            mask: self.mask.clone(),
+            band: self.band,
+            ..Constraints::default()
+        }
+    }
+
+    fn accepts(&self, score: i32, matches: usize) -> bool {
+        score >= self.min_score && matches >= self.min_matches
+    }
+
+    fn align(
+        &self,
+        seq1: &[u32],
+        seq2: &[u32],
+        constraints: &Constraints,
+    ) -> Result<Alignment, AlignError> {
+        let (seq1, seq2) = match &self.canonical {
+            Some(canonical) => (
+                Cow::Owned(canonicalize(seq1, canonical)),
+                Cow::Owned(canonicalize(seq2, canonical)),
+            ),
+            None => (Cow::Borrowed(seq1), Cow::Borrowed(seq2)),
+        };
+        let align = |seq1: &[u32], seq2: &[u32], constraints: &Constraints| {
+            if let Some(equiv) = self.equiv.as_ref().filter(|map| !map.is_empty()) {
+                let scoring = EquivalenceScoring {
+                    params: self.params,
+                    equiv,
+                };
+                return self.align_with(seq1, seq2, &scoring, constraints);
+            }
+            if self.numeric_tolerance > 0 {
+                let scoring = NumericScoring {
+                    params: self.params,
+                    tolerance: self.numeric_tolerance,
+                };
+                return self.align_with(seq1, seq2, &scoring, constraints);
+            }
+            if self.hamming_threshold > 0 {
+                let scoring = HammingScoring {
+                    params: self.params,
+                    threshold: self.hamming_threshold,
+                };
+                return self.align_with(seq1, seq2, &scoring, constraints);
+            }
+            if let Some(cross_map) = self.cross_map.as_ref().filter(|map| !map.is_empty()) {
+                let scoring = CrossVocabularyScoring {
+                    params: self.params,
+                    cross_map,
+                };
+                return self.align_with(seq1, seq2, &scoring, constraints);
+            }
+            self.align_with(seq1, seq2, &self.params, constraints)
+        };
+        if self.reverse {
+            return align_reversed(&seq1, &seq2, constraints, align);
+        }
+        align(&seq1, &seq2, constraints)
+    }
+
+    /// Runs the DP the options select under `scoring`.
+    fn align_with<S: Scoring>(
+        &self,
+        seq1: &[u32],
+        seq2: &[u32],
+        scoring: &S,
+        constraints: &Constraints,
+    ) -> Result<Alignment, AlignError> {
+        if self.require_query_start_zero {
+            return smith_waterman_query_start(seq1, seq2, scoring);
+        }
+        if self.restart_floor != 0 {
+            return smith_waterman_restart_floor(seq1, seq2, scoring, self.restart_floor);
+        }
+        if self.first_max_only {
+            return smith_waterman_first_max(seq1, seq2, scoring, constraints);
+        }
+        let tie_break = self.tie_seed.map_or(TieBreak::Canonical, TieBreak::Random);
+        smith_waterman_tie_break(seq1, seq2, scoring, constraints, tie_break)
+    }
+
+    /// Best alignment of `seq1` in `seq2`, or an all-zero alignment when it
+    /// fails `min_score` or `min_matches`.
+    pub fn align_pair(&self, seq1: &[u32], seq2: &[u32]) -> Result<Alignment, AlignError> {
+        self.validate()?;
+        let alignment = self.align(seq1, seq2, &self.constraints())?;
+        if !self.accepts(alignment.score, alignment.matches) {
+            return Ok(empty_alignment());
+        }
+        Ok(alignment)
+    }
+
+    /// Top `top_k` references ordered like `align_topk`, leaving out every
+    /// reference whose best alignment fails `min_score` or `min_matches`.
+    ///
+    /// Runs on the caller's rayon pool; `num_threads` is applied by the binding.
+    pub fn align_topk(
+        &self,
+        seq1: &[u32],
+        seqs: &[Vec<u32>],
+        top_k: usize,
+    ) -> Result<Vec<CandidateAlignment>, AlignError> {
+        self.validate()?;
+        if seqs.is_empty() || top_k == 0 {
+            return Ok(Vec::new());
+        }
+        let constraints = self.constraints();
+        let mut results: Vec<CandidateAlignment> = seqs
+            .par_iter()
+            .enumerate()
+            .map(|(index, seq2)| {
+                let alignment = self.align(seq1, seq2, &constraints)?;
+                Ok(CandidateAlignment::from_alignment(index, &alignment))
+            })
+            .collect::<Result<Vec<_>, AlignError>>()?
+            .into_iter()
+            .filter(|item| self.accepts(item.score, item.matches))
+            .collect();
+        results.sort_by(cmp_candidate);
+        results.truncate(top_k);
+        Ok(results)
+    }
+}
+
+#[pymethods]
+impl AlignConfig {
+    #[new]
+    #[pyo3(signature = (match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, separator=None, band=None, min_score=0, min_matches=0, num_threads=None, first_max_only=false, canonical=None, numeric_tolerance=0, tie_seed=None, reverse=false, hamming_threshold=0, cross_map=None, require_query_start_zero=false, restart_floor=0, mask=None, equiv=None, inclusive_ends=false, release_gil=true))]
+    fn py_new(
+        py: Python<'_>,
+        match_score: i32,
+        mismatch_score: i32,
+        gap_score: GapScore,
+        wildcard: Option<u32>,
+        gap_query: Option<i32>,
+        gap_ref: Option<i32>,
+        run_bonus: i32,
+        end_gap_discount: i32,
+        separator: Option<u32>,
+        band: Option<usize>,
+        min_score: i32,
+        min_matches: usize,
+        num_threads: Option<usize>,
+        first_max_only: bool,
+        canonical: Option<Vec<u32>>,
+        numeric_tolerance: u32,
+        tie_seed: Option<u64>,
+        reverse: bool,
+        hamming_threshold: u32,
+        cross_map: Option<HashMap<u32, u32>>,
+        require_query_start_zero: bool,
+        restart_floor: i32,
+        mask: Option<Vec<(usize, usize)>>,
+        equiv: Option<HashMap<u32, u32>>,
+        inclusive_ends: bool,
+        release_gil: bool,
+    ) -> PyResult<Self> {
+        let mut params = score_params(
+            py,
+            match_score,
+            mismatch_score,
+            gap_score,
+            wildcard,
+            gap_query,
+            gap_ref,
+            run_bonus,
+            end_gap_discount,
+        )?;
+        params.separator = separator;
+        let config = Self {
+            params,
+            band,
+            mask: mask.unwrap_or_default(),
+            min_score,
+            min_matches,
+            num_threads,
+            first_max_only,
+            canonical,
+            equiv,
+            numeric_tolerance,
+            tie_seed,
+            reverse,
+            hamming_threshold,
+            cross_map,
+            require_query_start_zero,
+            restart_floor,
+            inclusive_ends,
+            release_gil,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::banded::smith_waterman_banded;
+    use crate::smith_waterman::{align_topk, smith_waterman, smith_waterman_with};
+
+    #[test]
+    fn config_matches_equivalent_direct_calls() {
+        let params = ScoreParams::new(3, -1, -2);
+        let seq1 = vec![1, 2, 3, 4];
+        let seqs = vec![
+            vec![1, 2, 3, 4],
+            vec![9, 1, 2, 9],
+            vec![7, 7],
+            vec![1, 2, 9, 4],
+        ];
+
+        let plain = AlignConfig {
+            params,
+            ..AlignConfig::default()
+        };
+        assert_eq!(
+            plain.align_topk(&seq1, &seqs, 4).unwrap(),
+            align_topk(&seq1, &seqs, params, 4).unwrap()
+        );
+
+        let banded = AlignConfig {
+            band: Some(1),
+            ..plain.clone()
+        };
+        let expected = smith_waterman_banded(&seq1, &seqs[1], &params, 1).unwrap();
+        assert_eq!(
+            banded.align_pair(&seq1, &seqs[1]).unwrap().score,
+            expected.alignment.score
+        );
+
+        let filtered = AlignConfig {
+            min_score: 7,
+            min_matches: 3,
+            ..plain
+        };
+        let kept: Vec<usize> = filtered
+            .align_topk(&seq1, &seqs, 4)
+            .unwrap()
+            .iter()
+            .map(|item| item.index)
+            .collect();
+        assert_eq!(kept, vec![0, 3]);
+        assert_eq!(smith_waterman(&seq1, &seqs[1], params).unwrap().score, 6);
+        assert_eq!(filtered.align_pair(&seq1, &seqs[1]).unwrap().score, 0);
+    }
+
+    #[test]
+    fn config_options_select_the_matching_dp() {
+        let params = ScoreParams::new(2, -1, -1);
+        let plain = AlignConfig {
+            params,
+            ..AlignConfig::default()
+        };
+        let seq1 = vec![1, 1999, 3];
+        let seq2 = vec![7, 1, 2000, 3, 8];
+
+        let numeric = AlignConfig {
+            numeric_tolerance: 2,
+            ..plain.clone()
+        };
+        let scoring = NumericScoring {
+            params,
+            tolerance: 2,
+        };
+        let expected =
+            smith_waterman_with(&seq1, &seq2, &scoring, &Constraints::default()).unwrap();
+        let got = numeric.align_pair(&seq1, &seq2).unwrap();
+        assert_eq!(
+            (got.score, got.token_start, got.matches),
+            (expected.score, expected.token_start, expected.matches)
+        );
+        assert!(got.score > plain.align_pair(&seq1, &seq2).unwrap().score);
+
+        let seeded = AlignConfig {
+            tie_seed: Some(7),
+            ..plain.clone()
+        };
+        let tied = smith_waterman_tie_break(
+            &[1, 2],
+            &[1, 2, 9, 1, 2],
+            &params,
+            &Constraints::default(),
+            TieBreak::Random(7),
+        )
+        .unwrap();
+        assert_eq!(
+            seeded
+                .align_pair(&[1, 2], &[1, 2, 9, 1, 2])
+                .unwrap()
+                .token_start,
+            tied.token_start
+        );
+        let reversed = AlignConfig {
+            reverse: true,
+            ..plain.clone()
+        };
+        let last = reversed.align_pair(&[1, 2], &[1, 2, 9, 1, 2]).unwrap();
+        assert_eq!((last.score, last.token_start, last.token_end), (4, 3, 5));
+
+        let hamming = AlignConfig {
+            hamming_threshold: 1,
+            ..plain.clone()
+        };
+        let near = hamming
+            .align_pair(&[0b1010, 0b0110], &[0b1011, 0b0100])
+            .unwrap();
+        assert_eq!((near.score, near.matches), (4, 2));
+
+        let cross_map = HashMap::from([(100, 1), (101, 2)]);
+        let mapped = AlignConfig {
+            cross_map: Some(cross_map.clone()),
+            ..plain.clone()
+        };
+        let got = mapped.align_pair(&[100, 101, 3], &[7, 1, 2, 3]).unwrap();
+        assert_eq!((got.score, got.token_start, got.matches), (6, 1, 3));
+        let equivalent = AlignConfig {
+            equiv: Some(HashMap::from([(100, 1), (101, 2)])),
+            ..plain.clone()
+        };
+        let got = equivalent
+            .align_pair(&[1, 2, 3], &[7, 100, 101, 3])
+            .unwrap();
+        assert_eq!((got.score, got.token_start), (6, 1));
+        let masked = AlignConfig {
+            mask: vec![(0, 3)],
+            ..plain.clone()
+        };
+        let got = masked.align_pair(&[1, 2], &[1, 2, 9, 1, 2]).unwrap();
+        assert_eq!((got.score, got.token_start), (4, 3));
+
+        let (seq1, seq2) = (vec![9, 1, 2, 3, 4, 5], vec![7, 1, 2, 3, 4, 5, 9, 8]);
+        let anchored = AlignConfig {
+            require_query_start_zero: true,
+            ..plain.clone()
+        };
+        let got = anchored.align_pair(&seq1, &seq2).unwrap();
+        let expected = smith_waterman_query_start(&seq1, &seq2, &params).unwrap();
+        assert_eq!((got.score, got.query_start), (expected.score, 0));
+        let floored = AlignConfig {
+            restart_floor: 2,
+            ..plain.clone()
+        };
+        assert_eq!(
+            floored.align_pair(&seq1, &seq2).unwrap().score,
+            smith_waterman_restart_floor(&seq1, &seq2, &params, 2)
+                .unwrap()
+                .score
+        );
+
+        for invalid in [
+            AlignConfig {
+                num_threads: Some(0),
+                ..plain.clone()
+            },
+            AlignConfig {
+                numeric_tolerance: 1,
+                hamming_threshold: 1,
+                ..plain.clone()
+            },
+            AlignConfig {
+                equiv: Some(HashMap::from([(1, 2)])),
+                numeric_tolerance: 1,
+                ..plain.clone()
+            },
+            AlignConfig {
+                restart_floor: 1,
+                mask: vec![(0, 1)],
+                ..plain.clone()
+            },
+            AlignConfig {
+                hamming_threshold: 1,
+                cross_map: Some(cross_map),
+                ..plain.clone()
+            },
+            AlignConfig {
+                require_query_start_zero: true,
+                reverse: true,
+                ..plain.clone()
+            },
+            AlignConfig {
+                require_query_start_zero: true,
+                restart_floor: 1,
+                ..plain.clone()
+            },
+            AlignConfig {
+                restart_floor: 1,
+                band: Some(2),
+                ..plain.clone()
+            },
+            AlignConfig {
+                restart_floor: -1,
+                ..plain.clone()
+            },
+            AlignConfig {
+                tie_seed: Some(1),
+                first_max_only: true,
+                ..plain
+            },
+        ] {
+            assert!(invalid.align_pair(&seq1, &seq2).is_err());
+        }
+    }
+}
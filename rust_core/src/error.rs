@@ -0,0 +1,50 @@
+use pyo3::PyErr;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use std::fmt;
+
+/// Failures surfaced by the alignment routines instead of panicking across the
+/// FFI boundary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AlignError {
+    /// The scoring scheme and input lengths could push a cell past `i32::MAX`.
+    ScoreOverflow,
+    /// The scoring scheme cannot produce meaningful alignments.
+    InvalidParams(String),
+    /// A non-scoring argument is out of range.
+    InvalidArgument(String),
+    /// A scoped rayon thread pool could not be built.
+    ThreadPool(String),
+    /// An internal invariant was violated; indicates a bug rather than bad input.
+    Internal(&'static str),
+}
+
+impl fmt::Display for AlignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlignError::ScoreOverflow => {
+                write!(f, "alignment scores could overflow i32 for these inputs")
+            }
+            AlignError::InvalidParams(message) => {
+                write!(f, "invalid scoring parameters: {message}")
+            }
+            AlignError::InvalidArgument(message) => write!(f, "{message}"),
+            AlignError::ThreadPool(message) => write!(f, "failed to build thread pool: {message}"),
+            AlignError::Internal(message) => write!(f, "internal alignment error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for AlignError {}
+
+impl From<AlignError> for PyErr {
+    fn from(error: AlignError) -> Self {
+        match error {
+            AlignError::ScoreOverflow
+            | AlignError::InvalidParams(_)
+            | AlignError::InvalidArgument(_) => PyValueError::new_err(error.to_string()),
+            AlignError::ThreadPool(_) | AlignError::Internal(_) => {
+                PyRuntimeError::new_err(error.to_string())
+            }
+        }
+    }
+}
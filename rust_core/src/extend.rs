@@ -0,0 +1,281 @@
+//! Banded extension of a seed in one direction, for seed-and-extend search.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Scoring, check_score_range};
+
+/// The best-scoring extension from a seed boundary, in `seq1`/`seq2`
+/// coordinates (0-based, half-open).
+///
+/// For `extend_right` the span starts at the seed boundary; for `extend_left`
+/// it ends there. An extension that does not pay off is empty, with score 0
+/// and both spans of length zero at the boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Extension {
+    pub score: i32,
+    pub query_start: usize,
+    pub query_end: usize,
+    pub token_start: usize,
+    pub token_end: usize,
+}
+
+/// Extends rightwards from the cell before `seq1[query_pos]` and
+/// `seq2[token_pos]`, e.g. from the end of a seed.
+///
+/// The extension is anchored at that cell (it may gain or lose score along
+/// the way) and ends wherever the running score is highest. Only cells within
+/// `band` of the anchor's diagonal are filled. `run_bonus`,
+/// `end_gap_discount` and affine gaps are not supported.
+pub fn extend_right<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    query_pos: usize,
+    token_pos: usize,
+    scoring: &S,
+    band: usize,
+) -> Result<Extension, AlignError> {
+    check_anchor(seq1, seq2, query_pos, token_pos, scoring)?;
+    let (score, query_len, token_len) =
+        extend(&seq1[query_pos..], &seq2[token_pos..], scoring, band)?;
+    Ok(Extension {
+        score,
+        query_start: query_pos,
+        query_end: query_pos + query_len,
+        token_start: token_pos,
+        token_end: token_pos + token_len,
+    })
+}
+
+/// Extends leftwards from the cell after `seq1[query_pos - 1]` and
+/// `seq2[token_pos - 1]`, e.g. from the start of a seed.
+///
+/// Runs `extend_right` over the reversed prefixes `seq1[..query_pos]` and
+/// `seq2[..token_pos]` and maps the result back, so the returned span ends at
+/// `(query_pos, token_pos)` and `*_start` is where the extension reached.
+pub fn extend_left<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    query_pos: usize,
+    token_pos: usize,
+    scoring: &S,
+    band: usize,
+) -> Result<Extension, AlignError> {
+    check_anchor(seq1, seq2, query_pos, token_pos, scoring)?;
+    let query: Vec<u32> = seq1[..query_pos].iter().rev().copied().collect();
+    let reference: Vec<u32> = seq2[..token_pos].iter().rev().copied().collect();
+    let (score, query_len, token_len) = extend(&query, &reference, scoring, band)?;
+    Ok(Extension {
+        score,
+        query_start: query_pos - query_len,
+        query_end: query_pos,
+        token_start: token_pos - token_len,
+        token_end: token_pos,
+    })
+}
+
+/// Best alignment forced through the cell pairing `seq1[query_pos]` with
+/// `seq2[token_pos]`, e.g. a unique identifier known to be shared.
+///
+/// Extends left from before the anchor and right from after it, then adds
+/// the anchor's own substitution score (which may be a mismatch), so the
+/// result always contains the anchor cell. Costs two banded extensions
+/// instead of a full DP; `band` is applied to each side.
+pub fn align_anchored<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    query_pos: usize,
+    token_pos: usize,
+    scoring: &S,
+    band: usize,
+) -> Result<Extension, AlignError> {
+    if query_pos >= seq1.len() || token_pos >= seq2.len() {
+        return Err(AlignError::InvalidArgument(format!(
+            "anchor ({query_pos}, {token_pos}) is not a cell of sequences of length ({}, {})",
+            seq1.len(),
+            seq2.len()
+        )));
+    }
+    let left = extend_left(seq1, seq2, query_pos, token_pos, scoring, band)?;
+    let right = extend_right(seq1, seq2, query_pos + 1, token_pos + 1, scoring, band)?;
+    Ok(Extension {
+        score: left.score + scoring.substitution(seq1[query_pos], seq2[token_pos]) + right.score,
+        query_start: left.query_start,
+        query_end: right.query_end,
+        token_start: left.token_start,
+        token_end: right.token_end,
+    })
+}
+
+fn check_anchor<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    query_pos: usize,
+    token_pos: usize,
+    scoring: &S,
+) -> Result<(), AlignError> {
+    if query_pos > seq1.len() || token_pos > seq2.len() {
+        return Err(AlignError::InvalidArgument(format!(
+            "anchor ({query_pos}, {token_pos}) is out of range for sequences of length ({}, {})",
+            seq1.len(),
+            seq2.len()
+        )));
+    }
+    let params = scoring.params();
+    if params.run_bonus != 0 || params.end_gap_discount != 0 || params.is_affine() {
+        return Err(AlignError::InvalidParams(
+            "run_bonus, end_gap_discount and gap_open are not supported for extension".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Anchored DP from `(0, 0)`: no reset to zero, cells off the band are
+/// unreachable. Returns the best score and the prefix lengths reaching it,
+/// preferring the first such cell in row-major order (the shortest query
+/// prefix).
+fn extend<S: Scoring>(
+    query: &[u32],
+    reference: &[u32],
+    scoring: &S,
+    band: usize,
+) -> Result<(i32, usize, usize), AlignError> {
+    check_score_range(query.len(), reference.len(), scoring)?;
+    let params = scoring.params();
+    let unreachable = i32::MIN / 2;
+    let cols = reference.len().min(query.len().saturating_add(band)) + 1;
+
+    let mut best = (0, 0, 0);
+    let mut previous = vec![unreachable; cols];
+    for (j, cell) in previous.iter_mut().enumerate().take(band.min(cols - 1) + 1) {
+        *cell = params.gap_ref.saturating_mul(j as i32).max(unreachable);
+    }
+    for (i, &token) in query.iter().enumerate() {
+        let i = i + 1;
+        let mut row = vec![unreachable; cols];
+        let first = i.saturating_sub(band);
+        let last = i.saturating_add(band).min(cols - 1);
+        for j in first..=last {
+            let mut score = unreachable;
+            if j > 0 && previous[j - 1] > unreachable {
+                score = score.max(
+                    previous[j - 1].saturating_add(scoring.substitution(token, reference[j - 1])),
+                );
+            }
+            if previous[j] > unreachable {
+                score = score.max(previous[j].saturating_add(params.gap_query));
+            }
+            if j > 0 && row[j - 1] > unreachable {
+                score = score.max(row[j - 1].saturating_add(params.gap_ref));
+            }
+            row[j] = score.max(unreachable);
+            if row[j] > best.0 {
+                best = (row[j], i, j);
+            }
+        }
+        previous = row;
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn extensions_from_a_seed_compose_into_the_full_alignment() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4, 5, 6, 7];
+        let seq2 = vec![9, 1, 2, 3, 4, 8, 5, 6, 7, 9];
+        // Seed: query token 4 at seq1[3] against seq2[4].
+        let (seed_query, seed_token, seed_score) = ((3, 4), (4, 5), 2);
+
+        let left = extend_left(&seq1, &seq2, seed_query.0, seed_token.0, &params, 2).unwrap();
+        assert_eq!(
+            left,
+            Extension {
+                score: 6,
+                query_start: 0,
+                query_end: 3,
+                token_start: 1,
+                token_end: 4,
+            }
+        );
+        let right = extend_right(&seq1, &seq2, seed_query.1, seed_token.1, &params, 2).unwrap();
+        assert_eq!(
+            right,
+            Extension {
+                score: 5,
+                query_start: 4,
+                query_end: 7,
+                token_start: 5,
+                token_end: 9,
+            }
+        );
+
+        let full = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!(left.score + seed_score + right.score, full.score);
+        assert_eq!(
+            (
+                left.query_start,
+                right.query_end,
+                left.token_start,
+                right.token_end
+            ),
+            (
+                full.query_start,
+                full.query_end,
+                full.token_start,
+                full.token_end
+            )
+        );
+
+        let stuck = extend_right(&seq1, &seq2, 7, 9, &params, 2).unwrap();
+        assert_eq!((stuck.score, stuck.query_end, stuck.token_end), (0, 7, 9));
+        assert!(extend_right(&seq1, &seq2, 8, 0, &params, 2).is_err());
+        // A band wider than any input is no band.
+        let unbounded = extend_right(
+            &seq1,
+            &seq2,
+            seed_query.1,
+            seed_token.1,
+            &params,
+            usize::MAX,
+        );
+        assert_eq!(unbounded.unwrap(), right);
+    }
+
+    #[test]
+    fn anchored_alignment_passes_through_the_anchor() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seq2 = vec![1, 2, 3, 4, 9, 5, 2, 6];
+        let full = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!((full.score, full.token_start, full.token_end), (8, 0, 4));
+
+        // The lone shared token far from the optimum.
+        let forced = align_anchored(&seq1, &seq2, 1, 6, &params, 16).unwrap();
+        assert_eq!(
+            forced,
+            Extension {
+                score: 2,
+                query_start: 1,
+                query_end: 2,
+                token_start: 6,
+                token_end: 7,
+            }
+        );
+
+        let on_optimum = align_anchored(&seq1, &seq2, 1, 1, &params, 16).unwrap();
+        assert_eq!(
+            (
+                on_optimum.score,
+                on_optimum.query_start,
+                on_optimum.query_end,
+                on_optimum.token_start,
+                on_optimum.token_end
+            ),
+            (8, 0, 4, 0, 4)
+        );
+        assert!(align_anchored(&seq1, &seq2, 4, 0, &params, 16).is_err());
+    }
+}
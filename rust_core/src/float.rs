@@ -0,0 +1,221 @@
+//! Smith-Waterman with `f64` scores, for fractional (e.g. log-odds) models.
+//!
+//! The recurrence matches the integer path; only score comparisons differ.
+//! Two scores within [`EPSILON`] (relative, for magnitudes above one) count as
+//! tied, so tie-breaking stays stable under rounding noise.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Alignment, cmp_alignment};
+
+pub const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Debug)]
+pub struct FloatParams {
+    pub match_score: f64,
+    pub mismatch_score: f64,
+    pub gap_query: f64,
+    pub gap_ref: f64,
+    /// Per-pair overrides of the match/mismatch score, keyed `(query, reference)`.
+    pub substitution: HashMap<(u32, u32), f64>,
+}
+
+impl FloatParams {
+    pub fn new(match_score: f64, mismatch_score: f64, gap_score: f64) -> Self {
+        Self {
+            match_score,
+            mismatch_score,
+            gap_query: gap_score,
+            gap_ref: gap_score,
+            substitution: HashMap::new(),
+        }
+    }
+
+    pub fn substitution(&self, a: u32, b: u32) -> f64 {
+        if let Some(&score) = self.substitution.get(&(a, b)) {
+            return score;
+        }
+        if a == b {
+            self.match_score
+        } else {
+            self.mismatch_score
+        }
+    }
+
+    /// Rejects NaN and infinite scores, which would poison every comparison.
+    pub fn validate(&self) -> Result<(), AlignError> {
+        let finite = [
+            self.match_score,
+            self.mismatch_score,
+            self.gap_query,
+            self.gap_ref,
+        ]
+        .into_iter()
+        .chain(self.substitution.values().copied())
+        .all(f64::is_finite);
+        if !finite {
+            return Err(AlignError::InvalidParams(
+                "scores must be finite".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FloatAlignment {
+    pub score: f64,
+    pub query_start: usize,
+    pub query_end: usize,
+    pub token_start: usize,
+    pub token_end: usize,
+    /// Diagonal steps over identical tokens.
+    pub matches: usize,
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() <= EPSILON * a.abs().max(b.abs()).max(1.0)
+}
+
+pub fn smith_waterman_f64(
+    seq1: &[u32],
+    seq2: &[u32],
+    params: &FloatParams,
+) -> Result<FloatAlignment, AlignError> {
+    params.validate()?;
+    let empty = FloatAlignment {
+        score: 0.0,
+        query_start: 0,
+        query_end: 0,
+        token_start: 0,
+        token_end: 0,
+        matches: 0,
+    };
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(empty);
+    }
+
+    let rows = seq1.len() + 1;
+    let cols = seq2.len() + 1;
+    let mut scores = vec![vec![0f64; cols]; rows];
+    let mut directions = vec![vec![0u8; cols]; rows];
+    let mut max_score = 0f64;
+    let mut max_positions: Vec<(usize, usize)> = Vec::new();
+
+    for i in 1..rows {
+        for j in 1..cols {
+            let score_diag = scores[i - 1][j - 1] + params.substitution(seq1[i - 1], seq2[j - 1]);
+            let score_up = scores[i - 1][j] + params.gap_query;
+            let score_left = scores[i][j - 1] + params.gap_ref;
+
+            let best = score_diag.max(score_up).max(score_left);
+            if best <= 0.0 || approx_eq(best, 0.0) {
+                continue;
+            }
+            scores[i][j] = best;
+            directions[i][j] = if approx_eq(best, score_diag) {
+                1
+            } else if approx_eq(best, score_up) {
+                2
+            } else {
+                3
+            };
+
+            if approx_eq(best, max_score) {
+                max_positions.push((i, j));
+            } else if best > max_score {
+                max_score = best;
+                max_positions.clear();
+                max_positions.push((i, j));
+            }
+        }
+    }
+
+    // Every tied end cell shares the maximum, so the integer ordering on
+    // coordinates alone picks the canonical one.
+    let mut best: Option<Alignment> = None;
+    for &(i_end, j_end) in &max_positions {
+        let (mut i, mut j, mut matches) = (i_end, j_end, 0usize);
+        while i > 0 && j > 0 && directions[i][j] != 0 {
+            match directions[i][j] {
+                1 => {
+                    if seq1[i - 1] == seq2[j - 1] {
+                        matches += 1;
+                    }
+                    i -= 1;
+                    j -= 1;
+                }
+                2 => i -= 1,
+                _ => j -= 1,
+            }
+        }
+        let candidate = Alignment {
+            score: 0,
+            query_start: i,
+            query_end: i_end,
+            token_start: j,
+            token_end: j_end,
+            matches,
+        };
+        if best
+            .as_ref()
+            .is_none_or(|current| cmp_alignment(&candidate, current) == Ordering::Less)
+        {
+            best = Some(candidate);
+        }
+    }
+
+    Ok(best.map_or(empty, |alignment| FloatAlignment {
+        score: max_score,
+        query_start: alignment.query_start,
+        query_end: alignment.query_end,
+        token_start: alignment.token_start,
+        token_end: alignment.token_end,
+        matches: alignment.matches,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn integer_valued_floats_match_integer_path() {
+        let cases: [(&[u32], &[u32]); 4] = [
+            (&[1, 2], &[1, 2, 1, 2]),
+            (&[1, 2, 3, 4], &[1, 2, 9, 3, 4]),
+            (&[5, 1, 2, 3], &[1, 2, 7, 3, 1, 2, 3]),
+            (&[1, 2, 3], &[4, 5, 6]),
+        ];
+        for (seq1, seq2) in cases {
+            let int = smith_waterman(seq1, seq2, ScoreParams::new(2, -1, -1)).unwrap();
+            let float = smith_waterman_f64(seq1, seq2, &FloatParams::new(2.0, -1.0, -1.0)).unwrap();
+            assert_eq!(float.score, f64::from(int.score));
+            assert_eq!(
+                (
+                    float.query_start,
+                    float.query_end,
+                    float.token_start,
+                    float.token_end
+                ),
+                (
+                    int.query_start,
+                    int.query_end,
+                    int.token_start,
+                    int.token_end
+                )
+            );
+            assert_eq!(float.matches, int.matches);
+        }
+
+        let mut params = FloatParams::new(1.5, -0.25, -0.75);
+        params.substitution.insert((1, 9), 0.5);
+        let alignment = smith_waterman_f64(&[1, 2], &[9, 2], &params).unwrap();
+        assert!(approx_eq(alignment.score, 2.0));
+        assert_eq!(alignment.matches, 1);
+        assert!(smith_waterman_f64(&[1], &[1], &FloatParams::new(f64::NAN, -1.0, -1.0)).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Local alignment with gap scores given as a function of gap length
+//! (Waterman-Smith-Beyer), for gap models beyond linear and affine.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, Scoring, check_score_range, cmp_alignment, empty_alignment,
+};
+
+/// Score of a gap of `L` tokens, on either side. Every variant must keep
+/// `open` and `extend` (or `gap`) at zero or below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GapCost {
+    /// `gap * L`, the same as `ScoreParams::new`.
+    Linear(i32),
+    /// `open + extend * (L - 1)`, the same as `ScoreParams::affine`.
+    Affine { open: i32, extend: i32 },
+    /// `open + extend * ln(L)`, rounded: each extra token costs less than the
+    /// one before, so one long gap beats several short ones of the same total.
+    Convex { open: i32, extend: i32 },
+}
+
+impl GapCost {
+    /// Score of a gap of `len` tokens, `len >= 1`.
+    pub fn score(&self, len: usize) -> i32 {
+        let len = i32::try_from(len).unwrap_or(i32::MAX);
+        match *self {
+            Self::Linear(gap) => gap.saturating_mul(len),
+            Self::Affine { open, extend } => open.saturating_add(extend.saturating_mul(len - 1)),
+            Self::Convex { open, extend } => {
+                open.saturating_add((f64::from(extend) * f64::from(len).ln()).round() as i32)
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), AlignError> {
+        let positive = match *self {
+            Self::Linear(gap) => gap > 0,
+            Self::Affine { open, extend } | Self::Convex { open, extend } => open > 0 || extend > 0,
+        };
+        if positive {
+            return Err(AlignError::InvalidParams(format!(
+                "gap costs must not be positive, got {self:?}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+const STOP: u8 = 0;
+const DIAG: u8 = 1;
+const UP: u8 = 2;
+const LEFT: u8 = 3;
+
+/// Best local alignment of `seq1` in `seq2` where a gap of `L` tokens scores
+/// `gap_cost.score(L)`, replacing the gap fields of `scoring.params()`.
+///
+/// Each cell scans every gap length back along its column and its row, so
+/// this costs O(len1 * len2 * (len1 + len2)) time, against O(len1 * len2) for
+/// `smith_waterman_with`; the matrix takes O(len1 * len2) memory either way.
+/// Use it only when the gap model needs it. Moves tie-break like
+/// `smith_waterman` (diagonal, then up, then left), shorter gaps first, and
+/// tied end cells like `smith_waterman_with`. `run_bonus` and
+/// `end_gap_discount` are not supported.
+pub fn smith_waterman_gap_cost<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    gap_cost: GapCost,
+) -> Result<Alignment, AlignError> {
+    let params = scoring.params();
+    if params.run_bonus != 0 || params.end_gap_discount != 0 {
+        return Err(AlignError::InvalidParams(
+            "run_bonus and end_gap_discount are not supported with a gap cost function".to_string(),
+        ));
+    }
+    gap_cost.validate()?;
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(empty_alignment());
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    let gaps: Vec<i32> = (0..=seq1.len().max(seq2.len()))
+        .map(|len| if len == 0 { 0 } else { gap_cost.score(len) })
+        .collect();
+
+    let cols = seq2.len() + 1;
+    let mut scores = vec![0i32; (seq1.len() + 1) * cols];
+    // The move into each cell and, for a gap, its length.
+    let mut moves = vec![(STOP, 0usize); scores.len()];
+    let mut max_score = 0i32;
+    let mut max_positions = Vec::new();
+    for i in 1..=seq1.len() {
+        for j in 1..cols {
+            let diag = scores[(i - 1) * cols + j - 1]
+                .saturating_add(scoring.substitution(seq1[i - 1], seq2[j - 1]));
+            let mut best = (diag.max(0), if diag > 0 { DIAG } else { STOP }, 0);
+            for len in 1..=i {
+                let up = scores[(i - len) * cols + j].saturating_add(gaps[len]);
+                if up > best.0 {
+                    best = (up, UP, len);
+                }
+            }
+            for len in 1..=j {
+                let left = scores[i * cols + j - len].saturating_add(gaps[len]);
+                if left > best.0 {
+                    best = (left, LEFT, len);
+                }
+            }
+            let (score, direction, len) = best;
+            scores[i * cols + j] = score;
+            moves[i * cols + j] = (direction, len);
+            if score > max_score {
+                max_score = score;
+                max_positions.clear();
+            }
+            if score == max_score && score > 0 {
+                max_positions.push((i, j));
+            }
+        }
+    }
+
+    let trace = |i_end: usize, j_end: usize| {
+        let (mut i, mut j, mut matches) = (i_end, j_end, 0usize);
+        loop {
+            match moves[i * cols + j] {
+                (DIAG, _) => {
+                    if scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+                        matches += 1;
+                    }
+                    i -= 1;
+                    j -= 1;
+                }
+                (UP, len) => i -= len,
+                (LEFT, len) => j -= len,
+                _ => break,
+            }
+        }
+        Alignment {
+            score: max_score,
+            query_start: i,
+            query_end: i_end,
+            token_start: j,
+            token_end: j_end,
+            matches,
+        }
+    };
+    Ok(max_positions
+        .into_iter()
+        .map(|(i, j)| trace(i, j))
+        .min_by(cmp_alignment)
+        .unwrap_or_else(empty_alignment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn convex_gaps_bridge_a_long_insertion() {
+        let params = ScoreParams::new(2, -1, -2);
+        let seq1: Vec<u32> = (1..=10).collect();
+        // The query's two halves, ten foreign tokens apart.
+        let seq2: Vec<u32> = (1..=5).chain(100..110).chain(6..=10).collect();
+
+        // Linear: the ten-token gap costs 20 and cancels the second half.
+        let linear = smith_waterman_gap_cost(&seq1, &seq2, &params, GapCost::Linear(-2)).unwrap();
+        let plain = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!(
+            (linear.score, linear.token_start, linear.token_end),
+            (plain.score, plain.token_start, plain.token_end)
+        );
+        assert_eq!((linear.score, linear.matches), (10, 5));
+
+        // Convex: -3 + -2 * ln(10) rounds to -8, so both halves align.
+        let convex = GapCost::Convex {
+            open: -3,
+            extend: -2,
+        };
+        assert_eq!(convex.score(10), -8);
+        let bridged = smith_waterman_gap_cost(&seq1, &seq2, &params, convex).unwrap();
+        assert_eq!(
+            (bridged.score, bridged.token_start, bridged.token_end),
+            (12, 0, 20)
+        );
+        assert_eq!(
+            (bridged.query_start, bridged.query_end, bridged.matches),
+            (0, 10, 10)
+        );
+
+        let affine = ScoreParams::affine(2, -1, -3, -1);
+        let expected = smith_waterman(&seq1, &seq2, affine).unwrap();
+        let gap_cost = GapCost::Affine {
+            open: -3,
+            extend: -1,
+        };
+        let actual = smith_waterman_gap_cost(&seq1, &seq2, &params, gap_cost).unwrap();
+        assert_eq!(
+            (
+                actual.score,
+                actual.token_start,
+                actual.token_end,
+                actual.matches
+            ),
+            (
+                expected.score,
+                expected.token_start,
+                expected.token_end,
+                expected.matches
+            )
+        );
+        assert!(smith_waterman_gap_cost(&seq1, &seq2, &params, GapCost::Linear(1)).is_err());
+    }
+}
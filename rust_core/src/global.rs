@@ -0,0 +1,123 @@
+//! Global (Needleman-Wunsch) alignment of two whole sequences, for telling
+//! whether two citation strings are the same.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Scoring, check_score_range};
+
+const DIAG: u8 = 0;
+const UP: u8 = 1;
+const LEFT: u8 = 2;
+
+/// Identity of the best global alignment of `seq1` and `seq2`: matches over
+/// every aligned column, end gaps included, in `0.0..=1.0`.
+///
+/// This is the usual metric for deduplication clustering. Unlike local
+/// `percent_identity`, a string that is a prefix of a longer one is not
+/// identical to it, and the result is a fraction rather than a percentage.
+/// Moves tie-break like `smith_waterman` (diagonal, then up, then left). Two
+/// empty sequences are identical (1.0); one empty sequence gives 0.0.
+/// `run_bonus`, `end_gap_discount` and affine gaps are not supported.
+pub fn global_identity<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> Result<f64, AlignError> {
+    let params = scoring.params();
+    if params.run_bonus != 0 || params.end_gap_discount != 0 || params.is_affine() {
+        return Err(AlignError::InvalidParams(
+            "run_bonus, end_gap_discount and gap_open are not supported for global alignment"
+                .to_string(),
+        ));
+    }
+    if seq1.is_empty() && seq2.is_empty() {
+        return Ok(1.0);
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    let (_, matches, columns) = align_global(seq1, seq2, scoring);
+    Ok(matches as f64 / columns as f64)
+}
+
+/// `(score, matches, columns)` of the best global alignment, without the
+/// parameter and range checks of `global_identity`. Either sequence may be
+/// empty, leaving an all-gap alignment.
+pub(crate) fn align_global<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> (i32, usize, usize) {
+    let params = scoring.params();
+    let cols = seq2.len() + 1;
+
+    // Row 0 and column 0 are end gaps, charged like any other gap.
+    let mut directions = vec![LEFT; cols];
+    directions[0] = DIAG;
+    let mut scores: Vec<i32> = (0..cols)
+        .map(|j| params.gap_ref.saturating_mul(j as i32))
+        .collect();
+    for (i, &token) in seq1.iter().enumerate() {
+        let mut row = vec![params.gap_query.saturating_mul(i as i32 + 1); cols];
+        directions.push(UP);
+        for j in 1..cols {
+            let diag = scores[j - 1].saturating_add(scoring.substitution(token, seq2[j - 1]));
+            let up = scores[j].saturating_add(params.gap_query);
+            let left = row[j - 1].saturating_add(params.gap_ref);
+            let (score, direction) = if diag >= up && diag >= left {
+                (diag, DIAG)
+            } else if up >= left {
+                (up, UP)
+            } else {
+                (left, LEFT)
+            };
+            row[j] = score;
+            directions.push(direction);
+        }
+        scores = row;
+    }
+
+    let (mut i, mut j) = (seq1.len(), seq2.len());
+    let (mut matches, mut columns) = (0usize, 0usize);
+    while i > 0 || j > 0 {
+        columns += 1;
+        match directions[i * cols + j] {
+            DIAG => {
+                if scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+                    matches += 1;
+                }
+                i -= 1;
+                j -= 1;
+            }
+            UP => i -= 1,
+            _ => j -= 1,
+        }
+    }
+    (scores[seq2.len()], matches, columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::ScoreParams;
+
+    #[test]
+    fn global_identity_counts_every_column() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=10).collect();
+
+        let mut substituted = seq1.clone();
+        substituted[4] = 99;
+        assert_eq!(global_identity(&seq1, &substituted, &params).unwrap(), 0.9);
+
+        // Nine matches and one gap column.
+        let deleted: Vec<u32> = seq1.iter().copied().filter(|&token| token != 5).collect();
+        assert_eq!(global_identity(&seq1, &deleted, &params).unwrap(), 0.9);
+
+        // End gaps count, unlike in local identity.
+        assert_eq!(
+            global_identity(&[1, 2, 3], &[1, 2, 3, 4, 5], &params).unwrap(),
+            0.6
+        );
+        assert_eq!(global_identity(&seq1, &seq1, &params).unwrap(), 1.0);
+        assert_eq!(global_identity(&[], &[], &params).unwrap(), 1.0);
+        assert_eq!(global_identity(&seq1, &[], &params).unwrap(), 0.0);
+    }
+}
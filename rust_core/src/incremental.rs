@@ -0,0 +1,153 @@
+//! Re-alignment of a growing query against a fixed reference.
+
+use pyo3::prelude::*;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, ColumnRules, ScoreMatrix, ScoreParams, check_score_range, empty_alignment,
+    select_best,
+};
+use crate::{GapScore, PairDetails, detach_if, pair_details, score_params};
+
+/// Smith-Waterman state for one reference, extended one query token at a time.
+///
+/// Each `push_query_token` computes a single new DP row, O(len(reference)),
+/// instead of refilling the whole matrix. The full score and direction
+/// matrices are kept for the traceback, so memory grows by about
+/// `5 * (len(reference) + 1)` bytes per pushed token.
+#[pyclass(name = "IncrementalAligner")]
+pub struct IncrementalAligner {
+    reference: Vec<u32>,
+    params: ScoreParams,
+    query: Vec<u32>,
+    matrix: ScoreMatrix,
+}
+
+impl IncrementalAligner {
+    /// Rejects `end_gap_discount`, whose costs depend on the final query
+    /// length and so cannot be computed row by row.
+    pub fn new(reference: Vec<u32>, params: ScoreParams) -> Result<Self, AlignError> {
+        if params.end_gap_discount != 0 {
+            return Err(AlignError::InvalidParams(
+                "end_gap_discount is not supported for incremental alignment".to_string(),
+            ));
+        }
+        let matrix = ScoreMatrix::new(reference.len() + 1);
+        Ok(Self {
+            reference,
+            params,
+            query: Vec::new(),
+            matrix,
+        })
+    }
+
+    /// Appends `token` to the query and returns the updated best alignment,
+    /// identical to a from-scratch `smith_waterman` on the whole query.
+    pub fn push(&mut self, token: u32) -> Result<Alignment, AlignError> {
+        check_score_range::<u32, _>(self.query.len() + 1, self.reference.len(), &self.params)?;
+        self.matrix.push_row(
+            token,
+            self.query.len() + 1,
+            &self.reference,
+            &self.params,
+            &ColumnRules::default(),
+        );
+        self.query.push(token);
+        self.best()
+    }
+
+    pub fn best(&self) -> Result<Alignment, AlignError> {
+        if self.matrix.max_score <= 0 {
+            return Ok(empty_alignment());
+        }
+        select_best(&self.matrix, &self.query, &self.reference, &self.params)
+    }
+}
+
+#[pymethods]
+impl IncrementalAligner {
+    #[new]
+    #[pyo3(signature = (reference, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0))]
+    fn py_new(
+        py: Python<'_>,
+        reference: Vec<u32>,
+        match_score: i32,
+        mismatch_score: i32,
+        gap_score: GapScore,
+        wildcard: Option<u32>,
+        gap_query: Option<i32>,
+        gap_ref: Option<i32>,
+        run_bonus: i32,
+    ) -> PyResult<Self> {
+        let params = score_params(
+            py,
+            match_score,
+            mismatch_score,
+            gap_score,
+            wildcard,
+            gap_query,
+            gap_ref,
+            run_bonus,
+            0,
+        )?;
+        Ok(Self::new(reference, params)?)
+    }
+
+    fn __len__(&self) -> usize {
+        self.query.len()
+    }
+
+    #[pyo3(signature = (token, release_gil=true))]
+    fn push_query_token(
+        &mut self,
+        py: Python<'_>,
+        token: u32,
+        release_gil: bool,
+    ) -> PyResult<PairDetails> {
+        let alignment = detach_if(py, release_gil, || self.push(token))?;
+        Ok(pair_details(&alignment))
+    }
+
+    #[pyo3(name = "best")]
+    fn py_best(&self) -> PyResult<PairDetails> {
+        Ok(pair_details(&self.best()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::smith_waterman;
+
+    #[test]
+    fn incremental_matches_from_scratch_after_each_append() {
+        let params = ScoreParams::new(2, -1, -1);
+        let reference = vec![4, 1, 2, 9, 3, 1, 2, 3, 5];
+        let query = [7, 1, 2, 3, 3, 5, 1];
+
+        let mut aligner = IncrementalAligner::new(reference.clone(), params).unwrap();
+        for end in 1..=query.len() {
+            let incremental = aligner.push(query[end - 1]).unwrap();
+            let scratch = smith_waterman(&query[..end], &reference, params).unwrap();
+            assert_eq!(
+                (
+                    incremental.score,
+                    incremental.query_start,
+                    incremental.query_end,
+                    incremental.token_start,
+                    incremental.token_end,
+                    incremental.matches,
+                ),
+                (
+                    scratch.score,
+                    scratch.query_start,
+                    scratch.query_end,
+                    scratch.token_start,
+                    scratch.token_end,
+                    scratch.matches,
+                ),
+                "after {end} tokens"
+            );
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use pyo3::prelude::*;
+use std::collections::HashSet;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{self, CandidateAlignment, ScoreParams};
+use crate::{AlignmentDetails, GapScore, candidate_details, detach_if, parallel, score_params};
+
+/// A tokenized reference corpus kept resident in Rust across queries.
+///
+/// Alignment methods give exactly the same results as the stateless corpus
+/// functions. When built with a `kmer_size`, the index also keeps each
+/// reference's set of k-mers so `candidates` can shortlist references sharing
+/// at least one k-mer with a query.
+#[pyclass(name = "ReferenceIndex", frozen)]
+pub struct ReferenceIndex {
+    seqs: Vec<Vec<u32>>,
+    kmer_size: Option<usize>,
+    kmers: Vec<HashSet<Vec<u32>>>,
+}
+
+impl ReferenceIndex {
+    pub fn build(seqs: Vec<Vec<u32>>, kmer_size: Option<usize>) -> Result<Self, AlignError> {
+        let kmers = match kmer_size {
+            None => Vec::new(),
+            Some(0) => {
+                return Err(AlignError::InvalidArgument(
+                    "kmer_size must be at least 1".to_string(),
+                ));
+            }
+            Some(k) => seqs.iter().map(|seq| kmer_set(seq, k)).collect(),
+        };
+        Ok(Self {
+            seqs,
+            kmer_size,
+            kmers,
+        })
+    }
+
+    pub fn topk(
+        &self,
+        seq1: &[u32],
+        params: ScoreParams,
+        top_k: usize,
+    ) -> Result<Vec<CandidateAlignment>, AlignError> {
+        smith_waterman::align_topk(seq1, &self.seqs, params, top_k)
+    }
+
+    pub fn best(
+        &self,
+        seq1: &[u32],
+        params: ScoreParams,
+    ) -> Result<Option<CandidateAlignment>, AlignError> {
+        smith_waterman::align_best(seq1, &self.seqs, params)
+    }
+
+    /// Indices of references sharing at least one k-mer with `seq1`, ascending.
+    pub fn candidate_indices(&self, seq1: &[u32]) -> Result<Vec<usize>, AlignError> {
+        let Some(k) = self.kmer_size else {
+            return Err(AlignError::InvalidArgument(
+                "index was built without kmer_size".to_string(),
+            ));
+        };
+        let query = kmer_set(seq1, k);
+        Ok(self
+            .kmers
+            .iter()
+            .enumerate()
+            .filter(|(_, kmers)| !kmers.is_disjoint(&query))
+            .map(|(index, _)| index)
+            .collect())
+    }
+}
+
+fn kmer_set(seq: &[u32], k: usize) -> HashSet<Vec<u32>> {
+    seq.windows(k).map(<[u32]>::to_vec).collect()
+}
+
+#[pymethods]
+impl ReferenceIndex {
+    #[new]
+    #[pyo3(signature = (seqs, kmer_size=None))]
+    fn new(seqs: Vec<Vec<u32>>, kmer_size: Option<usize>) -> PyResult<Self> {
+        Ok(Self::build(seqs, kmer_size)?)
+    }
+
+    fn __len__(&self) -> usize {
+        self.seqs.len()
+    }
+
+    #[pyo3(signature = (seq1, top_k=1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None, release_gil=true))]
+    fn align_topk(
+        &self,
+        py: Python<'_>,
+        seq1: Vec<u32>,
+        top_k: usize,
+        match_score: i32,
+        mismatch_score: i32,
+        gap_score: GapScore,
+        wildcard: Option<u32>,
+        gap_query: Option<i32>,
+        gap_ref: Option<i32>,
+        run_bonus: i32,
+        end_gap_discount: i32,
+        num_threads: Option<usize>,
+        release_gil: bool,
+    ) -> PyResult<Vec<AlignmentDetails>> {
+        let params = score_params(
+            py,
+            match_score,
+            mismatch_score,
+            gap_score,
+            wildcard,
+            gap_query,
+            gap_ref,
+            run_bonus,
+            end_gap_discount,
+        )?;
+        let top = detach_if(py, release_gil, || {
+            parallel::with_thread_pool(num_threads, || self.topk(&seq1, params, top_k))
+        })?;
+        Ok(top.iter().map(candidate_details).collect())
+    }
+
+    #[pyo3(signature = (seq1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None, release_gil=true))]
+    fn align_best(
+        &self,
+        py: Python<'_>,
+        seq1: Vec<u32>,
+        match_score: i32,
+        mismatch_score: i32,
+        gap_score: GapScore,
+        wildcard: Option<u32>,
+        gap_query: Option<i32>,
+        gap_ref: Option<i32>,
+        run_bonus: i32,
+        end_gap_discount: i32,
+        num_threads: Option<usize>,
+        release_gil: bool,
+    ) -> PyResult<Option<AlignmentDetails>> {
+        let params = score_params(
+            py,
+            match_score,
+            mismatch_score,
+            gap_score,
+            wildcard,
+            gap_query,
+            gap_ref,
+            run_bonus,
+            end_gap_discount,
+        )?;
+        let best = detach_if(py, release_gil, || {
+            parallel::with_thread_pool(num_threads, || self.best(&seq1, params))
+        })?;
+        Ok(best.as_ref().map(candidate_details))
+    }
+
+    fn candidates(&self, py: Python<'_>, seq1: Vec<u32>) -> PyResult<Vec<usize>> {
+        Ok(py.detach(|| self.candidate_indices(&seq1))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_index_matches_stateless_queries() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seqs = vec![vec![3, 4], vec![1, 2, 1, 2], vec![1, 2], vec![0, 1, 2, 3]];
+        let index = ReferenceIndex::build(seqs.clone(), Some(2)).unwrap();
+
+        for query in [vec![1, 2], vec![2, 3, 4]] {
+            let expected = smith_waterman::align_topk(&query, &seqs, params, 3).unwrap();
+            let actual = index.topk(&query, params, 3).unwrap();
+            assert_eq!(
+                actual.iter().map(candidate_details).collect::<Vec<_>>(),
+                expected.iter().map(candidate_details).collect::<Vec<_>>()
+            );
+            let best = index.best(&query, params).unwrap().unwrap();
+            assert_eq!(candidate_details(&best), candidate_details(&expected[0]));
+        }
+
+        assert_eq!(index.candidate_indices(&[1, 2]).unwrap(), vec![1, 2, 3]);
+        assert_eq!(index.candidate_indices(&[2, 3, 4]).unwrap(), vec![0, 3]);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::smith_waterman::CandidateAlignment;
+
+/// Serializes a candidate as a flat JSON object.
+///
+/// All fields are numeric, so this is plain formatting rather than a general
+/// serializer; `None` serializes as `null`.
+pub fn candidate_to_json(candidate: Option<&CandidateAlignment>) -> String {
+    let Some(candidate) = candidate else {
+        return "null".to_string();
+    };
+    format!(
+        "{{\"score\":{},\"index\":{},\"token_start\":{},\"token_end\":{},\"query_start\":{},\"query_end\":{},\"matches\":{},\"percent_identity\":{:?}}}",
+        candidate.score,
+        candidate.index,
+        candidate.token_start,
+        candidate.token_end,
+        candidate.query_start,
+        candidate.query_end,
+        candidate.matches,
+        candidate.percent_identity(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, align_best};
+
+    #[test]
+    fn candidate_to_json_uses_named_fields() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seqs = vec![vec![3, 4], vec![0, 1, 2, 3]];
+        let best = align_best(&[1, 2, 3], &seqs, params).unwrap().unwrap();
+        assert_eq!(
+            candidate_to_json(Some(&best)),
+            "{\"score\":6,\"index\":1,\"token_start\":1,\"token_end\":4,\"query_start\":0,\"query_end\":3,\"matches\":3,\"percent_identity\":100.0}"
+        );
+        assert_eq!(candidate_to_json(None), "null");
+    }
+}
@@ -1,174 +1,3024 @@
+// pyfunction signatures mirror their Python keyword arguments one-to-one.
+#![allow(clippy::too_many_arguments)]
+
+use pyo3::exceptions::PyUserWarning;
 use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
 
-mod smith_waterman;
+mod arrays;
+pub mod banded;
+pub mod chain;
+pub mod classify;
+pub mod config;
+pub mod error;
+pub mod extend;
+pub mod float;
+pub mod gap_cost;
+pub mod global;
+pub mod incremental;
+pub mod index;
+pub mod json;
+pub mod minhash;
+pub mod overlap;
+pub mod parallel;
+pub mod prefix;
+pub mod regions;
+pub mod restart;
+pub mod scoring;
+pub mod segments;
+pub mod smith_waterman;
+pub mod strand;
+pub mod stream;
+pub mod summary;
+pub mod ties;
+pub mod tokenize;
+pub mod ungapped;
 
 type MatchBlocks = Vec<(usize, usize)>;
-type AlignmentDetails = (i32, usize, usize, usize, usize, usize, usize);
+pub(crate) type AlignmentDetails = (i32, usize, usize, usize, usize, usize, usize);
+pub(crate) type PairDetails = (i32, usize, usize, usize, usize, usize);
+type ExtensionDetails = (i32, usize, usize, usize, usize);
+type GappedColumns = (Vec<Option<u32>>, Vec<Option<u32>>);
 type AlignmentWithBlocks = (i32, usize, usize, usize, usize, usize, MatchBlocks);
+type AlignmentWithBlockScores = (
+    i32,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    MatchBlocks,
+    Vec<i32>,
+);
+type AlignmentWithBlockWeights = (
+    i32,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    MatchBlocks,
+    Vec<f64>,
+);
+type CandidateWithBlocks = (i32, usize, usize, usize, usize, usize, usize, MatchBlocks);
+type AlignmentWithBlockCounts = (
+    i32,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    Vec<smith_waterman::CountedBlock>,
+);
+
+pub(crate) fn pair_details(alignment: &smith_waterman::Alignment) -> PairDetails {
+    (
+        alignment.score,
+        alignment.token_start,
+        alignment.token_end,
+        alignment.query_start,
+        alignment.query_end,
+        alignment.matches,
+    )
+}
+
+/// `pair_details` with inclusive `token_end` and `query_end` (the last
+/// aligned tokens); an empty alignment keeps its all-zero coordinates.
+fn inclusive_pair_details(alignment: &smith_waterman::Alignment) -> PairDetails {
+    let mut details = pair_details(alignment);
+    if let Some((query_last, token_last)) = alignment.inclusive_ends() {
+        (details.2, details.4) = (token_last, query_last);
+    }
+    details
+}
+
+/// `candidate_details` with inclusive ends, like `inclusive_pair_details`.
+fn inclusive_candidate_details(item: &smith_waterman::CandidateAlignment) -> AlignmentDetails {
+    let mut details = candidate_details(item);
+    if item.token_end > item.token_start {
+        (details.3, details.5) = (item.token_end - 1, item.query_end - 1);
+    }
+    details
+}
+
+fn extension_details(extension: &extend::Extension) -> ExtensionDetails {
+    (
+        extension.score,
+        extension.token_start,
+        extension.token_end,
+        extension.query_start,
+        extension.query_end,
+    )
+}
+
+pub(crate) fn candidate_details(item: &smith_waterman::CandidateAlignment) -> AlignmentDetails {
+    (
+        item.score,
+        item.index,
+        item.token_start,
+        item.token_end,
+        item.query_start,
+        item.query_end,
+        item.matches,
+    )
+}
+
+/// Python `gap_score`: a scalar charged per gap token, or an
+/// `(open, extend)` pair for affine gaps (the first token costs `open`, each
+/// further token `extend`).
+#[derive(Clone, Copy, FromPyObject)]
+pub(crate) enum GapScore {
+    Linear(i32),
+    Affine(i32, i32),
+}
+
+/// Builds and validates the scoring scheme shared by every pyfunction.
+///
+/// `gap_score` is a scalar per-token cost or an `(open, extend)` pair for
+/// affine gaps. `gap_query` and `gap_ref` override its per-token (extension)
+/// cost for up and left moves respectively.
+///
+/// Invalid schemes raise `ValueError`; degenerate but usable ones emit a
+/// `UserWarning` and proceed.
+pub(crate) fn score_params(
+    py: Python<'_>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<smith_waterman::ScoreParams> {
+    let (gap_open, gap_extend) = match gap_score {
+        GapScore::Linear(gap) => (0, gap),
+        GapScore::Affine(open, extend) => (open - extend, extend),
+    };
+    let params = smith_waterman::ScoreParams {
+        wildcard,
+        gap_query: gap_query.unwrap_or(gap_extend),
+        gap_ref: gap_ref.unwrap_or(gap_extend),
+        gap_open,
+        run_bonus,
+        end_gap_discount,
+        ..smith_waterman::ScoreParams::new(match_score, mismatch_score, gap_extend)
+    };
+    params.validate()?;
+    if let Some(reason) = params.degenerate_reason() {
+        PyErr::warn(py, &py.get_type::<PyUserWarning>(), reason, 1)?;
+    }
+    Ok(params)
+}
 
-#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=-1))]
+/// `(match_score, mismatch_score, gap_score)` of a named preset.
+#[pyfunction]
+fn score_preset(name: &str) -> PyResult<(i32, i32, i32)> {
+    let params = smith_waterman::ScoreParams::preset(name).ok_or_else(|| {
+        error::AlignError::InvalidArgument(format!(
+            "unknown preset {name:?}; expected citation, dna, strict or lenient"
+        ))
+    })?;
+    Ok((params.match_score, params.mismatch_score, params.gap_query))
+}
+
+/// Runs `op` with the GIL released, or while holding it when `release_gil` is
+/// false: releasing and reacquiring it costs more than aligning a few tokens.
+fn detach_if<T, F>(py: Python<'_>, release_gil: bool, op: F) -> T
+where
+    T: Send,
+    F: FnOnce() -> T + Send,
+{
+    if release_gil { py.detach(op) } else { op() }
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, release_gil=true))]
 fn align_pair(
     py: Python<'_>,
     seq1: Vec<u32>,
     seq2: Vec<u32>,
     match_score: i32,
     mismatch_score: i32,
-    gap_score: i32,
-) -> (i32, usize, usize) {
-    let params = smith_waterman::ScoreParams {
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    release_gil: bool,
+) -> PyResult<(i32, usize, usize)> {
+    let params = score_params(
+        py,
         match_score,
         mismatch_score,
         gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = detach_if(py, release_gil, || {
+        smith_waterman::smith_waterman(&seq1, &seq2, params)
+    })?;
+    Ok((alignment.score, alignment.token_start, alignment.token_end))
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2.0, mismatch_score=-1.0, gap_score=-1.0, gap_query=None, gap_ref=None, substitution=None))]
+fn align_pair_f64(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: f64,
+    mismatch_score: f64,
+    gap_score: f64,
+    gap_query: Option<f64>,
+    gap_ref: Option<f64>,
+    substitution: Option<HashMap<(u32, u32), f64>>,
+) -> PyResult<(f64, usize, usize, usize, usize, usize)> {
+    let params = float::FloatParams {
+        gap_query: gap_query.unwrap_or(gap_score),
+        gap_ref: gap_ref.unwrap_or(gap_score),
+        substitution: substitution.unwrap_or_default(),
+        ..float::FloatParams::new(match_score, mismatch_score, gap_score)
     };
-    py.detach(|| {
-        let alignment = smith_waterman::smith_waterman(&seq1, &seq2, params);
-        (alignment.score, alignment.token_start, alignment.token_end)
-    })
+    let alignment = py.detach(|| float::smith_waterman_f64(&seq1, &seq2, &params))?;
+    Ok((
+        alignment.score,
+        alignment.token_start,
+        alignment.token_end,
+        alignment.query_start,
+        alignment.query_end,
+        alignment.matches,
+    ))
+}
+
+#[pyfunction(signature = (text1, text2, vocab, unknown_id=0, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_str(
+    py: Python<'_>,
+    text1: &str,
+    text2: &str,
+    vocab: HashMap<String, u32>,
+    unknown_id: u32,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(i32, usize, usize)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| {
+        let seq1 = tokenize::tokenize(text1, &vocab, unknown_id);
+        let seq2 = tokenize::tokenize(text2, &vocab, unknown_id);
+        smith_waterman::smith_waterman(&seq1, &seq2, params)
+    })?;
+    Ok((alignment.score, alignment.token_start, alignment.token_end))
+}
+
+#[pyfunction(name = "tokenize", signature = (text, vocab, unknown_id=0))]
+fn tokenize_text(
+    py: Python<'_>,
+    text: &str,
+    vocab: HashMap<String, u32>,
+    unknown_id: u32,
+) -> Vec<u32> {
+    py.detach(|| tokenize::tokenize(text, &vocab, unknown_id))
 }
 
-#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=-1))]
+/// `(distinct, min_id, max_id, dense)` over every token of `seqs`, where
+/// `dense` means the ids are exactly `0..distinct`.
+#[pyfunction]
+fn analyze_tokens(py: Python<'_>, seqs: Vec<Vec<u32>>) -> (usize, Option<u32>, Option<u32>, bool) {
+    let stats = py.detach(|| tokenize::analyze_tokens(&seqs));
+    (stats.distinct, stats.min, stats.max, stats.dense)
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, mask=None, equiv=None, inclusive_ends=false, release_gil=true))]
 fn align_pair_details(
     py: Python<'_>,
     seq1: Vec<u32>,
     seq2: Vec<u32>,
     match_score: i32,
     mismatch_score: i32,
-    gap_score: i32,
-) -> (i32, usize, usize, usize, usize, usize) {
-    let params = smith_waterman::ScoreParams {
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+    equiv: Option<HashMap<u32, u32>>,
+    inclusive_ends: bool,
+    release_gil: bool,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
         match_score,
         mismatch_score,
         gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let config = config::AlignConfig {
+        params,
+        mask: mask.unwrap_or_default(),
+        equiv,
+        inclusive_ends,
+        release_gil,
+        ..Default::default()
     };
-    py.detach(|| {
-        let alignment = smith_waterman::smith_waterman(&seq1, &seq2, params);
-        (
-            alignment.score,
-            alignment.token_start,
-            alignment.token_end,
-            alignment.query_start,
-            alignment.query_end,
-            alignment.matches,
-        )
-    })
+    config_pair_details(py, &seq1, &seq2, &config)
+}
+
+/// Parses a `frame` argument: `"query"` or `"reference"`.
+fn block_frame(frame: &str) -> PyResult<smith_waterman::BlockFrame> {
+    match frame {
+        "query" => Ok(smith_waterman::BlockFrame::Query),
+        "reference" => Ok(smith_waterman::BlockFrame::Reference),
+        other => Err(error::AlignError::InvalidArgument(format!(
+            "frame must be \"query\" or \"reference\", got {other:?}"
+        ))
+        .into()),
+    }
+}
+
+/// Parses a `sort_key` argument: `"score"`, `"matches"`,
+/// `"percent_identity"` or `"token_start"`.
+fn sort_key(key: &str) -> PyResult<smith_waterman::SortKey> {
+    match key {
+        "score" => Ok(smith_waterman::SortKey::Score),
+        "matches" => Ok(smith_waterman::SortKey::Matches),
+        "percent_identity" => Ok(smith_waterman::SortKey::PercentIdentity),
+        "token_start" => Ok(smith_waterman::SortKey::TokenStart),
+        other => Err(error::AlignError::InvalidArgument(format!(
+            "sort_key must be \"score\", \"matches\", \"percent_identity\" or \"token_start\", got {other:?}"
+        ))
+        .into()),
+    }
 }
 
-#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=-1))]
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, mask=None, inclusive_ends=false, frame="reference", min_anchor=0, max_blocks=None, release_gil=true))]
 fn align_pair_blocks_details(
     py: Python<'_>,
     seq1: Vec<u32>,
     seq2: Vec<u32>,
     match_score: i32,
     mismatch_score: i32,
-    gap_score: i32,
-) -> AlignmentWithBlocks {
-    let params = smith_waterman::ScoreParams {
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+    inclusive_ends: bool,
+    frame: &str,
+    min_anchor: usize,
+    max_blocks: Option<usize>,
+    release_gil: bool,
+) -> PyResult<AlignmentWithBlocks> {
+    let frame = block_frame(frame)?;
+    let params = score_params(
+        py,
         match_score,
         mismatch_score,
         gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let constraints = smith_waterman::Constraints {
+        mask: mask.unwrap_or_default(),
+        ..Default::default()
     };
-    py.detach(|| {
-        let (alignment, match_blocks) =
-            smith_waterman::smith_waterman_match_blocks(&seq1, &seq2, params);
-        (
-            alignment.score,
-            alignment.token_start,
-            alignment.token_end,
-            alignment.query_start,
-            alignment.query_end,
-            alignment.matches,
-            match_blocks,
+    let options = smith_waterman::BlockOptions {
+        frame,
+        min_anchor,
+        max_blocks,
+    };
+    let (alignment, mut match_blocks) = detach_if(py, release_gil, || {
+        smith_waterman::smith_waterman_match_blocks_with(
+            &seq1,
+            &seq2,
+            &params,
+            &constraints,
+            options,
         )
-    })
+    })?;
+    let (score, token_start, token_end, query_start, query_end, matches) = if inclusive_ends {
+        for block in &mut match_blocks {
+            block.1 -= 1;
+        }
+        inclusive_pair_details(&alignment)
+    } else {
+        pair_details(&alignment)
+    };
+    Ok((
+        score,
+        token_start,
+        token_end,
+        query_start,
+        query_end,
+        matches,
+        match_blocks,
+    ))
 }
 
-#[pyfunction(signature = (seq1, seqs, match_score=2, mismatch_score=-1, gap_score=-1))]
-fn align_best(
+/// `align_pair_blocks_details` plus each block's score contribution, as
+/// `(score, token_start, token_end, query_start, query_end, matches, blocks,
+/// block_scores)`; see `smith_waterman::smith_waterman_block_scores`.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, mask=None, frame="reference", min_anchor=0, max_blocks=None))]
+fn align_pair_block_scores(
     py: Python<'_>,
     seq1: Vec<u32>,
-    seqs: Vec<Vec<u32>>,
+    seq2: Vec<u32>,
     match_score: i32,
     mismatch_score: i32,
-    gap_score: i32,
-) -> Option<(i32, usize, usize, usize)> {
-    let params = smith_waterman::ScoreParams {
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+    frame: &str,
+    min_anchor: usize,
+    max_blocks: Option<usize>,
+) -> PyResult<AlignmentWithBlockScores> {
+    let frame = block_frame(frame)?;
+    let params = score_params(
+        py,
         match_score,
         mismatch_score,
         gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let constraints = smith_waterman::Constraints {
+        mask: mask.unwrap_or_default(),
+        ..Default::default()
     };
-    let best = py.detach(|| smith_waterman::align_best(&seq1, &seqs, params))?;
-    Some((best.score, best.index, best.token_start, best.token_end))
+    let options = smith_waterman::BlockOptions {
+        frame,
+        min_anchor,
+        max_blocks,
+    };
+    let (alignment, blocks, block_scores) = py.detach(|| {
+        smith_waterman::smith_waterman_block_scores(&seq1, &seq2, &params, &constraints, options)
+    })?;
+    let (score, token_start, token_end, query_start, query_end, matches) = pair_details(&alignment);
+    Ok((
+        score,
+        token_start,
+        token_end,
+        query_start,
+        query_end,
+        matches,
+        blocks,
+        block_scores,
+    ))
 }
 
-#[pyfunction(signature = (seq1, seqs, match_score=2, mismatch_score=-1, gap_score=-1))]
-fn align_best_details(
+/// `align_pair_blocks_details` plus each block's summed `token_idf` weight,
+/// as `(score, token_start, token_end, query_start, query_end, matches,
+/// blocks, block_weights)`; see `smith_waterman::smith_waterman_block_weights`.
+#[pyfunction(signature = (seq1, seq2, token_idf, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, mask=None, frame="reference", min_anchor=0, max_blocks=None))]
+fn align_pair_block_weights(
     py: Python<'_>,
     seq1: Vec<u32>,
-    seqs: Vec<Vec<u32>>,
+    seq2: Vec<u32>,
+    token_idf: Vec<f64>,
     match_score: i32,
     mismatch_score: i32,
-    gap_score: i32,
-) -> Option<AlignmentDetails> {
-    let params = smith_waterman::ScoreParams {
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+    frame: &str,
+    min_anchor: usize,
+    max_blocks: Option<usize>,
+) -> PyResult<AlignmentWithBlockWeights> {
+    let frame = block_frame(frame)?;
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let constraints = smith_waterman::Constraints {
+        mask: mask.unwrap_or_default(),
+        ..Default::default()
+    };
+    let options = smith_waterman::BlockOptions {
+        frame,
+        min_anchor,
+        max_blocks,
+    };
+    let (alignment, blocks, block_weights) = py.detach(|| {
+        smith_waterman::smith_waterman_block_weights(
+            &seq1,
+            &seq2,
+            &params,
+            &constraints,
+            options,
+            &token_idf,
+        )
+    })?;
+    let (score, token_start, token_end, query_start, query_end, matches) = pair_details(&alignment);
+    Ok((
+        score,
+        token_start,
+        token_end,
+        query_start,
+        query_end,
+        matches,
+        blocks,
+        block_weights,
+    ))
+}
+
+#[pyfunction(signature = (seq1, seq2, merge_gap=0, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, mask=None, inclusive_ends=false))]
+fn align_pair_block_counts_details(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    merge_gap: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+    inclusive_ends: bool,
+) -> PyResult<AlignmentWithBlockCounts> {
+    let params = score_params(
+        py,
         match_score,
         mismatch_score,
         gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let constraints = smith_waterman::Constraints {
+        mask: mask.unwrap_or_default(),
+        ..Default::default()
     };
-    let best = py.detach(|| smith_waterman::align_best(&seq1, &seqs, params))?;
-    Some((
-        best.score,
-        best.index,
-        best.token_start,
-        best.token_end,
-        best.query_start,
-        best.query_end,
-        best.matches,
+    let (alignment, mut blocks) = py.detach(|| {
+        smith_waterman::smith_waterman_match_block_counts_with(
+            &seq1,
+            &seq2,
+            &params,
+            &constraints,
+            merge_gap,
+        )
+    })?;
+    let (score, token_start, token_end, query_start, query_end, matches) = if inclusive_ends {
+        for block in &mut blocks {
+            block.1 -= 1;
+        }
+        inclusive_pair_details(&alignment)
+    } else {
+        pair_details(&alignment)
+    };
+    Ok((
+        score,
+        token_start,
+        token_end,
+        query_start,
+        query_end,
+        matches,
+        blocks,
     ))
 }
 
-#[pyfunction(signature = (seq1, seqs, top_k=1, match_score=2, mismatch_score=-1, gap_score=-1))]
-fn align_topk_details(
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_ungapped(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| ungapped::smith_waterman_ungapped(&seq1, &seq2, &params))?;
+    Ok(pair_details(&alignment))
+}
+
+/// `(lower, upper)` bounds on the `align_pair` score without the full DP.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn score_bounds(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(i32, i32)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    Ok(py.detach(|| ungapped::score_bounds(&seq1, &seq2, &params))?)
+}
+
+/// Approximate `align_pair_details`: full DP only within `band` of the
+/// `probes` diagonals with the most exact matches.
+#[pyfunction(signature = (seq1, seq2, probes=3, band=8, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_best_diagonal_prefilter(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    probes: usize,
+    band: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| {
+        ungapped::smith_waterman_diagonal_prefilter(&seq1, &seq2, &params, probes, band)
+    })?;
+    Ok(pair_details(&alignment))
+}
+
+/// `align_pair_details` driven by an `AlignConfig`.
+#[pyfunction]
+fn align_pair_cfg(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    config: PyRef<'_, config::AlignConfig>,
+) -> PyResult<PairDetails> {
+    config_pair_details(py, &seq1, &seq2, &config.clone())
+}
+
+/// `AlignConfig::align_pair` as a details tuple, honouring the config's
+/// `release_gil` and `inclusive_ends`.
+fn config_pair_details(
+    py: Python<'_>,
+    seq1: &[u32],
+    seq2: &[u32],
+    config: &config::AlignConfig,
+) -> PyResult<PairDetails> {
+    let alignment = detach_if(py, config.release_gil, || config.align_pair(seq1, seq2))?;
+    if config.inclusive_ends {
+        return Ok(inclusive_pair_details(&alignment));
+    }
+    Ok(pair_details(&alignment))
+}
+
+/// `align_topk_details` driven by an `AlignConfig`.
+#[pyfunction(signature = (seq1, seqs, config, top_k=1))]
+fn align_topk_cfg(
     py: Python<'_>,
     seq1: Vec<u32>,
     seqs: Vec<Vec<u32>>,
+    config: PyRef<'_, config::AlignConfig>,
     top_k: usize,
+) -> PyResult<Vec<AlignmentDetails>> {
+    let config = config.clone();
+    let top = detach_if(py, config.release_gil, || {
+        parallel::with_thread_pool(config.num_threads, || {
+            config.align_topk(&seq1, &seqs, top_k)
+        })
+    })?;
+    if config.inclusive_ends {
+        return Ok(top.iter().map(inclusive_candidate_details).collect());
+    }
+    Ok(top.iter().map(candidate_details).collect())
+}
+
+/// `align_pair_details` filling only cells within `band` of the main diagonal;
+/// the flag is `True` when the result touches the band's edge.
+#[pyfunction(signature = (seq1, seq2, band, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_banded(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    band: usize,
     match_score: i32,
     mismatch_score: i32,
-    gap_score: i32,
-) -> Vec<AlignmentDetails> {
-    if top_k == 0 || seqs.is_empty() {
-        return Vec::new();
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(PairDetails, bool)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let banded = py.detach(|| banded::smith_waterman_banded(&seq1, &seq2, &params, band))?;
+    Ok((pair_details(&banded.alignment), banded.band_clipped))
+}
+
+/// `align_pair_details` plus the tokens left unaligned at each end:
+/// `(query_clip_left, query_clip_right, reference_clip_left, reference_clip_right)`.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_clips(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(PairDetails, (usize, usize, usize, usize))> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| smith_waterman::smith_waterman(&seq1, &seq2, params))?;
+    let (query_left, query_right) = alignment.query_clips(seq1.len());
+    let (reference_left, reference_right) = alignment.reference_clips(seq2.len());
+    Ok((
+        pair_details(&alignment),
+        (query_left, query_right, reference_left, reference_right),
+    ))
+}
+
+/// `align_pair_details` plus whether the score exceeds `floor`, so "nothing
+/// aligned" is not mistaken for a match starting at 0.
+#[pyfunction(signature = (seq1, seq2, floor=0, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_is_match(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    floor: i32,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(PairDetails, bool)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| smith_waterman::smith_waterman(&seq1, &seq2, params))?;
+    Ok((pair_details(&alignment), alignment.is_match(floor)))
+}
+
+/// `align_pair_details` plus the start's diagonal offset,
+/// `token_start - query_start`.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_diagonal(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(PairDetails, i64)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| smith_waterman::smith_waterman(&seq1, &seq2, params))?;
+    Ok((pair_details(&alignment), alignment.diagonal()))
+}
+
+/// Joins two `align_pair_details` results on the same sequence pair whose
+/// spans touch or overlap; see `smith_waterman::merge_alignments`.
+#[pyfunction]
+fn merge_alignments(a: PairDetails, b: PairDetails) -> PyResult<PairDetails> {
+    let alignment = |(score, token_start, token_end, query_start, query_end, matches)| {
+        smith_waterman::Alignment {
+            score,
+            query_start,
+            query_end,
+            token_start,
+            token_end,
+            matches,
+        }
+    };
+    let merged = smith_waterman::merge_alignments(&alignment(a), &alignment(b))?;
+    Ok(pair_details(&merged))
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_coverage(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(PairDetails, f64, f64)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| smith_waterman::smith_waterman(&seq1, &seq2, params))?;
+    Ok((
+        pair_details(&alignment),
+        alignment.query_coverage(seq1.len()),
+        alignment.reference_coverage(seq2.len()),
+    ))
+}
+
+/// `align_pair_details` with an explicit contract for empty input:
+/// `on_empty="none"` returns `None`, `on_empty="raise"` raises `ValueError`.
+#[pyfunction(signature = (seq1, seq2, on_empty="none", match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_checked(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    on_empty: &str,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<Option<PairDetails>> {
+    let raise = match on_empty {
+        "none" => false,
+        "raise" => true,
+        other => {
+            return Err(error::AlignError::InvalidArgument(format!(
+                "on_empty must be \"none\" or \"raise\", got {other:?}"
+            ))
+            .into());
+        }
+    };
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| {
+        smith_waterman::smith_waterman_checked(
+            &seq1,
+            &seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )
+    })?;
+    if alignment.is_none() && raise {
+        let side = match (seq1.is_empty(), seq2.is_empty()) {
+            (true, true) => "seq1 and seq2 are",
+            (true, false) => "seq1 is",
+            _ => "seq2 is",
+        };
+        return Err(error::AlignError::InvalidArgument(format!("{side} empty")).into());
     }
-    let params = smith_waterman::ScoreParams {
+    Ok(alignment.as_ref().map(pair_details))
+}
+
+/// Number of distinct optimal alignment paths, counting every tied move, up
+/// to `cap` (`ties::DEFAULT_CO_OPTIMAL_CAP` by default, `u64::MAX` when
+/// `None`), as an ambiguity signal for one pair.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, end_gap_discount=0, mask=None, cap=Some(ties::DEFAULT_CO_OPTIMAL_CAP)))]
+fn count_optimal_alignments(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+    cap: Option<u64>,
+) -> PyResult<u64> {
+    let params = score_params(
+        py,
         match_score,
         mismatch_score,
         gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        0,
+        end_gap_discount,
+    )?;
+    let constraints = smith_waterman::Constraints {
+        mask: mask.unwrap_or_default(),
+        ..Default::default()
     };
-    py.detach(|| {
-        smith_waterman::align_topk(&seq1, &seqs, params, top_k)
-            .into_iter()
-            .map(|item| {
-                (
-                    item.score,
-                    item.index,
-                    item.token_start,
-                    item.token_end,
-                    item.query_start,
-                    item.query_end,
-                    item.matches,
-                )
-            })
-            .collect()
-    })
+    let cap = cap.unwrap_or(u64::MAX);
+    Ok(py.detach(|| ties::count_optimal_alignments(&seq1, &seq2, &params, &constraints, cap))?)
+}
+
+/// Every distinct alignment tied at the maximum score, canonical first.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, mask=None))]
+fn align_pair_all_max(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+) -> PyResult<Vec<PairDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let constraints = smith_waterman::Constraints {
+        mask: mask.unwrap_or_default(),
+        ..Default::default()
+    };
+    let all =
+        py.detach(|| smith_waterman::smith_waterman_all_max(&seq1, &seq2, &params, &constraints))?;
+    Ok(all.iter().map(pair_details).collect())
+}
+
+/// `align_pair_details` of the weakest positive-scoring non-overlapping
+/// region, or `None` when nothing scores above zero.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_min_positive(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<Option<PairDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| regions::smith_waterman_min_positive(&seq1, &seq2, &params))?;
+    Ok(alignment.as_ref().map(pair_details))
+}
+
+#[pyfunction(signature = (seq1, seq2, n, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_nth(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    n: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<Option<PairDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| regions::smith_waterman_nth(&seq1, &seq2, &params, n))?;
+    Ok(alignment.as_ref().map(pair_details))
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, mask=None))]
+fn align_pair_stats(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    mask: Option<Vec<(usize, usize)>>,
+) -> PyResult<(PairDetails, (usize, usize))> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let constraints = smith_waterman::Constraints {
+        mask: mask.unwrap_or_default(),
+        ..Default::default()
+    };
+    let (alignment, stats) =
+        py.detach(|| smith_waterman::smith_waterman_stats(&seq1, &seq2, &params, &constraints))?;
+    Ok((
+        pair_details(&alignment),
+        (stats.cells_computed, stats.traceback_steps),
+    ))
+}
+
+/// `align_pair_details` calling `on_row(row, rows, best_score, best_column,
+/// max_score)` after each DP row, for debugging a scoring scheme. Holds the
+/// GIL throughout; the first exception raised by `on_row` stops further calls
+/// and is re-raised once the DP finishes.
+#[pyfunction(signature = (seq1, seq2, on_row, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_observed(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    on_row: Bound<'_, PyAny>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let mut failure = None;
+    let alignment = smith_waterman::smith_waterman_observed(
+        &seq1,
+        &seq2,
+        &params,
+        &smith_waterman::Constraints::default(),
+        |progress| {
+            if failure.is_some() {
+                return;
+            }
+            let args = (
+                progress.row,
+                progress.rows,
+                progress.best_score,
+                progress.best_column,
+                progress.max_score,
+            );
+            if let Err(error) = on_row.call1(args) {
+                failure = Some(error);
+            }
+        },
+    )?;
+    if let Some(error) = failure {
+        return Err(error);
+    }
+    Ok(pair_details(&alignment))
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_matrix<'py>(
+    py: Python<'py>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(PairDetails, arrays::Matrix<'py>)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (alignment, dense) = py.detach(|| {
+        smith_waterman::smith_waterman_matrix(
+            &seq1,
+            &seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )
+    })?;
+    let matrix = arrays::matrix(py, dense.rows, dense.cols, dense.scores)?;
+    Ok((pair_details(&alignment), matrix))
+}
+
+/// Best DP score ending on each token of `seq2`, without the full matrix of
+/// `align_pair_matrix`.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn column_maxima<'py>(
+    py: Python<'py>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<arrays::Vector<'py>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let maxima = py.detach(|| smith_waterman::column_maxima(&seq1, &seq2, &params))?;
+    Ok(arrays::vector(py, maxima))
+}
+
+/// `align_pair_details` plus the DP score of its end cell, for checking
+/// traceback against the reported score.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_end_score(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(PairDetails, i32)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (alignment, end_score) = py.detach(|| {
+        smith_waterman::smith_waterman_end_score(
+            &seq1,
+            &seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )
+    })?;
+    Ok((pair_details(&alignment), end_score))
+}
+
+#[pyfunction(signature = (seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn pairwise_score_matrix<'py>(
+    py: Python<'py>,
+    seqs: Vec<Vec<u32>>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<arrays::Matrix<'py>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let scores = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::pairwise_scores(&seqs, &params)
+        })
+    })?;
+    arrays::matrix(py, seqs.len(), seqs.len(), scores)
+}
+
+/// Match, mismatch and gap counts of the `align_pair_details` alignment.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_summary(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<summary::AlignmentSummary> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    Ok(py.detach(|| {
+        summary::summarize(
+            &seq1,
+            &seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )
+    })?)
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_aligned_tokens(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<GappedColumns> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    py.detach(|| {
+        let (alignment, path) = smith_waterman::smith_waterman_path(
+            &seq1,
+            &seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )?;
+        Ok(smith_waterman::aligned_tokens(
+            &seq1, &seq2, &alignment, &path,
+        ))
+    })
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_matched_tokens(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<Vec<u32>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    py.detach(|| {
+        let (alignment, path) = smith_waterman::smith_waterman_path(
+            &seq1,
+            &seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )?;
+        Ok(smith_waterman::matched_tokens(
+            &seq1, &seq2, &alignment, &path, &params,
+        ))
+    })
+}
+
+/// `(label, details)`: `align_pair_details` and its `"exact"`, `"strong"`,
+/// `"partial"` or `"none"` label under the given cutoffs.
+#[pyfunction(signature = (seq1, seq2, min_score=1, strong_identity=90.0, strong_coverage=0.8, partial_coverage=0.3, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn classify_match(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    min_score: i32,
+    strong_identity: f64,
+    strong_coverage: f64,
+    partial_coverage: f64,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(&'static str, PairDetails)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let thresholds = classify::MatchThresholds {
+        min_score,
+        strong_identity,
+        strong_coverage,
+        partial_coverage,
+    };
+    let (label, alignment) =
+        py.detach(|| classify::classify_match(&seq1, &seq2, &params, &thresholds))?;
+    Ok((label.as_str(), pair_details(&alignment)))
+}
+
+/// `(token_start, token_end, query_start, query_end)` from the first to the
+/// last match of the best alignment, or `None` without a match.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, numeric_tolerance=0))]
+fn align_pair_matched_span(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    numeric_tolerance: u32,
+) -> PyResult<Option<(usize, usize, usize, usize)>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let scoring = scoring::NumericScoring {
+        params,
+        tolerance: numeric_tolerance,
+    };
+    let span = py.detach(|| {
+        let (alignment, path) = smith_waterman::smith_waterman_path(
+            &seq1,
+            &seq2,
+            &scoring,
+            &smith_waterman::Constraints::default(),
+        )?;
+        Ok::<_, error::AlignError>(smith_waterman::matched_span(
+            &seq1, &seq2, &alignment, &path, &scoring,
+        ))
+    })?;
+    Ok(span.map(|span| {
+        (
+            span.token_start,
+            span.token_end,
+            span.query_start,
+            span.query_end,
+        )
+    }))
+}
+
+#[pyfunction(signature = (seq1, seq2, complement, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_stranded(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    complement: HashMap<u32, u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(i32, usize, usize, usize, usize, usize, &'static str)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (alignment, strand) =
+        py.detach(|| strand::smith_waterman_stranded(&seq1, &seq2, params, &complement))?;
+    Ok((
+        alignment.score,
+        alignment.token_start,
+        alignment.token_end,
+        alignment.query_start,
+        alignment.query_end,
+        alignment.matches,
+        strand.symbol(),
+    ))
+}
+
+#[pyfunction(signature = (seq1, corpus, boundaries, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_segmented(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    corpus: Vec<u32>,
+    boundaries: Vec<usize>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(i32, usize, usize, usize, usize, usize, usize)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (alignment, segment) =
+        py.detach(|| segments::smith_waterman_segmented(&seq1, &corpus, &boundaries, &params))?;
+    Ok((
+        alignment.score,
+        alignment.token_start,
+        alignment.token_end,
+        alignment.query_start,
+        alignment.query_end,
+        alignment.matches,
+        segment,
+    ))
+}
+
+#[pyfunction(signature = (seq1, seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None, min_query_coverage=0.0, max_gaps=None, min_columns=0, release_gil=true))]
+fn align_best(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+    min_query_coverage: f64,
+    max_gaps: Option<usize>,
+    min_columns: usize,
+    release_gil: bool,
+) -> PyResult<Option<(i32, usize, usize, usize)>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let best = detach_if(py, release_gil, || {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_topk_within(
+                &seq1,
+                &seqs,
+                &params,
+                1,
+                min_query_coverage,
+                max_gaps,
+                min_columns,
+            )
+        })
+    })?;
+    Ok(best
+        .first()
+        .map(|best| (best.score, best.index, best.token_start, best.token_end)))
+}
+
+#[pyfunction(signature = (seq1, seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None, min_query_coverage=0.0, max_gaps=None, min_columns=0, release_gil=true))]
+fn align_best_details(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+    min_query_coverage: f64,
+    max_gaps: Option<usize>,
+    min_columns: usize,
+    release_gil: bool,
+) -> PyResult<Option<AlignmentDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let best = detach_if(py, release_gil, || {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_topk_within(
+                &seq1,
+                &seqs,
+                &params,
+                1,
+                min_query_coverage,
+                max_gaps,
+                min_columns,
+            )
+        })
+    })?;
+    Ok(best.first().map(candidate_details))
+}
+
+/// `align_best_details` plus the winning reference's match blocks, traced
+/// back for the winner only.
+#[pyfunction(signature = (seq1, seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None, release_gil=true))]
+fn align_best_blocks_details(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+    release_gil: bool,
+) -> PyResult<Option<CandidateWithBlocks>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let best = detach_if(py, release_gil, || {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_best_with_blocks(&seq1, &seqs, &params)
+        })
+    })?;
+    Ok(best.map(|(best, blocks)| {
+        let (score, index, token_start, token_end, query_start, query_end, matches) =
+            candidate_details(&best);
+        (
+            score,
+            index,
+            token_start,
+            token_end,
+            query_start,
+            query_end,
+            matches,
+            blocks,
+        )
+    }))
+}
+
+/// `align_best_details` for each of `queries`, in query order.
+#[pyfunction(signature = (queries, seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_best_batch(
+    py: Python<'_>,
+    queries: Vec<Vec<u32>>,
+    seqs: Vec<Vec<u32>>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<Option<AlignmentDetails>>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let batch = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_best_batch(&queries, &seqs, params)
+        })
+    })?;
+    Ok(batch
+        .iter()
+        .map(|best| best.as_ref().map(candidate_details))
+        .collect())
+}
+
+/// Writes each reference's best local score against `seq1` into `out`, which
+/// must have one slot per reference. Reusing `out` across calls avoids
+/// allocating a result per query.
+#[pyfunction(signature = (seq1, seqs, out, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_all_scores_into(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    out: arrays::ScoresOut<'_>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<()> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    arrays::fill_scores(py, out, |scores| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_all_scores_into(&seq1, &seqs, &params, scores)
+        })
+    })
+}
+
+/// `align_best_details` of the highest-scoring reference within each bucket
+/// `bucket_edges[k] <= score < bucket_edges[k + 1]`, or `None` for an empty
+/// bucket.
+#[pyfunction(signature = (seq1, seqs, bucket_edges, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_best_per_bucket(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    bucket_edges: Vec<i32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<Option<AlignmentDetails>>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let best = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_best_per_bucket(&seq1, &seqs, &bucket_edges, &params)
+        })
+    })?;
+    Ok(best
+        .iter()
+        .map(|item| item.as_ref().map(candidate_details))
+        .collect())
+}
+
+#[pyfunction(signature = (seq1, seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_best_with_margin(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<(Option<AlignmentDetails>, Option<i32>)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (best, runner_up) = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_best_with_margin(&seq1, &seqs, &params)
+        })
+    })?;
+    Ok((best.as_ref().map(candidate_details), runner_up))
+}
+
+#[pyfunction(signature = (seq1, seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_best_json(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<String> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let best = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_best(&seq1, &seqs, params)
+        })
+    })?;
+    Ok(json::candidate_to_json(best.as_ref()))
+}
+
+/// `align_topk_details` for every reference scoring at least `min_score`,
+/// with no `top_k` cap.
+#[pyfunction(signature = (seq1, seqs, min_score, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_above_threshold(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    min_score: i32,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<AlignmentDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let above = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_above_threshold(&seq1, &seqs, &params, min_score)
+        })
+    })?;
+    Ok(above.iter().map(candidate_details).collect())
+}
+
+/// `(cutoff, details)`: the `percentile`-th percentile of every reference's
+/// best score, and `align_topk_details` for the references scoring above it.
+#[pyfunction(signature = (seq1, seqs, percentile, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_above_percentile(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    percentile: f64,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<(i32, Vec<AlignmentDetails>)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (cutoff, above) = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_above_percentile(&seq1, &seqs, &params, percentile)
+        })
+    })?;
+    Ok((cutoff, above.iter().map(candidate_details).collect()))
+}
+
+/// `(details, scored)`: `align_topk_details` of a reference scoring at least
+/// `target_score`, or `None`, and how many references were scored before the
+/// scan stopped.
+#[pyfunction(signature = (seq1, seqs, target_score, in_order=true, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_first_above(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    target_score: i32,
+    in_order: bool,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<(Option<AlignmentDetails>, usize)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (first, scored) = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_first_above(&seq1, &seqs, &params, target_score, in_order)
+        })
+    })?;
+    Ok((first.as_ref().map(candidate_details), scored))
+}
+
+/// `(details, aligned)`: `align_topk_details` computed once per distinct
+/// reference, and how many distinct references were aligned.
+#[pyfunction(signature = (seq1, seqs, top_k=1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_topk_deduped(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    top_k: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<(Vec<AlignmentDetails>, usize)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (top, aligned) = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_topk_deduped(&seq1, &seqs, &params, top_k)
+        })
+    })?;
+    Ok((top.iter().map(candidate_details).collect(), aligned))
+}
+
+/// `align_topk_details` as one `(k, 7)` `int64` array, one row per result in
+/// the tuple's field order, to avoid a Python object per result.
+#[pyfunction(signature = (seq1, seqs, top_k=1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_topk_packed<'py>(
+    py: Python<'py>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    top_k: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<arrays::Matrix<'py, i64>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let top = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_topk_with(&seq1, &seqs, &params, top_k)
+        })
+    })?;
+    let fields: Vec<i64> = top
+        .iter()
+        .flat_map(|item| {
+            [
+                i64::from(item.score),
+                item.index as i64,
+                item.token_start as i64,
+                item.token_end as i64,
+                item.query_start as i64,
+                item.query_end as i64,
+                item.matches as i64,
+            ]
+        })
+        .collect();
+    arrays::matrix(py, top.len(), 7, fields)
+}
+
+/// `align_topk_details` plus each reference's DP time in seconds, indexed
+/// like `seqs`, for profiling slow references.
+#[pyfunction(signature = (seq1, seqs, top_k=1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_topk_timed(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    top_k: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<(Vec<AlignmentDetails>, Vec<f64>)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (top, durations) = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_topk_timed(&seq1, &seqs, &params, top_k)
+        })
+    })?;
+    Ok((
+        top.iter().map(candidate_details).collect(),
+        durations
+            .iter()
+            .map(|elapsed| elapsed.as_secs_f64())
+            .collect(),
+    ))
+}
+
+#[pyfunction(signature = (seq1, seqs, top_k=1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None, stopwords=None, stopword_match_score=1, min_query_coverage=0.0, drop_zero=false, max_gaps=None, min_columns=0, sort_key="score", ref_weights=None, release_gil=true))]
+fn align_topk_details(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    top_k: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+    stopwords: Option<HashSet<u32>>,
+    stopword_match_score: i32,
+    min_query_coverage: f64,
+    drop_zero: bool,
+    max_gaps: Option<usize>,
+    min_columns: usize,
+    sort_key: &str,
+    ref_weights: Option<Vec<f64>>,
+    release_gil: bool,
+) -> PyResult<Vec<AlignmentDetails>> {
+    let sort_key = self::sort_key(sort_key)?;
+    if let Some(ref_weights) = &ref_weights {
+        smith_waterman::check_ref_weights(ref_weights, seqs.len())?;
+    }
+    if top_k == 0 || seqs.is_empty() {
+        return Ok(Vec::new());
+    }
+    // Weighting can promote any reference, so every one is kept for it.
+    let selected = if ref_weights.is_some() {
+        seqs.len()
+    } else {
+        top_k
+    };
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let stopwords = stopwords.unwrap_or_default();
+    let mut top = detach_if(py, release_gil, || {
+        parallel::with_thread_pool(num_threads, || {
+            if stopwords.is_empty() {
+                return smith_waterman::align_topk_within(
+                    &seq1,
+                    &seqs,
+                    &params,
+                    selected,
+                    min_query_coverage,
+                    max_gaps,
+                    min_columns,
+                );
+            }
+            let scoring = scoring::StopwordScoring {
+                params,
+                stopwords: &stopwords,
+                stopword_match_score,
+            };
+            smith_waterman::align_topk_within(
+                &seq1,
+                &seqs,
+                &scoring,
+                selected,
+                min_query_coverage,
+                max_gaps,
+                min_columns,
+            )
+        })
+    })?;
+    if let Some(ref_weights) = &ref_weights {
+        top = smith_waterman::rank_weighted(top, ref_weights, top_k);
+    }
+    if drop_zero {
+        // Zero scores sort last, so dropping them after the cut loses nothing.
+        top.retain(|item| item.score > 0);
+    }
+    // With weights, the default order is the weighted ranking itself.
+    if ref_weights.is_none() || sort_key != smith_waterman::SortKey::Score {
+        smith_waterman::sort_candidates(&mut top, sort_key);
+    }
+    Ok(top.iter().map(candidate_details).collect())
+}
+
+/// `align_pair_details` scoring each diagonal step as `matrix[a][b]`, for a
+/// dense substitution matrix over token ids `0..len(matrix)`.
+#[pyfunction(signature = (seq1, seq2, matrix, gap_score=GapScore::Linear(-1), gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_submatrix(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    matrix: arrays::MatrixArg<'_>,
+    gap_score: GapScore,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
+        2,
+        -1,
+        gap_score,
+        None,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (rows, cols, scores) = arrays::matrix_data(matrix)?;
+    let alignment = py.detach(|| {
+        let scoring = scoring::MatrixScoring::new(params, rows, cols, &scores)?;
+        scoring.check_tokens(&seq1)?;
+        scoring.check_tokens(&seq2)?;
+        smith_waterman::smith_waterman_with(
+            &seq1,
+            &seq2,
+            &scoring,
+            &smith_waterman::Constraints::default(),
+        )
+    })?;
+    Ok(pair_details(&alignment))
+}
+
+/// `align_pair_details` over byte tokens, read straight from `bytes` without
+/// widening each token to `u32`.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_bytes(
+    py: Python<'_>,
+    seq1: &[u8],
+    seq2: &[u8],
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| {
+        smith_waterman::smith_waterman_tokens(
+            seq1,
+            seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )
+    })?;
+    Ok(pair_details(&alignment))
+}
+
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn align_pair_u64(
+    py: Python<'_>,
+    seq1: Vec<u64>,
+    seq2: Vec<u64>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let alignment = py.detach(|| {
+        smith_waterman::smith_waterman_tokens(
+            &seq1,
+            &seq2,
+            &params,
+            &smith_waterman::Constraints::default(),
+        )
+    })?;
+    Ok(pair_details(&alignment))
+}
+
+#[pyfunction(signature = (seq1, seqs, top_k=1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_topk_u64(
+    py: Python<'_>,
+    seq1: Vec<u64>,
+    seqs: Vec<Vec<u64>>,
+    top_k: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<AlignmentDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let top = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_topk_tokens(&seq1, &seqs, &params, top_k)
+        })
+    })?;
+    Ok(top.iter().map(candidate_details).collect())
+}
+
+#[pyfunction(signature = (seq1, seqs, top_k=1, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_topk_regions(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    top_k: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<AlignmentDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let top = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            regions::align_topk_regions(&seq1, &seqs, &params, top_k)
+        })
+    })?;
+    Ok(top.iter().map(candidate_details).collect())
+}
+
+/// Banded extension rightwards from `(query_pos, token_pos)`, as
+/// `(score, token_start, token_end, query_start, query_end)`.
+#[pyfunction(signature = (seq1, seq2, query_pos, token_pos, band=16, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None))]
+fn extend_right(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    query_pos: usize,
+    token_pos: usize,
+    band: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+) -> PyResult<ExtensionDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        0,
+        0,
+    )?;
+    let extension =
+        py.detach(|| extend::extend_right(&seq1, &seq2, query_pos, token_pos, &params, band))?;
+    Ok(extension_details(&extension))
+}
+
+/// Banded extension leftwards, ending at `(query_pos, token_pos)`; same tuple
+/// as `extend_right`.
+#[pyfunction(signature = (seq1, seq2, query_pos, token_pos, band=16, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None))]
+fn extend_left(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    query_pos: usize,
+    token_pos: usize,
+    band: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+) -> PyResult<ExtensionDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        0,
+        0,
+    )?;
+    let extension =
+        py.detach(|| extend::extend_left(&seq1, &seq2, query_pos, token_pos, &params, band))?;
+    Ok(extension_details(&extension))
+}
+
+/// Best alignment forced through the cell `(anchor_i, anchor_j)`, stitched
+/// from banded extensions on both sides; same tuple as `extend_right`.
+#[pyfunction(signature = (seq1, seq2, anchor_i, anchor_j, band=16, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None))]
+fn align_pair_anchored(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    anchor_i: usize,
+    anchor_j: usize,
+    band: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+) -> PyResult<ExtensionDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        0,
+        0,
+    )?;
+    let alignment =
+        py.detach(|| extend::align_anchored(&seq1, &seq2, anchor_i, anchor_j, &params, band))?;
+    Ok(extension_details(&alignment))
+}
+
+/// `(query_start, query_end, matches)` of the `align_pair_details` alignment
+/// of `query` against an already known `reference_window`.
+#[pyfunction(signature = (query, reference_window, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0))]
+fn locate_in_query(
+    py: Python<'_>,
+    query: Vec<u32>,
+    reference_window: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+) -> PyResult<(usize, usize, usize)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let location =
+        py.detach(|| smith_waterman::locate_in_query(&query, &reference_window, &params))?;
+    Ok((location.query_start, location.query_end, location.matches))
+}
+
+/// `align_pair_details` with convex gaps: a gap of `L` tokens scores
+/// `gap_open + gap_extend * ln(L)`, rounded. Slower than the linear and affine
+/// schemes; see `gap_cost::smith_waterman_gap_cost`.
+#[pyfunction(signature = (seq1, seq2, gap_open=-3, gap_extend=-1, match_score=2, mismatch_score=-1, wildcard=None))]
+fn align_pair_convex(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    gap_open: i32,
+    gap_extend: i32,
+    match_score: i32,
+    mismatch_score: i32,
+    wildcard: Option<u32>,
+) -> PyResult<PairDetails> {
+    // The gap fields are replaced by `gap_cost`; `gap_open` only sets them
+    // for validation.
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        GapScore::Linear(gap_open),
+        wildcard,
+        None,
+        None,
+        0,
+        0,
+    )?;
+    let gap_cost = gap_cost::GapCost::Convex {
+        open: gap_open,
+        extend: gap_extend,
+    };
+    let alignment =
+        py.detach(|| gap_cost::smith_waterman_gap_cost(&seq1, &seq2, &params, gap_cost))?;
+    Ok(pair_details(&alignment))
+}
+
+/// `(details, window_start)` of the best alignment of `query` within any
+/// `window_len`-token window of `reference`, windows starting every `step`
+/// tokens. Coordinates are in the `reference` frame.
+#[pyfunction(signature = (query, reference, window_len, step, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_best_over_windows(
+    py: Python<'_>,
+    query: Vec<u32>,
+    reference: Vec<u32>,
+    window_len: usize,
+    step: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<(PairDetails, usize)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let (alignment, window_start) = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            segments::align_best_over_windows(&query, &reference, window_len, step, &params)
+        })
+    })?;
+    Ok((pair_details(&alignment), window_start))
+}
+
+/// Approximate `align_pair_details` for very long inputs: exact seeds of
+/// `seed_len` tokens are chained and only the gaps between them aligned, see
+/// `chain::align_pair_chained`.
+#[pyfunction(signature = (seq1, seq2, seed_len=8, max_gap=64, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None))]
+fn align_pair_chained(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    seed_len: usize,
+    max_gap: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+) -> PyResult<PairDetails> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        0,
+        0,
+    )?;
+    let alignment =
+        py.detach(|| chain::align_pair_chained(&seq1, &seq2, &params, seed_len, max_gap))?;
+    Ok(pair_details(&alignment))
+}
+
+/// Best overlap of a suffix of `seq1` with a prefix of `seq2`, as
+/// `(score, query_len, reference_len)`: the overlap covers `seq1[-query_len:]`
+/// and `seq2[:reference_len]`.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None))]
+fn align_overlap(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+) -> PyResult<(i32, usize, usize)> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        0,
+        0,
+    )?;
+    let overlap = py.detach(|| overlap::align_overlap(&seq1, &seq2, &params))?;
+    Ok((overlap.score, overlap.query_len, overlap.reference_len))
+}
+
+/// Identity of the global alignment of `seq1` and `seq2`, end gaps included,
+/// as a fraction in `0.0..=1.0`.
+#[pyfunction(signature = (seq1, seq2, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None))]
+fn global_identity(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seq2: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+) -> PyResult<f64> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        0,
+        0,
+    )?;
+    Ok(py.detach(|| global::global_identity(&seq1, &seq2, &params))?)
+}
+
+/// `(details, variant_index)` of the best-aligning query variant against one
+/// reference, or `None` without variants.
+#[pyfunction(signature = (variants, reference, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_variants_best(
+    py: Python<'_>,
+    variants: Vec<Vec<u32>>,
+    reference: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Option<(PairDetails, usize)>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let best = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_variants_best(&variants, &reference, &params)
+        })
+    })?;
+    Ok(best.map(|(alignment, index)| (pair_details(&alignment), index)))
+}
+
+/// `align_pair_details` of every query against one reference, in query order.
+#[pyfunction(signature = (queries, reference, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_queries_vs_reference(
+    py: Python<'_>,
+    queries: Vec<Vec<u32>>,
+    reference: Vec<u32>,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<PairDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let all = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            smith_waterman::align_queries_vs_reference(&queries, &reference, &params)
+        })
+    })?;
+    Ok(all.iter().map(pair_details).collect())
+}
+
+/// Min-hash signature of every reference, for `align_topk_approx`.
+#[pyfunction(signature = (seqs, kmer_size=3, num_hashes=64, num_threads=None))]
+fn minhash_signatures(
+    py: Python<'_>,
+    seqs: Vec<Vec<u32>>,
+    kmer_size: usize,
+    num_hashes: usize,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<Vec<u64>>> {
+    Ok(py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            minhash::minhash_signatures(&seqs, kmer_size, num_hashes)
+        })
+    })?)
+}
+
+/// `align_topk_details` restricted to the `candidate_budget` references whose
+/// min-hash signatures best match the query. Approximate: may miss matches.
+#[pyfunction(signature = (seq1, seqs, signatures, top_k=1, candidate_budget=100, kmer_size=3, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+fn align_topk_approx(
+    py: Python<'_>,
+    seq1: Vec<u32>,
+    seqs: Vec<Vec<u32>>,
+    signatures: Vec<Vec<u64>>,
+    top_k: usize,
+    candidate_budget: usize,
+    kmer_size: usize,
+    match_score: i32,
+    mismatch_score: i32,
+    gap_score: GapScore,
+    wildcard: Option<u32>,
+    gap_query: Option<i32>,
+    gap_ref: Option<i32>,
+    run_bonus: i32,
+    end_gap_discount: i32,
+    num_threads: Option<usize>,
+) -> PyResult<Vec<AlignmentDetails>> {
+    let params = score_params(
+        py,
+        match_score,
+        mismatch_score,
+        gap_score,
+        wildcard,
+        gap_query,
+        gap_ref,
+        run_bonus,
+        end_gap_discount,
+    )?;
+    let top = py.detach(|| {
+        parallel::with_thread_pool(num_threads, || {
+            minhash::align_topk_approx(
+                &seq1,
+                &seqs,
+                &signatures,
+                &params,
+                top_k,
+                candidate_budget,
+                kmer_size,
+            )
+        })
+    })?;
+    Ok(top.iter().map(candidate_details).collect())
 }
 
 #[pymodule]
 fn _core(module: &Bound<'_, PyModule>) -> PyResult<()> {
     module.add_function(wrap_pyfunction!(align_pair, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_str, module)?)?;
+    module.add_function(wrap_pyfunction!(tokenize_text, module)?)?;
     module.add_function(wrap_pyfunction!(align_pair_details, module)?)?;
     module.add_function(wrap_pyfunction!(align_pair_blocks_details, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_matrix, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_aligned_tokens, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_stranded, module)?)?;
     module.add_function(wrap_pyfunction!(align_best, module)?)?;
     module.add_function(wrap_pyfunction!(align_best_details, module)?)?;
+    module.add_function(wrap_pyfunction!(align_best_json, module)?)?;
     module.add_function(wrap_pyfunction!(align_topk_details, module)?)?;
+    module.add_function(wrap_pyfunction!(align_topk_regions, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_segmented, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_f64, module)?)?;
+    module.add_function(wrap_pyfunction!(align_best_with_margin, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_block_counts_details, module)?)?;
+    module.add_function(wrap_pyfunction!(pairwise_score_matrix, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_ungapped, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_matched_tokens, module)?)?;
+    module.add_function(wrap_pyfunction!(score_preset, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_stats, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_nth, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_checked, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_u64, module)?)?;
+    module.add_function(wrap_pyfunction!(align_topk_u64, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_coverage, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_banded, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_cfg, module)?)?;
+    module.add_function(wrap_pyfunction!(align_topk_cfg, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_all_max, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_clips, module)?)?;
+    module.add_function(wrap_pyfunction!(minhash_signatures, module)?)?;
+    module.add_function(wrap_pyfunction!(align_topk_approx, module)?)?;
+    module.add_function(wrap_pyfunction!(align_queries_vs_reference, module)?)?;
+    module.add_function(wrap_pyfunction!(extend_right, module)?)?;
+    module.add_function(wrap_pyfunction!(extend_left, module)?)?;
+    module.add_function(wrap_pyfunction!(score_bounds, module)?)?;
+    module.add_function(wrap_pyfunction!(align_best_batch, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_is_match, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_bytes, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_diagonal, module)?)?;
+    module.add_function(wrap_pyfunction!(align_best_blocks_details, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_anchored, module)?)?;
+    module.add_function(wrap_pyfunction!(align_overlap, module)?)?;
+    module.add_function(wrap_pyfunction!(align_best_diagonal_prefilter, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_end_score, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_summary, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_submatrix, module)?)?;
+    module.add_function(wrap_pyfunction!(merge_alignments, module)?)?;
+    module.add_function(wrap_pyfunction!(align_topk_timed, module)?)?;
+    module.add_function(wrap_pyfunction!(analyze_tokens, module)?)?;
+    module.add_function(wrap_pyfunction!(align_variants_best, module)?)?;
+    module.add_function(wrap_pyfunction!(global_identity, module)?)?;
+    module.add_function(wrap_pyfunction!(align_all_scores_into, module)?)?;
+    module.add_function(wrap_pyfunction!(align_best_per_bucket, module)?)?;
+    module.add_function(wrap_pyfunction!(locate_in_query, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_convex, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_block_scores, module)?)?;
+    module.add_function(wrap_pyfunction!(align_topk_deduped, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_chained, module)?)?;
+    module.add_function(wrap_pyfunction!(column_maxima, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_min_positive, module)?)?;
+    module.add_function(wrap_pyfunction!(align_best_over_windows, module)?)?;
+    module.add_function(wrap_pyfunction!(align_above_threshold, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_observed, module)?)?;
+    module.add_function(wrap_pyfunction!(align_topk_packed, module)?)?;
+    module.add_function(wrap_pyfunction!(align_above_percentile, module)?)?;
+    module.add_function(wrap_pyfunction!(align_first_above, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_matched_span, module)?)?;
+    module.add_function(wrap_pyfunction!(classify_match, module)?)?;
+    module.add_function(wrap_pyfunction!(align_pair_block_weights, module)?)?;
+    module.add_function(wrap_pyfunction!(count_optimal_alignments, module)?)?;
+    module.add_class::<index::ReferenceIndex>()?;
+    module.add_class::<incremental::IncrementalAligner>()?;
+    module.add_class::<config::AlignConfig>()?;
+    module.add_class::<stream::AlignStream>()?;
+    module.add_class::<summary::AlignmentSummary>()?;
     Ok(())
 }
@@ -0,0 +1,162 @@
+//! Min-hash prefiltering for corpora too large to align exhaustively.
+
+use rayon::prelude::*;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    CandidateAlignment, Constraints, Scoring, cmp_candidate, smith_waterman_with,
+};
+
+/// Final step of splitmix64: a cheap, well-mixed and stable 64-bit hash.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// `num_hashes` minimum hash values over the `kmer_size`-token shingles of `seq`.
+///
+/// Hashes are seeded deterministically, so signatures computed in different
+/// processes are comparable. A sequence shorter than `kmer_size` is hashed as a
+/// single shingle; an empty one gets an all-`u64::MAX` signature that matches
+/// nothing else.
+pub fn minhash_signature(
+    seq: &[u32],
+    kmer_size: usize,
+    num_hashes: usize,
+) -> Result<Vec<u64>, AlignError> {
+    if kmer_size == 0 || num_hashes == 0 {
+        return Err(AlignError::InvalidArgument(
+            "kmer_size and num_hashes must be at least 1".to_string(),
+        ));
+    }
+    let shingles: Vec<u64> = if seq.is_empty() {
+        Vec::new()
+    } else {
+        seq.windows(kmer_size.min(seq.len()))
+            .map(|shingle| {
+                shingle
+                    .iter()
+                    .fold(0u64, |hash, &token| mix(hash ^ u64::from(token)))
+            })
+            .collect()
+    };
+    Ok((0..num_hashes as u64)
+        .map(|seed| {
+            let salt = mix(seed.wrapping_add(0x9e37_79b9_7f4a_7c15));
+            shingles
+                .iter()
+                .map(|&shingle| mix(shingle ^ salt))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect())
+}
+
+/// `minhash_signature` of every reference, computed in parallel.
+pub fn minhash_signatures(
+    seqs: &[Vec<u32>],
+    kmer_size: usize,
+    num_hashes: usize,
+) -> Result<Vec<Vec<u64>>, AlignError> {
+    seqs.par_iter()
+        .map(|seq| minhash_signature(seq, kmer_size, num_hashes))
+        .collect()
+}
+
+/// Estimated Jaccard similarity of two shingle sets: the fraction of
+/// signature slots that agree.
+pub fn estimate_jaccard(left: &[u64], right: &[u64]) -> f64 {
+    if left.is_empty() {
+        return 0.0;
+    }
+    let agree = left
+        .iter()
+        .zip(right)
+        .filter(|(a, b)| a == b && **a != u64::MAX)
+        .count();
+    agree as f64 / left.len() as f64
+}
+
+/// Approximate `align_topk`: only the `candidate_budget` references whose
+/// signatures are most similar to the query's are aligned.
+///
+/// `signatures[i]` must come from `minhash_signature(&seqs[i], kmer_size, n)`
+/// with the same `kmer_size` and `n` for every reference. Results keep their
+/// corpus indices and `align_topk` ordering. A true match sharing few shingles
+/// with the query (low Jaccard similarity, e.g. a heavily reworded quote in a
+/// long reference) may fall outside the budget and be missed.
+pub fn align_topk_approx<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    signatures: &[Vec<u64>],
+    scoring: &S,
+    top_k: usize,
+    candidate_budget: usize,
+    kmer_size: usize,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    if signatures.len() != seqs.len() {
+        return Err(AlignError::InvalidArgument(format!(
+            "got {} signatures for {} references",
+            signatures.len(),
+            seqs.len()
+        )));
+    }
+    if seqs.is_empty() || top_k == 0 || candidate_budget == 0 {
+        return Ok(Vec::new());
+    }
+    let num_hashes = signatures[0].len();
+    if signatures
+        .iter()
+        .any(|signature| signature.len() != num_hashes)
+    {
+        return Err(AlignError::InvalidArgument(
+            "signatures must all have the same length".to_string(),
+        ));
+    }
+    let query = minhash_signature(seq1, kmer_size, num_hashes)?;
+
+    let mut ranked: Vec<(usize, f64)> = signatures
+        .par_iter()
+        .map(|signature| estimate_jaccard(&query, signature))
+        .enumerate()
+        .collect();
+    ranked.sort_by(|left, right| right.1.total_cmp(&left.1).then(left.0.cmp(&right.0)));
+    ranked.truncate(candidate_budget);
+
+    let mut results: Vec<CandidateAlignment> = ranked
+        .par_iter()
+        .map(|&(index, _)| {
+            let alignment =
+                smith_waterman_with(seq1, &seqs[index], scoring, &Constraints::default())?;
+            Ok(CandidateAlignment::from_alignment(index, &alignment))
+        })
+        .collect::<Result<_, AlignError>>()?;
+    results.sort_by(cmp_candidate);
+    results.truncate(top_k);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, align_topk};
+
+    #[test]
+    fn generous_budget_recovers_the_true_best_match() {
+        let params = ScoreParams::new(2, -1, -1);
+        let mut seqs: Vec<Vec<u32>> = (0..200u32)
+            .map(|n| (0..30).map(|t| (n * 7 + t * 13) % 500).collect())
+            .collect();
+        let query: Vec<u32> = (1000..1012).collect();
+        seqs[137].splice(8..8, query.iter().copied());
+
+        let signatures = minhash_signatures(&seqs, 3, 64).unwrap();
+        let approx = align_topk_approx(&query, &seqs, &signatures, &params, 1, 20, 3).unwrap();
+        let exact = align_topk(&query, &seqs, params, 1).unwrap();
+        assert_eq!(approx, exact);
+        assert_eq!(approx[0].index, 137);
+
+        assert!(align_topk_approx(&query, &seqs, &signatures[1..], &params, 1, 20, 3).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Overlap (dovetail) alignment of a suffix of one sequence against a prefix
+//! of the other, e.g. to stitch consecutive chunks of streamed text.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Scoring, check_score_range};
+
+/// `seq1`'s last `query_len` tokens aligned against `seq2`'s first
+/// `reference_len` tokens. An empty overlap has score 0 and both lengths 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overlap {
+    pub score: i32,
+    pub query_len: usize,
+    pub reference_len: usize,
+}
+
+/// Best overlap of a suffix of `seq1` with a prefix of `seq2`.
+///
+/// Unlike local alignment, the path must start at `seq2`'s first token and
+/// end at `seq1`'s last: only a leading part of `seq1` and a trailing part of
+/// `seq2` are free. Unlike semi-global alignment, both sequences are clipped,
+/// each on one end only. Score ties prefer the shorter `seq2` prefix, and
+/// moves tie-break like `smith_waterman` (diagonal, then up, then left).
+/// `run_bonus`, `end_gap_discount` and affine gaps are not supported.
+pub fn align_overlap<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> Result<Overlap, AlignError> {
+    let params = scoring.params();
+    if params.run_bonus != 0 || params.end_gap_discount != 0 || params.is_affine() {
+        return Err(AlignError::InvalidParams(
+            "run_bonus, end_gap_discount and gap_open are not supported for overlap alignment"
+                .to_string(),
+        ));
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    let cols = seq2.len() + 1;
+
+    // Row 0: skipping reference tokens before the query starts costs gaps.
+    // `starts[j]` is the query row the best path into column `j` began on.
+    let mut scores: Vec<i32> = (0..cols)
+        .map(|j| params.gap_ref.saturating_mul(j as i32))
+        .collect();
+    let mut starts = vec![0usize; cols];
+    for (i, &token) in seq1.iter().enumerate() {
+        let i = i + 1;
+        // Column 0: the query tokens before row `i` are skipped for free.
+        let mut row = vec![0i32; cols];
+        let mut row_starts = vec![i; cols];
+        for j in 1..cols {
+            let diag = scores[j - 1].saturating_add(scoring.substitution(token, seq2[j - 1]));
+            let up = scores[j].saturating_add(params.gap_query);
+            let left = row[j - 1].saturating_add(params.gap_ref);
+            (row[j], row_starts[j]) = if diag >= up && diag >= left {
+                (diag, starts[j - 1])
+            } else if up >= left {
+                (up, starts[j])
+            } else {
+                (left, row_starts[j - 1])
+            };
+        }
+        scores = row;
+        starts = row_starts;
+    }
+
+    let mut best = Overlap {
+        score: scores[0],
+        query_len: 0,
+        reference_len: 0,
+    };
+    for j in 1..cols {
+        if scores[j] > best.score {
+            best = Overlap {
+                score: scores[j],
+                query_len: seq1.len() - starts[j],
+                reference_len: j,
+            };
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn suffix_of_query_overlaps_prefix_of_reference() {
+        let params = ScoreParams::new(2, -1, -1);
+        let joined = align_overlap(&[5, 6, 7, 1, 2, 3], &[1, 2, 3, 8, 9], &params).unwrap();
+        assert_eq!(
+            joined,
+            Overlap {
+                score: 6,
+                query_len: 3,
+                reference_len: 3,
+            }
+        );
+
+        // A local match must still be carried to the query's end.
+        let seq1 = vec![1, 2, 3, 9];
+        let seq2 = vec![1, 2, 3];
+        assert_eq!(smith_waterman(&seq1, &seq2, params).unwrap().score, 6);
+        let carried = align_overlap(&seq1, &seq2, &params).unwrap();
+        assert_eq!(
+            (carried.score, carried.query_len, carried.reference_len),
+            (5, 4, 3)
+        );
+
+        let none = align_overlap(&[1, 2], &[3, 4], &params).unwrap();
+        assert_eq!((none.score, none.query_len, none.reference_len), (0, 0, 0));
+        assert_eq!(align_overlap(&[], &[1], &params).unwrap().score, 0);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::error::AlignError;
+
+/// Runs `op` on a dedicated rayon pool with `num_threads` workers, or on the
+/// global pool when `num_threads` is `None`.
+///
+/// Every `par_iter` inside `op` is confined to the chosen pool, so
+/// `Some(1)` forces serial execution. Building a pool per call costs a few
+/// thread spawns, which is negligible next to a corpus scan but not free for
+/// tiny inputs.
+pub fn with_thread_pool<T, F>(num_threads: Option<usize>, op: F) -> Result<T, AlignError>
+where
+    T: Send,
+    F: FnOnce() -> Result<T, AlignError> + Send,
+{
+    let Some(num_threads) = num_threads else {
+        return op();
+    };
+    if num_threads == 0 {
+        return Err(AlignError::InvalidArgument(
+            "num_threads must be at least 1".to_string(),
+        ));
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(|error| AlignError::ThreadPool(error.to_string()))?;
+    pool.install(op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{CandidateAlignment, ScoreParams, align_topk, smith_waterman};
+    use std::cmp::Reverse;
+
+    /// Deterministic xorshift generator so the corpus is identical every run.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self, bound: u32) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as u32
+        }
+    }
+
+    /// Small alphabet plus duplicated references, so score ties are common.
+    fn tie_heavy_corpus(rng: &mut XorShift) -> (Vec<u32>, Vec<Vec<u32>>) {
+        let query: Vec<u32> = (0..6).map(|_| rng.next(4)).collect();
+        let mut seqs: Vec<Vec<u32>> = Vec::new();
+        for _ in 0..200 {
+            if !seqs.is_empty() && rng.next(4) == 0 {
+                let copy = seqs[rng.next(seqs.len() as u32) as usize].clone();
+                seqs.push(copy);
+            } else {
+                let len = 1 + rng.next(12) as usize;
+                seqs.push((0..len).map(|_| rng.next(4)).collect());
+            }
+        }
+        (query, seqs)
+    }
+
+    /// Serial top-k written against the documented ordering rather than
+    /// `cmp_candidate`, so a drift in either is caught.
+    fn reference_topk(
+        seq1: &[u32],
+        seqs: &[Vec<u32>],
+        params: ScoreParams,
+        top_k: usize,
+    ) -> Vec<CandidateAlignment> {
+        let mut all: Vec<CandidateAlignment> = seqs
+            .iter()
+            .enumerate()
+            .map(|(index, seq2)| {
+                let alignment = smith_waterman(seq1, seq2, params).unwrap();
+                CandidateAlignment::from_alignment(index, &alignment)
+            })
+            .collect();
+        all.sort_by_key(|item| {
+            (
+                Reverse(item.score),
+                item.token_start,
+                Reverse(item.token_end - item.token_start),
+                item.query_start,
+                item.index,
+                item.token_end,
+                item.query_end,
+            )
+        });
+        all.truncate(top_k);
+        all
+    }
+
+    #[test]
+    fn topk_is_independent_of_scheduling() {
+        let params = ScoreParams::new(2, -1, -1);
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..8 {
+            let (query, seqs) = tie_heavy_corpus(&mut rng);
+            let expected = reference_topk(&query, &seqs, params, 25);
+            for num_threads in [None, Some(1), Some(2), Some(3), Some(8)] {
+                for _ in 0..4 {
+                    let top =
+                        with_thread_pool(num_threads, || align_topk(&query, &seqs, params, 25))
+                            .unwrap();
+                    assert_eq!(top, expected, "num_threads = {num_threads:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn serial_pool_matches_global_pool() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3];
+        let seqs: Vec<Vec<u32>> = (0..64).map(|n| vec![n % 4, 1, 2, n % 3, 3]).collect();
+
+        let global = align_topk(&seq1, &seqs, params, 10).unwrap();
+        let serial = with_thread_pool(Some(1), || align_topk(&seq1, &seqs, params, 10)).unwrap();
+        let default = with_thread_pool(None, || align_topk(&seq1, &seqs, params, 10)).unwrap();
+
+        let key = |items: &[CandidateAlignment]| {
+            items
+                .iter()
+                .map(|item| (item.score, item.index, item.token_start, item.token_end))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(key(&serial), key(&global));
+        assert_eq!(key(&default), key(&global));
+        assert!(with_thread_pool(Some(0), || Ok(())).is_err());
+    }
+}
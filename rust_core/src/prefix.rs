@@ -0,0 +1,143 @@
+//! Local alignment anchored at the query's first token, for telling whether a
+//! source contains a citation's exact opening.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, Scoring, check_score_range, cmp_alignment, empty_alignment,
+};
+
+const UNREACHABLE: i32 = i32::MIN / 2;
+
+const STOP: u8 = 0;
+const DIAG: u8 = 1;
+const UP: u8 = 2;
+const LEFT: u8 = 3;
+
+/// Best local alignment of `seq1` in `seq2` with `query_start == 0`.
+///
+/// The reference start and both ends are free, as in `smith_waterman`, but the
+/// path must open by pairing `seq1[0]` with some reference token and never
+/// resets to zero, so a poor opening is carried rather than clipped off. The
+/// result is empty when no such path scores above zero, even if a later part
+/// of the query matches well. Moves tie-break like `smith_waterman`
+/// (diagonal, then up, then left), and tied end cells like
+/// `smith_waterman_with`. `run_bonus`, `end_gap_discount` and affine gaps are
+/// not supported.
+pub fn smith_waterman_query_start<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> Result<Alignment, AlignError> {
+    let params = scoring.params();
+    if params.run_bonus != 0 || params.end_gap_discount != 0 || params.is_affine() {
+        return Err(AlignError::InvalidParams(
+            "run_bonus, end_gap_discount and gap_open are not supported with require_query_start_zero"
+                .to_string(),
+        ));
+    }
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(empty_alignment());
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+
+    let cols = seq2.len() + 1;
+    // Row 0 is free: the path may open on any reference token.
+    let mut previous = vec![0i32; cols];
+    let mut directions = vec![STOP; cols];
+    let mut max_score = 0i32;
+    let mut max_positions = Vec::new();
+    for (row, &token) in seq1.iter().enumerate() {
+        let i = row + 1;
+        let mut scores = vec![UNREACHABLE; cols];
+        directions.push(STOP);
+        for j in 1..cols {
+            let diag = previous[j - 1].saturating_add(scoring.substitution(token, seq2[j - 1]));
+            // Leaving row 0 by a query gap would skip `seq1[0]`.
+            let up = if i > 1 {
+                previous[j].saturating_add(params.gap_query)
+            } else {
+                UNREACHABLE
+            };
+            let left = scores[j - 1].saturating_add(params.gap_ref);
+            let (score, direction) = if diag >= up && diag >= left {
+                (diag, DIAG)
+            } else if up >= left {
+                (up, UP)
+            } else {
+                (left, LEFT)
+            };
+            scores[j] = score.max(UNREACHABLE);
+            directions.push(direction);
+            if score > max_score {
+                max_score = score;
+                max_positions.clear();
+            }
+            if score == max_score && score > 0 {
+                max_positions.push((i, j));
+            }
+        }
+        previous = scores;
+    }
+
+    let trace = |i_end: usize, j_end: usize| {
+        let (mut i, mut j, mut matches) = (i_end, j_end, 0usize);
+        while i > 0 {
+            match directions[i * cols + j] {
+                DIAG => {
+                    if scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+                        matches += 1;
+                    }
+                    i -= 1;
+                    j -= 1;
+                }
+                UP => i -= 1,
+                _ => j -= 1,
+            }
+        }
+        Alignment {
+            score: max_score,
+            query_start: 0,
+            query_end: i_end,
+            token_start: j,
+            token_end: j_end,
+            matches,
+        }
+    };
+    Ok(max_positions
+        .into_iter()
+        .map(|(i, j)| trace(i, j))
+        .min_by(cmp_alignment)
+        .unwrap_or_else(empty_alignment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn anchored_alignment_keeps_the_first_query_token() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![9, 1, 2, 3, 4, 5];
+        let seq2 = vec![7, 1, 2, 3, 4, 5, 9, 8];
+
+        // Unconstrained, the mismatched opening token is clipped off.
+        let local = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!(
+            (local.score, local.query_start, local.token_start),
+            (10, 1, 1)
+        );
+
+        let anchored = smith_waterman_query_start(&seq1, &seq2, &params).unwrap();
+        assert_eq!((anchored.score, anchored.matches), (9, 5));
+        assert_eq!((anchored.query_start, anchored.query_end), (0, 6));
+        assert_eq!((anchored.token_start, anchored.token_end), (0, 6));
+
+        // The opening can only mismatch the first reference token, and no
+        // path from there pays off.
+        let empty = smith_waterman_query_start(&[9, 1], &[1], &params).unwrap();
+        assert_eq!((empty.score, empty.query_end, empty.token_end), (0, 0, 0));
+        let affine = ScoreParams::affine(2, -1, -3, -1);
+        assert!(smith_waterman_query_start(&seq1, &seq2, &affine).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Several non-overlapping local alignments per reference (Waterman-Eggert).
+
+use rayon::prelude::*;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, CandidateAlignment, Constraints, Scoring, cmp_candidate, smith_waterman_with,
+};
+
+/// Extracts up to `max_regions` local alignments of `seq1` in `seq2`, best first.
+///
+/// After each alignment is found its reference span is masked and the DP is
+/// rerun, so regions never share reference tokens (they may reuse query
+/// tokens). Extraction stops once no positive-scoring alignment remains.
+/// Each region costs one full DP pass.
+pub fn smith_waterman_regions<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    max_regions: usize,
+) -> Result<Vec<Alignment>, AlignError> {
+    let mut constraints = Constraints::default();
+    let mut regions = Vec::new();
+    while regions.len() < max_regions {
+        let alignment = smith_waterman_with(seq1, seq2, scoring, &constraints)?;
+        if alignment.score <= 0 {
+            break;
+        }
+        constraints
+            .mask
+            .push((alignment.token_start, alignment.token_end));
+        regions.push(alignment);
+    }
+    Ok(regions)
+}
+
+/// The `n`-th (0-indexed) region `smith_waterman_regions` would return, or
+/// `None` when fewer than `n + 1` positive-scoring regions exist.
+pub fn smith_waterman_nth<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    n: usize,
+) -> Result<Option<Alignment>, AlignError> {
+    let regions = smith_waterman_regions(seq1, seq2, scoring, n.saturating_add(1))?;
+    Ok(regions.get(n).copied())
+}
+
+/// The weakest positive-scoring region `smith_waterman_regions` extracts, or
+/// `None` when no alignment scores above zero, e.g. for calibrating a score
+/// threshold against the least a reference offers.
+///
+/// Masking only removes options, so region scores never increase and the
+/// last region extracted is the weakest; among equally weak ones it is the
+/// last found. This runs extraction to exhaustion, one DP pass per region.
+pub fn smith_waterman_min_positive<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> Result<Option<Alignment>, AlignError> {
+    let regions = smith_waterman_regions(seq1, seq2, scoring, usize::MAX)?;
+    Ok(regions.last().copied())
+}
+
+/// Global top-k over every reference's non-overlapping regions, so one
+/// reference may contribute several results. Ordered like `align_topk`.
+pub fn align_topk_regions<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    top_k: usize,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    if seqs.is_empty() || top_k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let per_reference: Vec<Vec<CandidateAlignment>> = seqs
+        .par_iter()
+        .enumerate()
+        .map(|(index, seq2)| {
+            let regions = smith_waterman_regions(seq1, seq2, scoring, top_k)?;
+            Ok(regions
+                .into_iter()
+                .map(|alignment| CandidateAlignment::from_alignment(index, &alignment))
+                .collect())
+        })
+        .collect::<Result<_, AlignError>>()?;
+
+    let mut results: Vec<CandidateAlignment> = per_reference.into_iter().flatten().collect();
+    results.sort_by(cmp_candidate);
+    results.truncate(top_k);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::ScoreParams;
+
+    #[test]
+    fn one_reference_contributes_two_regions() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3];
+        let seqs = vec![vec![1, 2], vec![1, 2, 3, 9, 1, 2, 3], vec![7, 8]];
+
+        let top = align_topk_regions(&seq1, &seqs, &params, 3).unwrap();
+        let summary: Vec<_> = top
+            .iter()
+            .map(|item| (item.index, item.score, item.token_start, item.token_end))
+            .collect();
+        assert_eq!(summary, vec![(1, 6, 0, 3), (1, 6, 4, 7), (0, 4, 0, 2)]);
+    }
+
+    #[test]
+    fn nth_returns_the_second_region() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seq2 = vec![1, 2, 3, 9, 9, 1, 2, 3, 4];
+
+        let first = smith_waterman_nth(&seq1, &seq2, &params, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!((first.score, first.token_start, first.token_end), (8, 5, 9));
+        let second = smith_waterman_nth(&seq1, &seq2, &params, 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (second.score, second.token_start, second.token_end),
+            (6, 0, 3)
+        );
+        assert!(
+            smith_waterman_nth(&seq1, &seq2, &params, 2)
+                .unwrap()
+                .is_none()
+        );
+    }
+    #[test]
+    fn min_positive_returns_the_weaker_match() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        // A full copy scoring 8, and a two-token fragment scoring 4.
+        let seq2 = vec![9, 1, 2, 9, 9, 1, 2, 3, 4, 9];
+
+        let weakest = smith_waterman_min_positive(&seq1, &seq2, &params)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (weakest.score, weakest.token_start, weakest.token_end),
+            (4, 1, 3)
+        );
+        assert_eq!((weakest.query_start, weakest.query_end), (0, 2));
+        assert!(
+            smith_waterman_min_positive(&seq1, &[7, 8], &params)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
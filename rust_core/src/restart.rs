@@ -0,0 +1,182 @@
+//! Local alignment whose running score is held at a small floor instead of
+//! resetting, so one alignment can bridge a short poor stretch between two
+//! strong regions.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, Scoring, check_score_range, cmp_alignment, empty_alignment,
+};
+
+const STOP: u8 = 0;
+const DIAG: u8 = 1;
+const UP: u8 = 2;
+const LEFT: u8 = 3;
+
+/// Best local alignment of `seq1` in `seq2` where a path that would reset to
+/// zero is kept alive at `restart_floor` instead, once per poor stretch.
+///
+/// A cell whose best move scores zero or less normally starts a new
+/// alignment. Here, if that move comes from a positive cell, the cell scores
+/// `restart_floor` and keeps the move, so the path survives. The credit is
+/// spent until the path climbs back above `restart_floor`: dropping to zero
+/// again before that resets as usual, so only stretches costing less than
+/// about `restart_floor` more are bridged. The bridged alignment spans both
+/// regions and the stretch between, and its score is the sum along its path
+/// plus the credit each floor added, so it is not comparable with
+/// `smith_waterman` scores. `restart_floor` must be zero or positive; 0 is
+/// `smith_waterman` exactly. Moves tie-break like `smith_waterman` (diagonal,
+/// then up, then left), and tied end cells like `smith_waterman_with`.
+/// `run_bonus`, `end_gap_discount` and affine gaps are not supported.
+pub fn smith_waterman_restart_floor<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    restart_floor: i32,
+) -> Result<Alignment, AlignError> {
+    let params = scoring.params();
+    if params.run_bonus != 0 || params.end_gap_discount != 0 || params.is_affine() {
+        return Err(AlignError::InvalidParams(
+            "run_bonus, end_gap_discount and gap_open are not supported with restart_floor"
+                .to_string(),
+        ));
+    }
+    if restart_floor < 0 {
+        return Err(AlignError::InvalidArgument(format!(
+            "restart_floor must be zero or positive, got {restart_floor}"
+        )));
+    }
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(empty_alignment());
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    // Each step of a path can add at most one floor's worth on top.
+    let steps = (seq1.len() + seq2.len() + 1) as i64;
+    let step = i64::from(scoring.max_substitution().max(0)) + i64::from(restart_floor);
+    if steps.saturating_mul(step) > i64::from(i32::MAX) {
+        return Err(AlignError::ScoreOverflow);
+    }
+
+    let cols = seq2.len() + 1;
+    // `floored[j]`: the cell lives on floor credit not yet earned back.
+    let mut previous = vec![0i32; cols];
+    let mut previous_floored = vec![false; cols];
+    let mut directions = vec![STOP; cols];
+    let mut max_score = 0i32;
+    let mut max_positions = Vec::new();
+    for (row, &token) in seq1.iter().enumerate() {
+        let i = row + 1;
+        let mut scores = vec![0i32; cols];
+        let mut floored = vec![false; cols];
+        directions.push(STOP);
+        for j in 1..cols {
+            let diag = previous[j - 1] + scoring.substitution(token, seq2[j - 1]);
+            let up = previous[j] + params.gap_query;
+            let left = scores[j - 1] + params.gap_ref;
+            let (best, direction, origin, origin_floored) = if diag >= up && diag >= left {
+                (diag, DIAG, previous[j - 1], previous_floored[j - 1])
+            } else if up >= left {
+                (up, UP, previous[j], previous_floored[j])
+            } else {
+                (left, LEFT, scores[j - 1], floored[j - 1])
+            };
+            let in_credit = origin_floored && best <= restart_floor;
+            let (score, direction, on_floor) = if best > 0 {
+                (best, direction, in_credit)
+            } else if restart_floor > 0 && origin > 0 && !origin_floored {
+                (restart_floor, direction, true)
+            } else {
+                (0, STOP, false)
+            };
+            scores[j] = score;
+            floored[j] = on_floor;
+            directions.push(direction);
+            if score > max_score {
+                max_score = score;
+                max_positions.clear();
+            }
+            if score == max_score && score > 0 {
+                max_positions.push((i, j));
+            }
+        }
+        previous = scores;
+        previous_floored = floored;
+    }
+
+    let trace = |i_end: usize, j_end: usize| {
+        let (mut i, mut j, mut matches) = (i_end, j_end, 0usize);
+        loop {
+            match directions[i * cols + j] {
+                DIAG => {
+                    if scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+                        matches += 1;
+                    }
+                    i -= 1;
+                    j -= 1;
+                }
+                UP => i -= 1,
+                LEFT => j -= 1,
+                _ => break,
+            }
+        }
+        Alignment {
+            score: max_score,
+            query_start: i,
+            query_end: i_end,
+            token_start: j,
+            token_end: j_end,
+            matches,
+        }
+    };
+    Ok(max_positions
+        .into_iter()
+        .map(|(i, j)| trace(i, j))
+        .min_by(cmp_alignment)
+        .unwrap_or_else(empty_alignment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn restart_floor_bridges_a_short_poor_stretch() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Two exact regions of three tokens, `gap` mismatches apart.
+        let pair = |gap: u32| -> (Vec<u32>, Vec<u32>) {
+            let region = |stretch: std::ops::Range<u32>| {
+                [1, 2, 3]
+                    .into_iter()
+                    .chain(stretch)
+                    .chain([4, 5, 6])
+                    .collect()
+            };
+            (region(20..20 + gap), region(40..40 + gap))
+        };
+        let (seq1, seq2) = pair(6);
+
+        // Six mismatches exhaust the first region's 6, so it resets and the
+        // best alignment is one region alone.
+        let plain = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!((plain.score, plain.matches), (6, 3));
+        assert_eq!((plain.token_start, plain.token_end), (0, 3));
+        let unfloored = smith_waterman_restart_floor(&seq1, &seq2, &params, 0).unwrap();
+        assert_eq!(
+            (unfloored.score, unfloored.token_start, unfloored.token_end),
+            (plain.score, plain.token_start, plain.token_end)
+        );
+
+        // The floor holds the path at 2 where it would reach 0, and the
+        // second region carries it to 8, spanning both.
+        let bridged = smith_waterman_restart_floor(&seq1, &seq2, &params, 2).unwrap();
+        assert_eq!((bridged.score, bridged.matches), (8, 6));
+        assert_eq!((bridged.token_start, bridged.token_end), (0, 12));
+        assert_eq!((bridged.query_start, bridged.query_end), (0, 12));
+
+        // Two more mismatches spend the credit, and the path resets again.
+        let (far1, far2) = pair(8);
+        let apart = smith_waterman_restart_floor(&far1, &far2, &params, 2).unwrap();
+        assert_eq!((apart.score, apart.token_end - apart.token_start), (6, 3));
+        assert!(smith_waterman_restart_floor(&seq1, &seq2, &params, -1).is_err());
+    }
+}
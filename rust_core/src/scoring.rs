@@ -0,0 +1,385 @@
+//! Diagonal scoring rules layered on top of `ScoreParams`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::AlignError;
+use crate::smith_waterman::{ScoreParams, Scoring};
+
+/// Scores matches on stopword tokens with `stopword_match_score` instead of
+/// `match_score`, so common-word overlap contributes less to the alignment.
+///
+/// A diagonal match involving a stopword on either side still counts as a
+/// match; only its score changes. An empty set behaves like `params` alone.
+pub struct StopwordScoring<'a> {
+    pub params: ScoreParams,
+    pub stopwords: &'a HashSet<u32>,
+    pub stopword_match_score: i32,
+}
+
+impl Scoring for StopwordScoring<'_> {
+    fn params(&self) -> ScoreParams {
+        self.params
+    }
+
+    #[inline]
+    fn substitution(&self, a: u32, b: u32) -> i32 {
+        if !self.params.is_match(a, b) {
+            return self.params.mismatch_score;
+        }
+        if !self.stopwords.is_empty()
+            && (self.stopwords.contains(&a) || self.stopwords.contains(&b))
+        {
+            return self.stopword_match_score;
+        }
+        self.params.match_score
+    }
+
+    fn max_substitution(&self) -> i32 {
+        self.params
+            .match_score
+            .max(self.params.mismatch_score)
+            .max(self.stopword_match_score)
+    }
+}
+
+/// Canonicalizes both tokens through `equiv` before the match test, so ids in
+/// the same equivalence class (e.g. British and American spellings) match.
+///
+/// `equiv` maps an id to its class representative in one lookup; ids absent
+/// from the map stand for themselves. Map every member, including the
+/// representative if it has other aliases, straight to the representative.
+pub struct EquivalenceScoring<'a> {
+    pub params: ScoreParams,
+    pub equiv: &'a HashMap<u32, u32>,
+}
+
+impl EquivalenceScoring<'_> {
+    #[inline]
+    fn canonical(&self, token: u32) -> u32 {
+        self.equiv.get(&token).copied().unwrap_or(token)
+    }
+}
+
+impl Scoring for EquivalenceScoring<'_> {
+    fn params(&self) -> ScoreParams {
+        self.params
+    }
+
+    #[inline]
+    fn is_match(&self, a: u32, b: u32) -> bool {
+        self.params.is_match(self.canonical(a), self.canonical(b))
+    }
+
+    #[inline]
+    fn substitution(&self, a: u32, b: u32) -> i32 {
+        self.params
+            .substitution(self.canonical(a), self.canonical(b))
+    }
+}
+
+/// Translates query tokens through `cross_map` before the match test, for a
+/// query and reference tokenized with different vocabularies.
+///
+/// Unlike `EquivalenceScoring`, only the query side (`a`) is mapped: keys are
+/// query-vocabulary ids and values the reference-vocabulary ids for the same
+/// word. Query ids absent from the map are compared as they are, so ids both
+/// vocabularies share (e.g. a wildcard) still match.
+pub struct CrossVocabularyScoring<'a> {
+    pub params: ScoreParams,
+    pub cross_map: &'a HashMap<u32, u32>,
+}
+
+impl CrossVocabularyScoring<'_> {
+    #[inline]
+    fn translate(&self, token: u32) -> u32 {
+        self.cross_map.get(&token).copied().unwrap_or(token)
+    }
+}
+
+impl Scoring for CrossVocabularyScoring<'_> {
+    fn params(&self) -> ScoreParams {
+        self.params
+    }
+
+    #[inline]
+    fn is_match(&self, a: u32, b: u32) -> bool {
+        self.params.is_match(self.translate(a), b)
+    }
+
+    #[inline]
+    fn substitution(&self, a: u32, b: u32) -> i32 {
+        self.params.substitution(self.translate(a), b)
+    }
+}
+
+/// Gives near-equal ids partial credit, for tokens that encode quantized
+/// numbers (e.g. years 1999 and 2000).
+///
+/// Ids `d = |a - b|` apart with `0 < d <= tolerance` score
+/// `mismatch + (match - mismatch) * (tolerance + 1 - d) / (tolerance + 1)`,
+/// rounded down: the credit falls linearly from `match_score` towards
+/// `mismatch_score` as `d` grows. Farther ids score a full mismatch. Only
+/// equal ids (or the wildcard) count as matches; `tolerance == 0` behaves
+/// like `params` alone.
+pub struct NumericScoring {
+    pub params: ScoreParams,
+    pub tolerance: u32,
+}
+
+impl Scoring for NumericScoring {
+    fn params(&self) -> ScoreParams {
+        self.params
+    }
+
+    #[inline]
+    fn substitution(&self, a: u32, b: u32) -> i32 {
+        if self.params.is_match(a, b) {
+            return self.params.match_score;
+        }
+        let distance = a.abs_diff(b);
+        if distance > self.tolerance {
+            return self.params.mismatch_score;
+        }
+        let (matched, mismatched) = (
+            i64::from(self.params.match_score),
+            i64::from(self.params.mismatch_score),
+        );
+        let steps = i64::from(self.tolerance) + 1;
+        let credit = (matched - mismatched) * (steps - i64::from(distance));
+        (mismatched + credit / steps) as i32
+    }
+}
+
+/// Treats tokens as fixed-width bit codes (e.g. shingle hashes) and counts
+/// two codes at most `threshold` differing bits apart as a match, so
+/// near-duplicate codes align.
+///
+/// Only the match test changes: a near match scores and counts exactly like
+/// an equal pair, and the wildcard still matches anything. `threshold == 0`
+/// behaves like `params` alone.
+pub struct HammingScoring {
+    pub params: ScoreParams,
+    pub threshold: u32,
+}
+
+impl Scoring for HammingScoring {
+    fn params(&self) -> ScoreParams {
+        self.params
+    }
+
+    #[inline]
+    fn is_match(&self, a: u32, b: u32) -> bool {
+        (a ^ b).count_ones() <= self.threshold || self.params.is_match(a, b)
+    }
+
+    #[inline]
+    fn substitution(&self, a: u32, b: u32) -> i32 {
+        if self.is_match(a, b) {
+            self.params.match_score
+        } else {
+            self.params.mismatch_score
+        }
+    }
+}
+
+/// Looks up every diagonal score in a dense `vocab x vocab` matrix, row `a`
+/// and column `b` for query token `a` against reference token `b`, so
+/// arbitrary (even asymmetric) scores cost one index per cell.
+///
+/// `params` supplies only the gap scores, `run_bonus` and `end_gap_discount`;
+/// equal ids still count as matches. Check each sequence with
+/// `check_tokens` before aligning, since the lookup does not bound token ids.
+pub struct MatrixScoring<'a> {
+    pub params: ScoreParams,
+    vocab: usize,
+    scores: &'a [i32],
+}
+
+impl<'a> MatrixScoring<'a> {
+    /// Wraps row-major `scores` of shape `(rows, cols)`, which must be square.
+    pub fn new(
+        params: ScoreParams,
+        rows: usize,
+        cols: usize,
+        scores: &'a [i32],
+    ) -> Result<Self, AlignError> {
+        if rows != cols || scores.len() != rows * cols {
+            return Err(AlignError::InvalidArgument(format!(
+                "substitution matrix must be square, got shape ({rows}, {cols})"
+            )));
+        }
+        Ok(Self {
+            params,
+            vocab: rows,
+            scores,
+        })
+    }
+
+    /// Rejects a sequence holding a token id outside the matrix.
+    pub fn check_tokens(&self, seq: &[u32]) -> Result<(), AlignError> {
+        match seq.iter().find(|&&token| token as usize >= self.vocab) {
+            Some(token) => Err(AlignError::InvalidArgument(format!(
+                "token {token} is out of range for a {0}x{0} substitution matrix",
+                self.vocab
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Scoring for MatrixScoring<'_> {
+    fn params(&self) -> ScoreParams {
+        self.params
+    }
+
+    #[inline]
+    fn is_match(&self, a: u32, b: u32) -> bool {
+        a == b
+    }
+
+    #[inline]
+    fn substitution(&self, a: u32, b: u32) -> i32 {
+        self.scores[a as usize * self.vocab + b as usize]
+    }
+
+    fn max_substitution(&self) -> i32 {
+        self.scores.iter().copied().max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{
+        Constraints, align_topk, align_topk_with, smith_waterman, smith_waterman_with,
+    };
+
+    #[test]
+    fn stopwords_let_content_words_outrank() {
+        let params = ScoreParams::new(2, -1, -1);
+        let query = vec![1, 2, 3, 7, 8];
+        let seqs = vec![vec![1, 2, 3], vec![7, 8]];
+
+        let plain = align_topk(&query, &seqs, params, 2).unwrap();
+        assert_eq!((plain[0].index, plain[0].score), (0, 6));
+
+        let stopwords: HashSet<u32> = [1, 2, 3].into_iter().collect();
+        let scoring = StopwordScoring {
+            params,
+            stopwords: &stopwords,
+            stopword_match_score: 1,
+        };
+        let weighted = align_topk_with(&query, &seqs, &scoring, 2).unwrap();
+        assert_eq!((weighted[0].index, weighted[0].score), (1, 4));
+        assert_eq!((weighted[1].index, weighted[1].score), (0, 3));
+        assert_eq!(weighted[1].matches, 3);
+    }
+
+    #[test]
+    fn equivalent_ids_align_as_matches() {
+        let params = ScoreParams::new(2, -1, -1);
+        // 10 = "colour", 11 = "color".
+        let query = vec![1, 10, 2];
+        let reference = vec![1, 11, 2];
+        assert_eq!(smith_waterman(&query, &reference, params).unwrap().score, 3);
+
+        let equiv: HashMap<u32, u32> = [(11, 10)].into_iter().collect();
+        let scoring = EquivalenceScoring {
+            params,
+            equiv: &equiv,
+        };
+        let alignment =
+            smith_waterman_with(&query, &reference, &scoring, &Constraints::default()).unwrap();
+        assert_eq!((alignment.score, alignment.matches), (6, 3));
+    }
+
+    #[test]
+    fn mapped_query_ids_match_the_other_vocabulary() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Query vocabulary: 100 = "the", 101 = "river". Reference: 7, 8.
+        let query = vec![100, 101, 3];
+        let reference = vec![7, 8, 3];
+        let cross_map: HashMap<u32, u32> = [(100, 7), (101, 8)].into_iter().collect();
+        let scoring = CrossVocabularyScoring {
+            params,
+            cross_map: &cross_map,
+        };
+        let alignment =
+            smith_waterman_with(&query, &reference, &scoring, &Constraints::default()).unwrap();
+        assert_eq!((alignment.score, alignment.matches), (6, 3));
+        assert_eq!((alignment.token_start, alignment.token_end), (0, 3));
+
+        // The map is one-way: reference ids are never translated.
+        let swapped =
+            smith_waterman_with(&reference, &query, &scoring, &Constraints::default()).unwrap();
+        assert_eq!((swapped.score, swapped.matches), (2, 1));
+    }
+
+    #[test]
+    fn near_numbers_earn_partial_credit() {
+        let params = ScoreParams::new(2, -1, -1);
+        let query = vec![1, 1999, 3];
+        let reference = vec![1, 2000, 3];
+        assert_eq!(smith_waterman(&query, &reference, params).unwrap().score, 3);
+
+        let scoring = NumericScoring {
+            params,
+            tolerance: 2,
+        };
+        // 1 apart: -1 + 3 * 2 / 3 = 1; 2 apart: 0; 3 apart: a full mismatch.
+        assert_eq!(scoring.substitution(1999, 2000), 1);
+        assert_eq!(scoring.substitution(2001, 1999), 0);
+        assert_eq!(scoring.substitution(1997, 2000), -1);
+        let alignment =
+            smith_waterman_with(&query, &reference, &scoring, &Constraints::default()).unwrap();
+        assert_eq!((alignment.score, alignment.matches), (5, 2));
+    }
+
+    #[test]
+    fn codes_one_bit_apart_match_at_threshold_one() {
+        let params = ScoreParams::new(2, -1, -1);
+        let query = vec![0b1010_0000, 0b0110_1100, 0b1111_0000];
+        // One bit flipped in each code, and a code three bits off.
+        let reference = vec![0b1010_0001, 0b0100_1100, 0b1111_0000, 0b1010_0111];
+        let plain = smith_waterman(&query, &reference, params).unwrap();
+        assert_eq!((plain.score, plain.matches), (2, 1));
+
+        let scoring = HammingScoring {
+            params,
+            threshold: 1,
+        };
+        assert!(scoring.is_match(0b1010_0000, 0b1010_0001));
+        assert!(!scoring.is_match(0b1010_0000, 0b1010_0111));
+        let alignment =
+            smith_waterman_with(&query, &reference, &scoring, &Constraints::default()).unwrap();
+        assert_eq!(
+            (alignment.score, alignment.token_end, alignment.matches),
+            (6, 3, 3)
+        );
+    }
+
+    #[test]
+    fn substitution_matrix_aligns_distinct_ids() {
+        let params = ScoreParams::new(2, -1, -1);
+        #[rustfmt::skip]
+        let matrix = [
+            2, -1, -1,
+            -1, 2, 1,
+            -1, -1, 2,
+        ];
+        let scoring = MatrixScoring::new(params, 3, 3, &matrix).unwrap();
+        // Query 1 against reference 2 earns 1, but not the other way round.
+        let forward = smith_waterman_with(&[0, 1], &[0, 2], &scoring, &Constraints::default());
+        let forward = forward.unwrap();
+        assert_eq!(
+            (forward.score, forward.token_end, forward.matches),
+            (3, 2, 1)
+        );
+        let backward = smith_waterman_with(&[0, 2], &[0, 1], &scoring, &Constraints::default());
+        assert_eq!(backward.unwrap().score, 2);
+
+        assert!(scoring.check_tokens(&[0, 2]).is_ok());
+        assert!(scoring.check_tokens(&[0, 3]).is_err());
+        assert!(MatrixScoring::new(params, 3, 2, &matrix[..6]).is_err());
+    }
+}
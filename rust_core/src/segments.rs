@@ -0,0 +1,152 @@
+//! Alignment against parts of one long sequence: references concatenated
+//! into a corpus, or sliding windows over a document.
+
+use rayon::prelude::*;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, Constraints, Scoring, cmp_alignment, empty_alignment, smith_waterman_with,
+};
+
+/// Aligns `seq1` against `corpus`, a concatenation of references whose
+/// segments start at `boundaries` (strictly increasing, excluding offset 0).
+///
+/// The recurrence restarts at every boundary, so the alignment never spans two
+/// references. Coordinates stay in the corpus frame; the second value is the
+/// index of the segment the alignment fell into (0 for an empty alignment).
+pub fn smith_waterman_segmented<S: Scoring>(
+    seq1: &[u32],
+    corpus: &[u32],
+    boundaries: &[usize],
+    scoring: &S,
+) -> Result<(Alignment, usize), AlignError> {
+    let constraints = Constraints {
+        boundaries: boundaries.to_vec(),
+        ..Constraints::default()
+    };
+    let alignment = smith_waterman_with(seq1, corpus, scoring, &constraints)?;
+    let segment = boundaries.partition_point(|&offset| offset <= alignment.token_start);
+    Ok((alignment, segment))
+}
+
+/// Best alignment of `seq1` within any window of `window_len` tokens of
+/// `reference`, windows starting every `step` tokens, e.g. to find where in a
+/// document a quote sits. The windows are aligned in parallel.
+///
+/// Windows start at `0, step, 2 * step, ...` and the last one is cut short at
+/// the end of `reference`, so every token is covered when `step <=
+/// window_len`. An alignment never crosses a window edge: keep `window_len`
+/// above the longest match expected and `step` well below it, so some window
+/// holds the whole match. Coordinates are in the `reference` frame; the second
+/// value is the start of the winning window. Ties go to the earlier window.
+/// An empty reference, or no match anywhere, gives an empty alignment in
+/// window 0.
+pub fn align_best_over_windows<S: Scoring>(
+    seq1: &[u32],
+    reference: &[u32],
+    window_len: usize,
+    step: usize,
+    scoring: &S,
+) -> Result<(Alignment, usize), AlignError> {
+    if window_len == 0 || step == 0 {
+        return Err(AlignError::InvalidArgument(format!(
+            "window_len and step must be at least 1, got {window_len} and {step}"
+        )));
+    }
+    // Stop at the first window reaching the end of `reference`.
+    let mut starts = vec![0];
+    let mut start = 0;
+    while start + window_len < reference.len() && start + step < reference.len() {
+        start += step;
+        starts.push(start);
+    }
+    let best = starts
+        .par_iter()
+        .map(|&start| {
+            let end = (start + window_len).min(reference.len());
+            let mut alignment = smith_waterman_with(
+                seq1,
+                &reference[start..end],
+                scoring,
+                &Constraints::default(),
+            )?;
+            if alignment.score > 0 {
+                alignment.token_start += start;
+                alignment.token_end += start;
+            }
+            Ok((alignment, start))
+        })
+        .collect::<Result<Vec<_>, AlignError>>()?
+        .into_iter()
+        .filter(|(alignment, _)| alignment.score > 0)
+        .min_by(|(left, left_start), (right, right_start)| {
+            cmp_alignment(left, right).then(left_start.cmp(right_start))
+        });
+    Ok(best.unwrap_or_else(|| (empty_alignment(), 0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn boundary_confines_alignment_to_one_segment() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4, 5];
+        // Segments [7, 1, 2] and [3, 4, 5, 7]: the naive alignment runs across.
+        let corpus = vec![7, 1, 2, 3, 4, 5, 7];
+
+        let naive = smith_waterman(&seq1, &corpus, params).unwrap();
+        assert_eq!(
+            (naive.score, naive.token_start, naive.token_end),
+            (10, 1, 6)
+        );
+
+        let (alignment, segment) = smith_waterman_segmented(&seq1, &corpus, &[3], &params).unwrap();
+        assert_eq!(alignment.score, 6);
+        assert_eq!((alignment.token_start, alignment.token_end), (3, 6));
+        assert_eq!((alignment.query_start, alignment.query_end), (2, 5));
+        assert_eq!(alignment.matches, 3);
+        assert_eq!(segment, 1);
+
+        assert!(smith_waterman_segmented(&seq1, &corpus, &[3, 3], &params).is_err());
+        assert!(smith_waterman_segmented(&seq1, &corpus, &[7], &params).is_err());
+    }
+    #[test]
+    fn best_window_reports_its_offset() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4, 5];
+        // The quote at 47..52, inside the window starting at 40 only.
+        let mut reference: Vec<u32> = (100..200).collect();
+        reference.splice(47..52, seq1.iter().copied());
+
+        let (alignment, window) =
+            align_best_over_windows(&seq1, &reference, 20, 10, &params).unwrap();
+        assert_eq!(window, 40);
+        assert_eq!((alignment.score, alignment.matches), (10, 5));
+        assert_eq!((alignment.token_start, alignment.token_end), (47, 52));
+        let whole = smith_waterman(&seq1, &reference, params).unwrap();
+        assert_eq!(
+            (whole.token_start, whole.token_end),
+            (alignment.token_start, alignment.token_end)
+        );
+
+        // Windows at 0 and 10 only; the second one is cut short at 14.
+        let (alignment, window) = align_best_over_windows(
+            &seq1,
+            &[9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1, 2, 3],
+            10,
+            10,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(
+            (alignment.score, alignment.token_start, window),
+            (6, 11, 10)
+        );
+        let (alignment, window) = align_best_over_windows(&seq1, &[], 10, 5, &params).unwrap();
+        assert_eq!((alignment.score, window), (0, 0));
+        assert!(align_best_over_windows(&seq1, &reference, 10, 0, &params).is_err());
+    }
+}
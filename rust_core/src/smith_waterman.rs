@@ -1,13 +1,336 @@
 use rayon::prelude::*;
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
 
+use crate::error::AlignError;
+
+/// Token id type the DP can align. The algorithm only compares tokens for
+/// equality, so any width works; `widen` lets a `u32` wildcard id be compared
+/// against tokens of every width.
+pub trait Token: Copy + Eq + Send + Sync {
+    fn widen(self) -> u64;
+}
+
+impl Token for u8 {
+    #[inline]
+    fn widen(self) -> u64 {
+        u64::from(self)
+    }
+}
+
+impl Token for u32 {
+    #[inline]
+    fn widen(self) -> u64 {
+        u64::from(self)
+    }
+}
+
+impl Token for u64 {
+    #[inline]
+    fn widen(self) -> u64 {
+        self
+    }
+}
+
+/// Linear Smith-Waterman scoring scheme.
+///
+/// `match_score` must be positive or no cell can ever score above zero. The
+/// mismatch and gap scores are expected to be negative; a non-negative value
+/// means the local alignment never resets and simply grows over the whole input.
 #[derive(Clone, Copy)]
 pub struct ScoreParams {
     pub match_score: i32,
     pub mismatch_score: i32,
-    pub gap_score: i32,
+    /// Cost of a query token aligned to nothing (an up move): the citation
+    /// adds a token the reference lacks.
+    pub gap_query: i32,
+    /// Cost of a reference token aligned to nothing (a left move): the citation
+    /// omits a token of the reference.
+    pub gap_ref: i32,
+    /// Extra cost charged once when a gap opens, on top of its first token's
+    /// `gap_query`/`gap_ref` (affine gaps: a gap of `L` tokens costs
+    /// `gap_open + L * gap`). `0` gives linear gaps; must not be positive.
+    pub gap_open: i32,
+    /// Token id that matches any token on either side, e.g. a template
+    /// placeholder. `None` disables wildcard matching.
+    pub wildcard: Option<u32>,
+    /// Extra score for extending a run of consecutive diagonal matches: the
+    /// k-th match of a run earns `run_bonus * (k - 1)` on top of its
+    /// substitution score. Runs follow the traceback path, and any mismatch,
+    /// gap or restart ends them. `0` disables the bonus.
+    pub run_bonus: i32,
+    /// Discount on gaps near the query's ends, so a truncated or ragged quote
+    /// spends its gaps there instead of in the interior. See `row_gaps`.
+    pub end_gap_discount: i32,
+    /// Token id marking a hard break, e.g. between sentences. Every cell
+    /// pairing it on either side scores 0 and stops the traceback, so no
+    /// alignment spans it. Honoured by the Smith-Waterman DP and
+    /// `local_score`; `None` disables it.
+    pub separator: Option<u32>,
+}
+
+impl Default for ScoreParams {
+    fn default() -> Self {
+        Self::citation()
+    }
+}
+
+impl ScoreParams {
+    /// Linear scheme charging `gap_score` for gaps on either side.
+    pub fn new(match_score: i32, mismatch_score: i32, gap_score: i32) -> Self {
+        Self {
+            match_score,
+            mismatch_score,
+            gap_query: gap_score,
+            gap_ref: gap_score,
+            gap_open: 0,
+            wildcard: None,
+            run_bonus: 0,
+            end_gap_discount: 0,
+            separator: None,
+        }
+    }
+
+    /// The default: a match outweighs one mismatch or gap, so a quote with a
+    /// changed or dropped word still aligns end to end.
+    pub fn citation() -> Self {
+        Self::new(2, -1, -1)
+    }
+
+    /// Nucleotide-style scoring: a mismatch costs almost a match and an indel
+    /// two, so long reads tolerate scattered substitutions but rarely open gaps.
+    pub fn dna() -> Self {
+        Self::new(5, -4, -10)
+    }
+
+    /// Near-verbatim quotes only: any edit costs three matches, so alignments
+    /// break at the first mismatch or gap unless a long exact run follows.
+    pub fn strict() -> Self {
+        Self::new(1, -3, -3)
+    }
+
+    /// Loose paraphrase: a match outweighs three edits, so alignments bridge
+    /// reworded spans between shared words.
+    pub fn lenient() -> Self {
+        Self::new(3, -1, -1)
+    }
+
+    /// Affine scheme: a gap's first token costs `gap_open_score` and each
+    /// further token `gap_extend_score`, on either side.
+    pub fn affine(
+        match_score: i32,
+        mismatch_score: i32,
+        gap_open_score: i32,
+        gap_extend_score: i32,
+    ) -> Self {
+        Self {
+            gap_open: gap_open_score - gap_extend_score,
+            ..Self::new(match_score, mismatch_score, gap_extend_score)
+        }
+    }
+
+    /// Whether gaps pay an opening cost, which needs the affine recurrence.
+    #[inline]
+    pub fn is_affine(&self) -> bool {
+        self.gap_open != 0
+    }
+
+    /// Looks up a preset by name (`citation`, `dna`, `strict`, `lenient`).
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "citation" => Some(Self::citation()),
+            "dna" => Some(Self::dna()),
+            "strict" => Some(Self::strict()),
+            "lenient" => Some(Self::lenient()),
+            _ => None,
+        }
+    }
+
+    /// Whether `a` and `b` count as a match on the diagonal.
+    #[inline]
+    pub fn is_match<T: Token>(&self, a: T, b: T) -> bool {
+        a == b
+            || self.wildcard.is_some_and(|wildcard| {
+                let wildcard = u64::from(wildcard);
+                a.widen() == wildcard || b.widen() == wildcard
+            })
+    }
+
+    /// Whether `token` is the `separator`.
+    #[inline]
+    pub fn is_separator<T: Token>(&self, token: T) -> bool {
+        self.separator
+            .is_some_and(|separator| token.widen() == u64::from(separator))
+    }
+
+    /// Diagonal score for aligning `a` against `b`.
+    #[inline]
+    pub fn substitution<T: Token>(&self, a: T, b: T) -> i32 {
+        if self.is_match(a, b) {
+            self.match_score
+        } else {
+            self.mismatch_score
+        }
+    }
+
+    /// Largest score a single diagonal step can add, for the overflow guard.
+    #[inline]
+    pub fn max_substitution(&self) -> i32 {
+        self.match_score.max(self.mismatch_score)
+    }
+
+    /// Rejects schemes that can never produce a positive alignment.
+    pub fn validate(&self) -> Result<(), AlignError> {
+        if self.match_score <= 0 {
+            return Err(AlignError::InvalidParams(format!(
+                "match_score must be positive, got {}",
+                self.match_score
+            )));
+        }
+        if self.run_bonus < 0 {
+            return Err(AlignError::InvalidParams(format!(
+                "run_bonus must be non-negative, got {}",
+                self.run_bonus
+            )));
+        }
+        if self.end_gap_discount < 0 {
+            return Err(AlignError::InvalidParams(format!(
+                "end_gap_discount must be non-negative, got {}",
+                self.end_gap_discount
+            )));
+        }
+        if self.gap_open > 0 {
+            return Err(AlignError::InvalidParams(format!(
+                "gap open score must not exceed the gap extend score, got an opening bonus of {}",
+                self.gap_open
+            )));
+        }
+        Ok(())
+    }
+
+    /// `(up, left)` gap scores for moves into DP row `i` of a query of
+    /// `query_len` tokens.
+    ///
+    /// A gap `d` tokens from the nearer query end costs
+    /// `gap + max(0, end_gap_discount - d)`, capped at zero so a discount never
+    /// turns into a bonus. An up move into row `i` skips query token `i - 1`,
+    /// so `d = min(i - 1, query_len - i)`; a left move on row `i` sits between
+    /// query tokens `i - 1` and `i`, so `d = min(i, query_len - i)`.
+    #[inline]
+    pub fn row_gaps(&self, i: usize, query_len: usize) -> (i32, i32) {
+        if self.end_gap_discount == 0 {
+            return (self.gap_query, self.gap_ref);
+        }
+        let discounted = |gap: i32, distance: usize| {
+            let distance = i32::try_from(distance).unwrap_or(i32::MAX);
+            let discount = self.end_gap_discount.saturating_sub(distance).max(0);
+            gap.saturating_add(discount).min(gap.max(0))
+        };
+        let after = query_len.saturating_sub(i);
+        (
+            discounted(self.gap_query, (i - 1).min(after)),
+            discounted(self.gap_ref, i.min(after)),
+        )
+    }
+
+    /// Describes why a valid scheme is still degenerate, if it is.
+    pub fn degenerate_reason(&self) -> Option<&'static std::ffi::CStr> {
+        if self.mismatch_score >= 0 {
+            return Some(
+                c"mismatch_score >= 0 never penalizes mismatches, so alignments do not stay local",
+            );
+        }
+        if self.gap_query >= 0 || self.gap_ref >= 0 {
+            return Some(c"gap scores >= 0 never penalize gaps, so alignments do not stay local");
+        }
+        None
+    }
+}
+
+/// Diagonal scoring rule used by the DP and by the traceback's match counting.
+///
+/// `ScoreParams` is the plain rule; richer rules (see `crate::scoring`) wrap
+/// it and override the per-pair methods. Gap penalties always come from
+/// `params()`. Rules are written against one token type `T`, `u32` unless
+/// stated otherwise.
+pub trait Scoring<T: Token = u32>: Sync {
+    fn params(&self) -> ScoreParams;
+
+    /// Whether `a` and `b` count as a match on the diagonal.
+    fn is_match(&self, a: T, b: T) -> bool {
+        self.params().is_match(a, b)
+    }
+
+    /// Diagonal score for aligning `a` against `b`.
+    fn substitution(&self, a: T, b: T) -> i32 {
+        self.params().substitution(a, b)
+    }
+
+    /// Largest score a single diagonal step can add, for the overflow guard.
+    fn max_substitution(&self) -> i32 {
+        self.params().max_substitution()
+    }
+
+    /// Whether a verbatim occurrence of the query is always its best
+    /// alignment, and the first one the canonical choice, so the DP can be
+    /// skipped for it; see `exact_alignment`. Off unless a rule opts in.
+    fn exact_is_optimal(&self) -> bool {
+        false
+    }
+
+    /// Whether swapping query and reference can never change a local score,
+    /// so `pairwise_scores` may score each unordered pair once. Off unless a
+    /// rule opts in.
+    fn is_symmetric(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Token> Scoring<T> for ScoreParams {
+    #[inline]
+    fn params(&self) -> ScoreParams {
+        *self
+    }
+
+    #[inline]
+    fn is_match(&self, a: T, b: T) -> bool {
+        ScoreParams::is_match(self, a, b)
+    }
+
+    #[inline]
+    fn substitution(&self, a: T, b: T) -> i32 {
+        ScoreParams::substitution(self, a, b)
+    }
+
+    /// Every other column scores below a match only when mismatches score
+    /// below matches and gaps below zero everywhere; a wildcard or separator
+    /// could also make another alignment tie or win.
+    fn exact_is_optimal(&self) -> bool {
+        self.mismatch_score < self.match_score
+            && self.gap_query < 0
+            && self.gap_ref < 0
+            && self.end_gap_discount == 0
+            && self.wildcard.is_none()
+            && self.separator.is_none()
+    }
+
+    /// `gap_query` differing from `gap_ref` and `end_gap_discount` charge the
+    /// two sides differently, and a `run_bonus` run follows the traceback's
+    /// move priority, which is not the same for both orientations.
+    fn is_symmetric(&self) -> bool {
+        self.gap_query == self.gap_ref && self.end_gap_discount == 0 && self.run_bonus == 0
+    }
 }
 
+/// A local alignment of `seq1[query_start..query_end]` against
+/// `seq2[token_start..token_end]`.
+///
+/// Coordinates are 0-based and half-open, like Rust and Python slices: the
+/// `*_end` fields are one past the last aligned token, and `end - start` is
+/// the span length. Match blocks use the same `(start, end)` convention. An
+/// empty alignment has all-zero coordinates.
 #[derive(Clone, Copy, Debug)]
 pub struct Alignment {
     pub score: i32,
@@ -18,7 +341,148 @@ pub struct Alignment {
     pub matches: usize,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Alignment {
+    /// Fraction of the whole query matched, `matches / query_len`, in
+    /// `0.0..=1.0`. Returns 0.0 for an empty query.
+    pub fn query_coverage(&self, query_len: usize) -> f64 {
+        if query_len == 0 {
+            return 0.0;
+        }
+        self.matches as f64 / query_len as f64
+    }
+
+    /// Fraction of the whole reference spanned by the alignment,
+    /// `(token_end - token_start) / reference_len`. Returns 0.0 for an empty
+    /// reference.
+    pub fn reference_coverage(&self, reference_len: usize) -> f64 {
+        if reference_len == 0 {
+            return 0.0;
+        }
+        (self.token_end - self.token_start) as f64 / reference_len as f64
+    }
+
+    /// Offset of the alignment's start from the main diagonal,
+    /// `token_start - query_start`: positive when the match sits further into
+    /// the reference than into the query. 0 for an empty alignment.
+    pub fn diagonal(&self) -> i64 {
+        self.token_start as i64 - self.query_start as i64
+    }
+
+    /// Whether the alignment scored above `floor`, telling a real match apart
+    /// from the all-zero "nothing aligned" result, whose coordinates also
+    /// start at 0.
+    pub fn is_match(&self, floor: i32) -> bool {
+        self.score > floor
+    }
+
+    /// 0-based indices of the last aligned query and reference tokens,
+    /// `(query_end - 1, token_end - 1)`, or `None` for an empty alignment.
+    pub fn inclusive_ends(&self) -> Option<(usize, usize)> {
+        if self.token_end == self.token_start {
+            return None;
+        }
+        Some((self.query_end - 1, self.token_end - 1))
+    }
+
+    /// Query tokens left out before and after the alignment,
+    /// `(query_start, query_len - query_end)`. An empty alignment clips
+    /// `(0, query_len)`.
+    pub fn query_clips(&self, query_len: usize) -> (usize, usize) {
+        (self.query_start, query_len - self.query_end)
+    }
+
+    /// Reference tokens left out before and after the alignment,
+    /// `(token_start, reference_len - token_end)`.
+    pub fn reference_clips(&self, reference_len: usize) -> (usize, usize) {
+        (self.token_start, reference_len - self.token_end)
+    }
+}
+
+/// Joins two alignments on the same sequence pair whose spans touch or
+/// overlap in both sequences, e.g. from separately aligned query windows.
+///
+/// The result spans the union of both in each sequence. Without the
+/// sequences, overlapping matches are deduped by assuming every column the
+/// two share is a match both counted: `matches` drops by the overlap (the
+/// smaller of the query and reference overlaps), but never below either
+/// input's count. That is exact when the shared part is identical matched
+/// tokens and otherwise undercounts. `score` is the sum of both scores, exact
+/// for adjacent spans and an upper bound when they overlap; realign the
+/// merged span when an exact score matters.
+pub fn merge_alignments(a: &Alignment, b: &Alignment) -> Result<Alignment, AlignError> {
+    let overlap = |a_start: usize, a_end: usize, b_start: usize, b_end: usize| {
+        (a_start <= b_end && b_start <= a_end)
+            .then(|| a_end.min(b_end).saturating_sub(a_start.max(b_start)))
+    };
+    let (Some(query_overlap), Some(token_overlap)) = (
+        overlap(a.query_start, a.query_end, b.query_start, b.query_end),
+        overlap(a.token_start, a.token_end, b.token_start, b.token_end),
+    ) else {
+        return Err(AlignError::InvalidArgument(
+            "alignments must touch or overlap in both sequences to merge".to_string(),
+        ));
+    };
+    let shared = query_overlap.min(token_overlap);
+    Ok(Alignment {
+        score: a.score.saturating_add(b.score),
+        query_start: a.query_start.min(b.query_start),
+        query_end: a.query_end.max(b.query_end),
+        token_start: a.token_start.min(b.token_start),
+        token_end: a.token_end.max(b.token_end),
+        matches: (a.matches + b.matches)
+            .saturating_sub(shared)
+            .max(a.matches.max(b.matches)),
+    })
+}
+
+/// One column of an alignment path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// A query token aligned against a reference token (match or mismatch).
+    Diagonal,
+    /// A query token aligned against a gap in the reference.
+    Up,
+    /// A gap in the query aligned against a reference token.
+    Left,
+}
+
+/// Which sequence's coordinates match blocks are reported in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockFrame {
+    /// Blocks are `(start, end)` ranges of `seq1`.
+    Query,
+    /// Blocks are `(start, end)` ranges of `seq2`.
+    #[default]
+    Reference,
+}
+
+/// Presentation order for candidates already selected by score, see
+/// `sort_candidates`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// `cmp_candidate` order: score descending, then the usual tie-breaks.
+    #[default]
+    Score,
+    /// Matches descending.
+    Matches,
+    /// `CandidateAlignment::percent_identity` descending.
+    PercentIdentity,
+    /// Reference start ascending.
+    TokenStart,
+}
+
+/// How `smith_waterman_match_blocks` reports and post-processes blocks; the
+/// default gives every pure run in reference coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockOptions {
+    pub frame: BlockFrame,
+    /// Trims end blocks with fewer contiguous matches; 0 and 1 trim nothing.
+    pub min_anchor: usize,
+    /// Merges blocks until at most this many remain; `None` keeps them all.
+    pub max_blocks: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct CandidateAlignment {
     pub score: i32,
     pub index: usize,
@@ -29,428 +493,4136 @@ pub struct CandidateAlignment {
     pub matches: usize,
 }
 
-pub fn smith_waterman(seq1: &[u32], seq2: &[u32], params: ScoreParams) -> Alignment {
-    if seq1.is_empty() || seq2.is_empty() {
-        return Alignment {
-            score: 0,
-            query_start: 0,
-            query_end: 0,
-            token_start: 0,
-            token_end: 0,
-            matches: 0,
-        };
+impl CandidateAlignment {
+    pub fn from_alignment(index: usize, alignment: &Alignment) -> Self {
+        Self {
+            score: alignment.score,
+            index,
+            query_start: alignment.query_start,
+            query_end: alignment.query_end,
+            token_start: alignment.token_start,
+            token_end: alignment.token_end,
+            matches: alignment.matches,
+        }
     }
 
-    let rows = seq1.len() + 1;
-    let cols = seq2.len() + 1;
-    let mut scores = vec![vec![0i32; cols]; rows];
-    let mut directions = vec![vec![0u8; cols]; rows];
-
-    let mut max_score = 0i32;
-    let mut max_positions: Vec<(usize, usize)> = Vec::new();
+    /// Percentage of the longer aligned span (query or reference) covered by
+    /// exact matches, in `0.0..=100.0`. Returns 0.0 for an empty alignment.
+    pub fn percent_identity(&self) -> f64 {
+        let span = (self.query_end - self.query_start).max(self.token_end - self.token_start);
+        if span == 0 {
+            return 0.0;
+        }
+        100.0 * self.matches as f64 / span as f64
+    }
 
-    for i in 1..rows {
-        for j in 1..cols {
-            let match_score = if seq1[i - 1] == seq2[j - 1] {
-                params.match_score
-            } else {
-                params.mismatch_score
-            };
-            let score_diag = scores[i - 1][j - 1] + match_score;
-            let score_up = scores[i - 1][j] + params.gap_score;
-            let score_left = scores[i][j - 1] + params.gap_score;
+    /// `Alignment::query_coverage` for this candidate.
+    pub fn query_coverage(&self, query_len: usize) -> f64 {
+        if query_len == 0 {
+            return 0.0;
+        }
+        self.matches as f64 / query_len as f64
+    }
+}
 
-            let best = 0i32.max(score_diag).max(score_up).max(score_left);
-            if best <= 0 {
-                scores[i][j] = 0;
-                directions[i][j] = 0;
-            } else {
-                scores[i][j] = best;
-                directions[i][j] = choose_direction(best, score_diag, score_up, score_left);
-            }
+/// Restrictions on which DP cells an alignment may use.
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+    /// Half-open `(start, end)` reference intervals whose columns always score
+    /// zero, so no alignment can pass through them (e.g. boilerplate text).
+    pub mask: Vec<(usize, usize)>,
+    /// Strictly increasing reference offsets where a new segment begins. The
+    /// recurrence restarts at each one, so no alignment spans two segments.
+    pub boundaries: Vec<usize>,
+    /// Largest allowed `|j - i|` between a cell's reference and query
+    /// coordinates. Cells farther from the main diagonal are never filled and
+    /// stay zero, so the DP costs O(len1 * band) instead of O(len1 * len2).
+    pub band: Option<usize>,
+    /// Diagonal `j - i` the band is centred on instead of the main diagonal:
+    /// cells need `|j - i - band_offset| <= band`. Ignored without a `band`.
+    pub band_offset: isize,
+}
 
-            if scores[i][j] > max_score {
-                max_score = scores[i][j];
-                max_positions.clear();
-                if max_score > 0 {
-                    max_positions.push((i, j));
-                }
-            } else if scores[i][j] == max_score && scores[i][j] > 0 {
-                max_positions.push((i, j));
+impl Constraints {
+    /// Expands `mask` into one flag per reference token, or `None` when nothing
+    /// is masked so the DP can skip the lookup.
+    fn masked_columns(&self, len2: usize) -> Result<Option<Vec<bool>>, AlignError> {
+        if self.mask.is_empty() {
+            return Ok(None);
+        }
+        let mut masked = vec![false; len2];
+        for &(start, end) in &self.mask {
+            if start > end || end > len2 {
+                return Err(AlignError::InvalidArgument(format!(
+                    "mask interval ({start}, {end}) is out of range for a reference of length {len2}"
+                )));
             }
+            masked[start..end].fill(true);
         }
+        Ok(Some(masked))
     }
 
-    if max_score == 0 {
-        return Alignment {
-            score: 0,
-            query_start: 0,
-            query_end: 0,
-            token_start: 0,
-            token_end: 0,
-            matches: 0,
-        };
+    /// The same restrictions for a `len1` x `len2` DP over both sequences
+    /// reversed. Out-of-range entries are kept as they are, so they are still
+    /// rejected.
+    fn reversed(&self, len1: usize, len2: usize) -> Self {
+        let mask = self
+            .mask
+            .iter()
+            .map(|&(start, end)| {
+                if start <= end && end <= len2 {
+                    (len2 - end, len2 - start)
+                } else {
+                    (start, end)
+                }
+            })
+            .collect();
+        // A segment starting at `offset` ends just before it, and reversed
+        // that end becomes the next segment's start.
+        let boundaries = self
+            .boundaries
+            .iter()
+            .rev()
+            .map(|&offset| {
+                if (1..len2).contains(&offset) {
+                    len2 - offset
+                } else {
+                    offset
+                }
+            })
+            .collect();
+        Self {
+            mask,
+            boundaries,
+            band: self.band,
+            band_offset: len2 as isize - len1 as isize - self.band_offset,
+        }
     }
 
-    let mut best: Option<Alignment> = None;
-    for (i_end, j_end) in max_positions {
-        let (i_start, j_start, matches) =
-            traceback_details(i_end, j_end, &directions, &scores, seq1, seq2);
-        let candidate = Alignment {
-            score: max_score,
-            query_start: i_start,
-            query_end: i_end,
-            token_start: j_start,
-            token_end: j_end,
-            matches,
-        };
-        if best.is_none() {
-            best = Some(candidate);
-            continue;
+    /// Expands `boundaries` into one flag per reference token marking segment
+    /// starts, or `None` when the reference is a single segment.
+    fn segment_starts(&self, len2: usize) -> Result<Option<Vec<bool>>, AlignError> {
+        if self.boundaries.is_empty() {
+            return Ok(None);
         }
-        if cmp_alignment(&candidate, &best.unwrap()) == Ordering::Less {
-            best = Some(candidate);
+        let mut starts = vec![false; len2];
+        let mut previous = 0usize;
+        for &offset in &self.boundaries {
+            if offset <= previous || offset >= len2 {
+                return Err(AlignError::InvalidArgument(format!(
+                    "boundaries must be strictly increasing offsets in 1..{len2}, got {offset}"
+                )));
+            }
+            starts[offset] = true;
+            previous = offset;
         }
+        Ok(Some(starts))
     }
-
-    best.expect("max_positions is non-empty when max_score > 0")
 }
 
-pub fn smith_waterman_match_blocks(
+pub fn smith_waterman(
     seq1: &[u32],
     seq2: &[u32],
     params: ScoreParams,
-) -> (Alignment, Vec<(usize, usize)>) {
-    if seq1.is_empty() || seq2.is_empty() {
-        return (
-            Alignment {
-                score: 0,
-                query_start: 0,
-                query_end: 0,
-                token_start: 0,
-                token_end: 0,
-                matches: 0,
-            },
-            Vec::new(),
-        );
-    }
-
-    let rows = seq1.len() + 1;
-    let cols = seq2.len() + 1;
-    let mut scores = vec![vec![0i32; cols]; rows];
-    let mut directions = vec![vec![0u8; cols]; rows];
-
-    let mut max_score = 0i32;
-    let mut max_positions: Vec<(usize, usize)> = Vec::new();
+) -> Result<Alignment, AlignError> {
+    smith_waterman_with(seq1, seq2, &params, &Constraints::default())
+}
 
-    for i in 1..rows {
-        for j in 1..cols {
-            let match_score = if seq1[i - 1] == seq2[j - 1] {
-                params.match_score
-            } else {
-                params.mismatch_score
-            };
-            let score_diag = scores[i - 1][j - 1] + match_score;
-            let score_up = scores[i - 1][j] + params.gap_score;
-            let score_left = scores[i][j - 1] + params.gap_score;
+pub fn smith_waterman_with<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Alignment, AlignError> {
+    smith_waterman_tokens(seq1, seq2, scoring, constraints)
+}
 
-            let best = 0i32.max(score_diag).max(score_up).max(score_left);
-            if best <= 0 {
-                scores[i][j] = 0;
-                directions[i][j] = 0;
-            } else {
-                scores[i][j] = best;
-                directions[i][j] = choose_direction(best, score_diag, score_up, score_left);
-            }
+/// `smith_waterman_with` over any token width, e.g. `u64` ids that would
+/// collide if truncated to `u32`.
+pub fn smith_waterman_tokens<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Alignment, AlignError> {
+    let Some(matrix) = fill_matrix(seq1, seq2, scoring, constraints)? else {
+        return Ok(empty_alignment());
+    };
+    select_best(&matrix, seq1, seq2, scoring)
+}
 
-            if scores[i][j] > max_score {
-                max_score = scores[i][j];
-                max_positions.clear();
-                if max_score > 0 {
-                    max_positions.push((i, j));
-                }
-            } else if scores[i][j] == max_score && scores[i][j] > 0 {
-                max_positions.push((i, j));
-            }
-        }
+/// The alignment of `seq1` at its first verbatim occurrence in `seq2`, found
+/// by a rolling-hash scan instead of the DP, or `None` when `seq1` does not
+/// occur or `scoring` does not allow the shortcut (`Scoring::exact_is_optimal`).
+///
+/// When it returns an alignment, that is exactly what `smith_waterman_tokens`
+/// would return: all of `seq1` matched, with the score of one uninterrupted
+/// run. The scan costs O(len2) instead of O(len1 * len2).
+pub fn exact_alignment<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+) -> Result<Option<Alignment>, AlignError> {
+    if seq1.is_empty() || !scoring.exact_is_optimal() {
+        return Ok(None);
     }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    let Some(start) = find_exact(seq1, seq2) else {
+        return Ok(None);
+    };
+    let params = scoring.params();
+    // Every token matches and the k-th extends the run by k - 1, so the score
+    // is `len * match_score + run_bonus * len * (len - 1) / 2`. Worked in i64
+    // and checked: the pairs term alone overflows i32 for quotes of 46342
+    // tokens, which `check_score_range` can still accept.
+    let len = seq1.len() as i64;
+    let score = len
+        .checked_mul(len - 1)
+        .map(|pairs| pairs / 2)
+        .and_then(|pairs| pairs.checked_mul(i64::from(params.run_bonus)))
+        .and_then(|bonus| {
+            len.checked_mul(i64::from(params.match_score))?
+                .checked_add(bonus)
+        })
+        .and_then(|score| i32::try_from(score).ok())
+        .ok_or(AlignError::ScoreOverflow)?;
+    Ok(Some(Alignment {
+        score,
+        query_start: 0,
+        query_end: seq1.len(),
+        token_start: start,
+        token_end: start + seq1.len(),
+        matches: seq1.len(),
+    }))
+}
 
-    if max_score == 0 {
-        return (
-            Alignment {
-                score: 0,
-                query_start: 0,
-                query_end: 0,
-                token_start: 0,
-                token_end: 0,
-                matches: 0,
-            },
-            Vec::new(),
-        );
+/// Offset of the first occurrence of `needle` in `haystack` (Rabin-Karp over
+/// the widened ids, each hit confirmed by comparison).
+fn find_exact<T: Token>(needle: &[T], haystack: &[T]) -> Option<usize> {
+    const BASE: u64 = 1_000_003;
+    let len = needle.len();
+    if len == 0 || len > haystack.len() {
+        return None;
     }
-
-    let mut best: Option<(Alignment, Vec<(usize, usize)>)> = None;
-    for (i_end, j_end) in max_positions {
-        let (i_start, j_start, matches, match_blocks) =
-            traceback_details_with_match_blocks(i_end, j_end, &directions, &scores, seq1, seq2);
-        let candidate = Alignment {
-            score: max_score,
-            query_start: i_start,
-            query_end: i_end,
-            token_start: j_start,
-            token_end: j_end,
-            matches,
-        };
-        match best.as_ref() {
-            None => {
-                best = Some((candidate, match_blocks));
-            }
-            Some((best_alignment, _)) => {
-                if cmp_alignment(&candidate, best_alignment) == Ordering::Less {
-                    best = Some((candidate, match_blocks));
-                }
-            }
+    let hash = |tokens: &[T]| {
+        tokens.iter().fold(0u64, |hash, token| {
+            hash.wrapping_mul(BASE).wrapping_add(token.widen())
+        })
+    };
+    let target = hash(needle);
+    // BASE^(len - 1): the weight of the token leaving the window.
+    let leading = (1..len).fold(1u64, |power, _| power.wrapping_mul(BASE));
+    let mut window = hash(&haystack[..len]);
+    for start in 0..=haystack.len() - len {
+        if start > 0 {
+            window = window
+                .wrapping_sub(haystack[start - 1].widen().wrapping_mul(leading))
+                .wrapping_mul(BASE)
+                .wrapping_add(haystack[start + len - 1].widen());
+        }
+        if window == target && haystack[start..start + len] == *needle {
+            return Some(start);
         }
     }
-
-    best.expect("max_positions is non-empty when max_score > 0")
+    None
 }
 
-pub fn align_topk(
+/// Like `smith_waterman_with`, but `None` when either sequence is empty.
+///
+/// The plain entry points return an all-zero alignment for empty input, which
+/// looks the same as a genuine no-match at position 0. Here a no-match on
+/// non-empty input is still `Some` with score 0.
+pub fn smith_waterman_checked<S: Scoring>(
     seq1: &[u32],
-    seqs: &[Vec<u32>],
-    params: ScoreParams,
-    top_k: usize,
-) -> Vec<CandidateAlignment> {
-    if seqs.is_empty() || top_k == 0 {
-        return Vec::new();
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Option<Alignment>, AlignError> {
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(None);
     }
+    smith_waterman_with(seq1, seq2, scoring, constraints).map(Some)
+}
 
-    let mut results: Vec<CandidateAlignment> = seqs
-        .par_iter()
-        .enumerate()
-        .map(|(index, seq2)| {
-            let alignment = smith_waterman(seq1, seq2, params);
-            CandidateAlignment {
-                score: alignment.score,
-                index,
-                query_start: alignment.query_start,
-                query_end: alignment.query_end,
-                token_start: alignment.token_start,
-                token_end: alignment.token_end,
-                matches: alignment.matches,
-            }
-        })
-        .collect();
+/// Like `smith_waterman_with`, but keeps only the first cell reaching the
+/// maximum score in row-major order (smallest `query_end`, then smallest
+/// `token_end`) instead of every tied cell.
+///
+/// Bounds memory and skips the per-candidate tracebacks on highly repetitive
+/// input where many cells tie. Ties are broken "first found", so the result
+/// can differ from `smith_waterman_with` when several alignments share the
+/// maximum score.
+pub fn smith_waterman_first_max<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Alignment, AlignError> {
+    let options = FillOptions {
+        first_max_only: true,
+        ..FillOptions::default()
+    };
+    let Some(matrix) = fill_rows(seq1, seq2, scoring, constraints, options)? else {
+        return Ok(empty_alignment());
+    };
+    if matrix.max_score <= 0 {
+        return Ok(empty_alignment());
+    }
+    select_best(&matrix, seq1, seq2, scoring)
+}
 
-    results.sort_by(cmp_candidate);
-    results.truncate(top_k.min(results.len()));
-    results
+/// DP progress after one query row, see `smith_waterman_observed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RowProgress {
+    /// Query rows filled so far, `1..=rows`; this row ends on `seq1[row - 1]`.
+    pub row: usize,
+    pub rows: usize,
+    /// Best score in this row, and the `token_end` of its first cell reaching
+    /// it; `(0, 0)` when nothing in the row pays off.
+    pub best_score: i32,
+    pub best_column: usize,
+    /// Best score in any row so far.
+    pub max_score: i32,
 }
 
-pub fn align_best(
+/// Like `smith_waterman_with`, but calls `observe` once per row of `seq1` as
+/// the DP runs, in order, e.g. to see where an alignment gains or loses score
+/// when tuning a scheme. Empty input fills no rows and calls it never.
+///
+/// Each call scans the finished row once, so observing costs O(len2) per row
+/// on top of the DP; the plain entry points skip it entirely.
+pub fn smith_waterman_observed<S: Scoring>(
     seq1: &[u32],
-    seqs: &[Vec<u32>],
-    params: ScoreParams,
-) -> Option<CandidateAlignment> {
-    align_topk(seq1, seqs, params, 1).into_iter().next()
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    mut observe: impl FnMut(&RowProgress),
+) -> Result<Alignment, AlignError> {
+    let Some(matrix) = fill_rows_observed(
+        seq1,
+        seq2,
+        scoring,
+        constraints,
+        FillOptions::default(),
+        Some(&mut observe),
+    )?
+    else {
+        return Ok(empty_alignment());
+    };
+    if matrix.max_score <= 0 {
+        return Ok(empty_alignment());
+    }
+    select_best(&matrix, seq1, seq2, scoring)
 }
 
-fn choose_direction(best: i32, score_diag: i32, score_up: i32, _score_left: i32) -> u8 {
-    if best == score_diag {
-        return 1;
-    }
-    if best == score_up {
-        return 2;
-    }
-    3
+/// How `smith_waterman_tie_break` picks among end cells tied at the maximum
+/// score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The alignment `smith_waterman_with` reports.
+    #[default]
+    Canonical,
+    /// A pseudo-random tied cell, the same for every run with this seed.
+    Random(u64),
 }
 
-fn traceback_details(
-    mut i: usize,
-    mut j: usize,
-    directions: &[Vec<u8>],
-    scores: &[Vec<i32>],
+/// Like `smith_waterman_with`, but with `TieBreak::Random` the end cell is
+/// drawn from every maximal cell by a seeded generator instead of taking the
+/// canonical one, e.g. for bootstrap estimates of alignment stability.
+///
+/// The draw depends only on the seed and the number of tied cells, so it is
+/// reproducible for a given input and seed but varies across seeds. Only the
+/// end cell is drawn; the path back from it follows the usual priority.
+pub fn smith_waterman_tie_break<S: Scoring>(
     seq1: &[u32],
     seq2: &[u32],
-) -> (usize, usize, usize) {
-    let mut matches = 0usize;
-    while i > 0 && j > 0 && directions[i][j] != 0 && scores[i][j] > 0 {
-        match directions[i][j] {
-            1 => {
-                if seq1[i - 1] == seq2[j - 1] {
-                    matches += 1;
-                }
-                i -= 1;
-                j -= 1;
-            }
-            2 => {
-                i -= 1;
-            }
-            _ => {
-                j -= 1;
-            }
-        }
-    }
-    (i, j, matches)
+    scoring: &S,
+    constraints: &Constraints,
+    tie_break: TieBreak,
+) -> Result<Alignment, AlignError> {
+    let TieBreak::Random(seed) = tie_break else {
+        return smith_waterman_with(seq1, seq2, scoring, constraints);
+    };
+    let Some(matrix) = fill_matrix(seq1, seq2, scoring, constraints)? else {
+        return Ok(empty_alignment());
+    };
+    let draw = splitmix64(seed) % matrix.max_positions.len() as u64;
+    let (i_end, j_end) = matrix.max_positions[draw as usize];
+    Ok(alignment_at(&matrix, i_end, j_end, seq1, seq2, scoring))
 }
 
-fn traceback_details_with_match_blocks(
-    mut i: usize,
-    mut j: usize,
-    directions: &[Vec<u8>],
-    scores: &[Vec<i32>],
+/// Runs `align` on `seq1` and `seq2` reversed, with `constraints` mirrored to
+/// match, and maps the result back to forward coordinates.
+///
+/// Every scoring rule reads the same in both directions, so the score and
+/// match count equal the forward alignment's. Only tie-breaking changes: the
+/// canonical choice is made from the other end, e.g. among equal matches it
+/// picks the last rather than the first.
+pub fn align_reversed<F>(
     seq1: &[u32],
     seq2: &[u32],
-) -> (usize, usize, usize, Vec<(usize, usize)>) {
-    let mut matches = 0usize;
-    let mut match_positions: Vec<usize> = Vec::new();
+    constraints: &Constraints,
+    align: F,
+) -> Result<Alignment, AlignError>
+where
+    F: FnOnce(&[u32], &[u32], &Constraints) -> Result<Alignment, AlignError>,
+{
+    let (len1, len2) = (seq1.len(), seq2.len());
+    let reversed1: Vec<u32> = seq1.iter().rev().copied().collect();
+    let reversed2: Vec<u32> = seq2.iter().rev().copied().collect();
+    let alignment = align(&reversed1, &reversed2, &constraints.reversed(len1, len2))?;
+    if alignment.score == 0 {
+        return Ok(empty_alignment());
+    }
+    Ok(Alignment {
+        query_start: len1 - alignment.query_end,
+        query_end: len1 - alignment.query_start,
+        token_start: len2 - alignment.token_end,
+        token_end: len2 - alignment.token_start,
+        ..alignment
+    })
+}
 
-    while i > 0 && j > 0 && directions[i][j] != 0 && scores[i][j] > 0 {
-        match directions[i][j] {
-            1 => {
-                i -= 1;
-                j -= 1;
-                if seq1[i] == seq2[j] {
-                    matches += 1;
-                    match_positions.push(j);
+/// One SplitMix64 step: a well-mixed 64-bit value for each seed.
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Like `smith_waterman_with`, but also returns the alignment's columns from
+/// `(query_start, token_start)` to `(query_end, token_end)`.
+pub fn smith_waterman_path<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<(Alignment, Vec<Step>), AlignError> {
+    let Some(matrix) = fill_matrix(seq1, seq2, scoring, constraints)? else {
+        return Ok((empty_alignment(), Vec::new()));
+    };
+    let best = select_best(&matrix, seq1, seq2, scoring)?;
+    let path = traceback_path(best.query_end, best.token_end, &matrix);
+    Ok((best, path))
+}
+
+/// Where in the query a fixed reference window matches; see `locate_in_query`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryLocation {
+    pub query_start: usize,
+    pub query_end: usize,
+    pub matches: usize,
+}
+
+/// Query span and match count of the best alignment of `query` against
+/// `reference_window`, for when a coarse search has already fixed the
+/// reference region.
+///
+/// The result is that of `smith_waterman_with` on the same window, ties
+/// included, so the reference coordinates still take part in choosing the
+/// canonical alignment; they are only left out of the result. All zero when
+/// nothing aligns.
+pub fn locate_in_query<S: Scoring>(
+    query: &[u32],
+    reference_window: &[u32],
+    scoring: &S,
+) -> Result<QueryLocation, AlignError> {
+    let alignment = smith_waterman_with(query, reference_window, scoring, &Constraints::default())?;
+    Ok(QueryLocation {
+        query_start: alignment.query_start,
+        query_end: alignment.query_end,
+        matches: alignment.matches,
+    })
+}
+
+/// Work done by one pairwise alignment, for profiling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AlignStats {
+    /// DP cells filled; masked columns are skipped and not counted.
+    pub cells_computed: usize,
+    /// Columns in the winning alignment's traceback path.
+    pub traceback_steps: usize,
+}
+
+/// Like `smith_waterman_with`, but also reports how much work the DP did.
+pub fn smith_waterman_stats<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<(Alignment, AlignStats), AlignError> {
+    let Some(matrix) = fill_scores(seq1, seq2, scoring, constraints)? else {
+        return Ok((empty_alignment(), AlignStats::default()));
+    };
+    let mut stats = AlignStats {
+        cells_computed: matrix.cells,
+        traceback_steps: 0,
+    };
+    if matrix.max_score <= 0 {
+        return Ok((empty_alignment(), stats));
+    }
+    let best = select_best(&matrix, seq1, seq2, scoring)?;
+    stats.traceback_steps = traceback_path(best.query_end, best.token_end, &matrix).len();
+    Ok((best, stats))
+}
+
+/// Row-major DP score matrix of shape `(seq1.len() + 1, seq2.len() + 1)`,
+/// including the all-zero first row and column.
+pub struct DenseScores {
+    pub rows: usize,
+    pub cols: usize,
+    pub scores: Vec<i32>,
+}
+
+/// Like `smith_waterman_with`, but also returns the full score matrix for
+/// debugging and visualization. Only this entry point keeps the matrix; the
+/// regular calls drop it after traceback.
+pub fn smith_waterman_matrix<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<(Alignment, DenseScores), AlignError> {
+    let rows = seq1.len() + 1;
+    let cols = seq2.len() + 1;
+    let Some(matrix) = fill_scores(seq1, seq2, scoring, constraints)? else {
+        let scores = vec![0; rows * cols];
+        return Ok((empty_alignment(), DenseScores { rows, cols, scores }));
+    };
+    let alignment = if matrix.max_score > 0 {
+        select_best(&matrix, seq1, seq2, scoring)?
+    } else {
+        empty_alignment()
+    };
+    let scores = matrix.scores.concat();
+    Ok((alignment, DenseScores { rows, cols, scores }))
+}
+
+/// Like `smith_waterman_with`, but also returns `scores[query_end][token_end]`,
+/// the DP score of the reported end cell, as a consistency check.
+///
+/// Local alignment ends on a maximal cell, so the end score always equals
+/// `score`; an empty alignment reports the zero corner `(0, 0)`.
+pub fn smith_waterman_end_score<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<(Alignment, i32), AlignError> {
+    let Some(matrix) = fill_scores(seq1, seq2, scoring, constraints)? else {
+        return Ok((empty_alignment(), 0));
+    };
+    let alignment = if matrix.max_score > 0 {
+        select_best(&matrix, seq1, seq2, scoring)?
+    } else {
+        empty_alignment()
+    };
+    let end_score = matrix.scores[alignment.query_end][alignment.token_end];
+    Ok((alignment, end_score))
+}
+
+/// Lays out an aligned region as two equal-length columns, with `None`
+/// marking a gap on that side.
+pub fn aligned_tokens(
+    seq1: &[u32],
+    seq2: &[u32],
+    alignment: &Alignment,
+    path: &[Step],
+) -> (Vec<Option<u32>>, Vec<Option<u32>>) {
+    let mut query = Vec::with_capacity(path.len());
+    let mut reference = Vec::with_capacity(path.len());
+    let (mut i, mut j) = (alignment.query_start, alignment.token_start);
+    for step in path {
+        match step {
+            Step::Diagonal => {
+                query.push(Some(seq1[i]));
+                reference.push(Some(seq2[j]));
+                i += 1;
+                j += 1;
+            }
+            Step::Up => {
+                query.push(Some(seq1[i]));
+                reference.push(None);
+                i += 1;
+            }
+            Step::Left => {
+                query.push(None);
+                reference.push(Some(seq2[j]));
+                j += 1;
+            }
+        }
+    }
+    (query, reference)
+}
+
+/// Reference token ids of the path's diagonal matches, in reference order.
+///
+/// Unlike match blocks this reports values, not positions; with a wildcard the
+/// reference side's token is reported.
+pub fn matched_tokens<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    alignment: &Alignment,
+    path: &[Step],
+    scoring: &S,
+) -> Vec<u32> {
+    let mut matched = Vec::with_capacity(alignment.matches);
+    let (mut i, mut j) = (alignment.query_start, alignment.token_start);
+    for step in path {
+        match step {
+            Step::Diagonal => {
+                if scoring.is_match(seq1[i], seq2[j]) {
+                    matched.push(seq2[j]);
                 }
+                i += 1;
+                j += 1;
             }
-            2 => {
-                i -= 1;
+            Step::Up => i += 1,
+            Step::Left => j += 1,
+        }
+    }
+    matched
+}
+
+/// Half-open spans from the first to the last diagonal match of a path, on
+/// both sides; see `matched_span`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchedSpan {
+    pub token_start: usize,
+    pub token_end: usize,
+    pub query_start: usize,
+    pub query_end: usize,
+}
+
+/// Extent of the path's actual matches, for highlight bounds; `None` when the
+/// path has no match.
+///
+/// It lies within the alignment's span and is usually equal to it, but an
+/// alignment can open or close on a column that scores without matching,
+/// such as a near number under `NumericScoring` or a query opening kept by
+/// `smith_waterman_query_start`; such end columns are left out.
+pub fn matched_span<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    alignment: &Alignment,
+    path: &[Step],
+    scoring: &S,
+) -> Option<MatchedSpan> {
+    let mut span: Option<MatchedSpan> = None;
+    let (mut i, mut j) = (alignment.query_start, alignment.token_start);
+    for step in path {
+        match step {
+            Step::Diagonal => {
+                if scoring.is_match(seq1[i], seq2[j]) {
+                    let span = span.get_or_insert(MatchedSpan {
+                        token_start: j,
+                        token_end: j,
+                        query_start: i,
+                        query_end: i,
+                    });
+                    span.token_end = j + 1;
+                    span.query_end = i + 1;
+                }
+                i += 1;
+                j += 1;
             }
-            _ => {
-                j -= 1;
+            Step::Up => i += 1,
+            Step::Left => j += 1,
+        }
+    }
+    span
+}
+
+/// Every distinct alignment reaching the maximum score, in `cmp_alignment`
+/// order (so the first is what `smith_waterman_with` returns).
+///
+/// Each maximal cell is traced back; alignments with the same query and
+/// reference span are reported once. Empty when nothing scores above zero.
+pub fn smith_waterman_all_max<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Vec<Alignment>, AlignError> {
+    let Some(matrix) = fill_matrix(seq1, seq2, scoring, constraints)? else {
+        return Ok(Vec::new());
+    };
+    let mut all: Vec<Alignment> = matrix
+        .max_positions
+        .iter()
+        .map(|&(i_end, j_end)| alignment_at(&matrix, i_end, j_end, seq1, seq2, scoring))
+        .collect();
+    all.sort_by(cmp_alignment);
+    all.dedup_by_key(|item| {
+        (
+            item.query_start,
+            item.query_end,
+            item.token_start,
+            item.token_end,
+        )
+    });
+    Ok(all)
+}
+
+/// The alignment traced back from the maximal cell `(i_end, j_end)`.
+fn alignment_at<T: Token, S: Scoring<T>>(
+    matrix: &ScoreMatrix,
+    i_end: usize,
+    j_end: usize,
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+) -> Alignment {
+    let (i_start, j_start, matches) = traceback_details(i_end, j_end, matrix, seq1, seq2, scoring);
+    Alignment {
+        score: matrix.max_score,
+        query_start: i_start,
+        query_end: i_end,
+        token_start: j_start,
+        token_end: j_end,
+        matches,
+    }
+}
+
+/// Picks the canonical alignment among every cell tied at the maximum score.
+pub(crate) fn select_best<T: Token, S: Scoring<T>>(
+    matrix: &ScoreMatrix,
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+) -> Result<Alignment, AlignError> {
+    let mut best: Option<Alignment> = None;
+    for &(i_end, j_end) in &matrix.max_positions {
+        let candidate = alignment_at(matrix, i_end, j_end, seq1, seq2, scoring);
+        if best
+            .as_ref()
+            .is_none_or(|current| cmp_alignment(&candidate, current) == Ordering::Less)
+        {
+            best = Some(candidate);
+        }
+    }
+
+    best.ok_or(AlignError::Internal(
+        "max_positions is non-empty when max_score > 0",
+    ))
+}
+
+/// Best alignment plus its runs of consecutive matches as `(start, end)`
+/// blocks in the coordinates of `options.frame`.
+///
+/// Both frames cover the same matches, but a gap between two matches splits a
+/// block only in the frame whose sequence it skips: a query token aligned to
+/// a gap between two matches keeps them one reference-frame block.
+///
+/// With `min_anchor > 1`, leading and trailing blocks shorter than
+/// `min_anchor` are dropped until both end blocks have at least that many
+/// contiguous matches; blocks between them are kept whatever their length.
+/// The coordinates then move in to the kept end blocks and `matches` drops
+/// the trimmed matches, but `score` stays the untrimmed alignment's, so
+/// rankings are unchanged. When no block is long enough the result is empty.
+///
+/// With `max_blocks`, applied after trimming, the two adjacent blocks with
+/// the smallest gap between them are merged (the leftmost pair on ties) until
+/// at most `max_blocks` remain, so `Some(1)` gives one coarse span. Merged
+/// blocks also cover the mismatches and gaps between their runs, so every
+/// match stays covered but a block may no longer be a pure run.
+pub fn smith_waterman_match_blocks(
+    seq1: &[u32],
+    seq2: &[u32],
+    params: ScoreParams,
+    options: BlockOptions,
+) -> Result<(Alignment, Vec<(usize, usize)>), AlignError> {
+    smith_waterman_match_blocks_with(seq1, seq2, &params, &Constraints::default(), options)
+}
+
+pub fn smith_waterman_match_blocks_with<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    options: BlockOptions,
+) -> Result<(Alignment, Vec<(usize, usize)>), AlignError> {
+    match_blocks(seq1, seq2, scoring, constraints, options, |_, _, _| ())
+        .map(|(alignment, blocks, ())| (alignment, blocks))
+}
+
+/// An alignment, its match blocks and each block's score.
+pub type ScoredBlocks = (Alignment, Vec<(usize, usize)>, Vec<i32>);
+
+/// `smith_waterman_match_blocks_with` plus the score the alignment accrues
+/// within each block, for ranking the matched passages.
+///
+/// A block's score is the alignment's running score just past its last
+/// column minus the running score just before its first, so it includes any
+/// run bonus earned inside it. The block scores plus the gaps and mismatches
+/// between blocks add up to the untrimmed alignment's `score`. A merged block
+/// (see `max_blocks`) also counts the penalties it spans.
+pub fn smith_waterman_block_scores<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    options: BlockOptions,
+) -> Result<ScoredBlocks, AlignError> {
+    match_blocks(
+        seq1,
+        seq2,
+        scoring,
+        constraints,
+        options,
+        |matrix, end, blocks| block_scores(matrix, end, blocks, options.frame),
+    )
+}
+
+/// An alignment, its match blocks and each block's weight.
+pub type WeightedBlocks = (Alignment, Vec<(usize, usize)>, Vec<f64>);
+
+/// `smith_waterman_match_blocks_with` plus each block's importance: the sum
+/// of `token_idf[id]` over the reference token ids matched inside it.
+///
+/// Only the weighting reads `token_idf`; the alignment and its blocks are
+/// exactly `smith_waterman_match_blocks_with`'s, so a short block of rare
+/// tokens can outweigh a longer, higher-scoring block of common ones.
+/// Mismatches and gaps inside a merged block (see `max_blocks`) add nothing.
+/// Every reference token id must have an entry, and every weight must be
+/// finite and zero or positive.
+pub fn smith_waterman_block_weights<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    options: BlockOptions,
+    token_idf: &[f64],
+) -> Result<WeightedBlocks, AlignError> {
+    if let Some(weight) = token_idf.iter().find(|w| !w.is_finite() || **w < 0.0) {
+        return Err(AlignError::InvalidArgument(format!(
+            "token_idf weights must be finite and not negative, got {weight}"
+        )));
+    }
+    if let Some(&id) = seq2.iter().find(|&&id| id as usize >= token_idf.len()) {
+        return Err(AlignError::InvalidArgument(format!(
+            "token_idf has no weight for token id {id}"
+        )));
+    }
+    match_blocks(
+        seq1,
+        seq2,
+        scoring,
+        constraints,
+        options,
+        |matrix, end, blocks| {
+            block_weights(
+                matrix,
+                end,
+                blocks,
+                options.frame,
+                seq1,
+                seq2,
+                scoring,
+                token_idf,
+            )
+        },
+    )
+}
+
+type MeasuredBlocks<R> = (Alignment, Vec<(usize, usize)>, R);
+
+/// Shared body of the match-block entry points; `measure` gets the filled
+/// matrix, the path's end cell and the final blocks, and its result is left
+/// at its default when nothing aligns.
+fn match_blocks<S: Scoring, R: Default>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    options: BlockOptions,
+    measure: impl FnOnce(&ScoreMatrix, (usize, usize), &[(usize, usize)]) -> R,
+) -> Result<MeasuredBlocks<R>, AlignError> {
+    let BlockOptions {
+        frame,
+        min_anchor,
+        max_blocks,
+    } = options;
+    if max_blocks == Some(0) {
+        return Err(AlignError::InvalidArgument(
+            "max_blocks must be at least 1".to_string(),
+        ));
+    }
+    let Some(matrix) = fill_matrix(seq1, seq2, scoring, constraints)? else {
+        return Ok((empty_alignment(), Vec::new(), R::default()));
+    };
+
+    let mut best: Option<(Alignment, Vec<(usize, usize)>)> = None;
+    for &(i_end, j_end) in &matrix.max_positions {
+        let (i_start, j_start, matches, match_blocks) =
+            traceback_details_with_match_blocks(i_end, j_end, &matrix, seq1, seq2, scoring, frame);
+        let candidate = Alignment {
+            score: matrix.max_score,
+            query_start: i_start,
+            query_end: i_end,
+            token_start: j_start,
+            token_end: j_end,
+            matches,
+        };
+        if best
+            .as_ref()
+            .is_none_or(|(current, _)| cmp_alignment(&candidate, current) == Ordering::Less)
+        {
+            best = Some((candidate, match_blocks));
+        }
+    }
+
+    let (best, blocks) = best.ok_or(AlignError::Internal(
+        "max_positions is non-empty when max_score > 0",
+    ))?;
+    let end = (best.query_end, best.token_end);
+    let (best, mut blocks) = if min_anchor <= 1 {
+        (best, blocks)
+    } else {
+        trim_to_anchors(
+            best, blocks, &matrix, seq1, seq2, scoring, frame, min_anchor,
+        )
+    };
+    if let Some(max_blocks) = max_blocks {
+        cap_blocks(&mut blocks, max_blocks);
+    }
+    let measured = measure(&matrix, end, &blocks);
+    Ok((best, blocks, measured))
+}
+
+/// Running-score difference across each block of the path ending at `end`.
+///
+/// On the path, every cell after the first holds the path's running score,
+/// and both cells read here are entered or left by a diagonal, so neither is
+/// inside an affine gap state. The path's first cell counts as 0.
+fn block_scores(
+    matrix: &ScoreMatrix,
+    end: (usize, usize),
+    blocks: &[(usize, usize)],
+    frame: BlockFrame,
+) -> Vec<i32> {
+    let mut cells = Vec::new();
+    let start = walk_traceback(end.0, end.1, matrix, |_, i, j| cells.push((i, j)));
+    cells.push(start);
+    cells.reverse();
+    let coordinate = |&(i, j): &(usize, usize)| match frame {
+        BlockFrame::Query => i,
+        BlockFrame::Reference => j,
+    };
+    let running = |k: usize| {
+        let (i, j) = cells[k];
+        if k == 0 { 0 } else { matrix.scores[i][j] }
+    };
+    blocks
+        .iter()
+        .map(|&(block_start, block_end)| {
+            // The block's first column leaves the last cell at `block_start`;
+            // its last column enters the first cell at `block_end`.
+            let before = cells
+                .iter()
+                .rposition(|cell| coordinate(cell) == block_start)
+                .unwrap_or(0);
+            let after = cells
+                .iter()
+                .position(|cell| coordinate(cell) == block_end)
+                .unwrap_or(cells.len() - 1);
+            running(after) - running(before)
+        })
+        .collect()
+}
+
+/// Summed `token_idf` of the reference tokens matched inside each block of
+/// the path ending at `end`.
+fn block_weights<S: Scoring>(
+    matrix: &ScoreMatrix,
+    end: (usize, usize),
+    blocks: &[(usize, usize)],
+    frame: BlockFrame,
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    token_idf: &[f64],
+) -> Vec<f64> {
+    let mut matched = Vec::new();
+    walk_traceback(end.0, end.1, matrix, |step, i, j| {
+        if step == Step::Diagonal && scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+            let position = match frame {
+                BlockFrame::Query => i - 1,
+                BlockFrame::Reference => j - 1,
+            };
+            matched.push((position, token_idf[seq2[j - 1] as usize]));
+        }
+    });
+    blocks
+        .iter()
+        .map(|&(block_start, block_end)| {
+            matched
+                .iter()
+                .filter(|&&(position, _)| (block_start..block_end).contains(&position))
+                .map(|&(_, weight)| weight)
+                .sum()
+        })
+        .collect()
+}
+
+/// Merges the closest adjacent pair of sorted, disjoint blocks until at most
+/// `max_blocks` remain.
+fn cap_blocks(blocks: &mut Vec<(usize, usize)>, max_blocks: usize) {
+    while blocks.len() > max_blocks {
+        let Some(closest) = (0..blocks.len() - 1).min_by_key(|&k| blocks[k + 1].0 - blocks[k].1)
+        else {
+            return;
+        };
+        blocks[closest].1 = blocks[closest + 1].1;
+        blocks.remove(closest + 1);
+    }
+}
+
+/// Drops end blocks shorter than `min_anchor` and moves the alignment's
+/// coordinates in to the first match of the first kept block and past the
+/// last match of the last one.
+fn trim_to_anchors(
+    alignment: Alignment,
+    blocks: Vec<(usize, usize)>,
+    matrix: &ScoreMatrix,
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &impl Scoring,
+    frame: BlockFrame,
+    min_anchor: usize,
+) -> (Alignment, Vec<(usize, usize)>) {
+    let anchored = |&(start, end): &(usize, usize)| end - start >= min_anchor;
+    let (Some(first), Some(last)) = (
+        blocks.iter().position(anchored),
+        blocks.iter().rposition(anchored),
+    ) else {
+        return (empty_alignment(), Vec::new());
+    };
+    if first == 0 && last == blocks.len() - 1 {
+        return (alignment, blocks);
+    }
+    let kept = blocks[first..=last].to_vec();
+    let (kept_start, kept_end) = (kept[0].0, kept[kept.len() - 1].1);
+
+    // Each frame position holds at most one match, so the kept block ends
+    // pin down one cell each.
+    let mut start = (alignment.query_start, alignment.token_start);
+    let mut end = (alignment.query_end, alignment.token_end);
+    walk_traceback(
+        alignment.query_end,
+        alignment.token_end,
+        matrix,
+        |step, i, j| {
+            if step != Step::Diagonal || !scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+                return;
+            }
+            let pos = match frame {
+                BlockFrame::Query => i - 1,
+                BlockFrame::Reference => j - 1,
+            };
+            if pos == kept_start {
+                start = (i - 1, j - 1);
+            }
+            if pos + 1 == kept_end {
+                end = (i, j);
+            }
+        },
+    );
+    let trimmed: usize = blocks[..first]
+        .iter()
+        .chain(&blocks[last + 1..])
+        .map(|(start, end)| end - start)
+        .sum();
+    let alignment = Alignment {
+        score: alignment.score,
+        query_start: start.0,
+        query_end: end.0,
+        token_start: start.1,
+        token_end: end.1,
+        matches: alignment.matches - trimmed,
+    };
+    (alignment, kept)
+}
+
+/// A match block's reference `(start, end)` plus the matches it contains.
+pub type CountedBlock = (usize, usize, usize);
+
+/// Like `smith_waterman_match_blocks_with`, but each block is `(start, end,
+/// matches)` and blocks separated by at most `merge_gap` reference tokens are
+/// merged, so a block may then contain mismatches or gaps. With
+/// `merge_gap == 0` every block is a pure run and `matches == end - start`.
+pub fn smith_waterman_match_block_counts_with<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    merge_gap: usize,
+) -> Result<(Alignment, Vec<CountedBlock>), AlignError> {
+    let (alignment, blocks) = smith_waterman_match_blocks_with(
+        seq1,
+        seq2,
+        scoring,
+        constraints,
+        BlockOptions::default(),
+    )?;
+    Ok((alignment, count_match_blocks(&blocks, merge_gap)))
+}
+
+/// Merges sorted, disjoint match blocks whose gap is at most `merge_gap`
+/// reference tokens, summing the matches each merged block contains.
+pub fn count_match_blocks(blocks: &[(usize, usize)], merge_gap: usize) -> Vec<CountedBlock> {
+    let mut counted: Vec<CountedBlock> = Vec::new();
+    for &(start, end) in blocks {
+        match counted.last_mut() {
+            Some(last) if start - last.1 <= merge_gap => {
+                last.1 = end;
+                last.2 += end - start;
+            }
+            _ => counted.push((start, end, end - start)),
+        }
+    }
+    counted
+}
+
+/// Filled Smith-Waterman matrices plus every cell that reached the maximum score.
+pub(crate) struct ScoreMatrix {
+    pub(crate) scores: Vec<Vec<i32>>,
+    /// 0 stop, 1 diagonal, 2 up, 3 left, 4 diagonal that starts a segment
+    /// (the traceback stops after taking it).
+    directions: Vec<Vec<u8>>,
+    /// Length of the match run ending at each cell; rows stay empty unless
+    /// `run_bonus` is enabled.
+    runs: Vec<Vec<u32>>,
+    /// `TIE_*` bits of every move reaching each cell's score; rows stay empty
+    /// unless ties are recorded.
+    pub(crate) ties: Vec<Vec<u8>>,
+    /// `GAP_*_EXTENDS` bits of each cell; rows stay empty unless gaps are affine.
+    gap_extends: Vec<Vec<u8>>,
+    /// Best score of a path ending in an up move at each cell of the last row
+    /// (the affine recurrence's vertical gap state); empty unless gaps are affine.
+    up_gaps: Vec<i32>,
+    pub(crate) max_score: i32,
+    /// DP cells filled so far (masked cells are skipped and not counted).
+    cells: usize,
+    pub(crate) max_positions: Vec<(usize, usize)>,
+}
+
+/// Tie bits: the diagonal move, the up move, the left move, and a diagonal
+/// that starts a segment (the path begins before it).
+pub(crate) const TIE_DIAG: u8 = 1;
+pub(crate) const TIE_UP: u8 = 2;
+pub(crate) const TIE_LEFT: u8 = 4;
+pub(crate) const TIE_START: u8 = 8;
+
+/// Affine gap bits: the best up (left) move into a cell extends the gap of
+/// the cell above (to the left) rather than opening a new one.
+const GAP_UP_EXTENDS: u8 = 1;
+const GAP_LEFT_EXTENDS: u8 = 2;
+
+/// Score of a gap state no path can be in; far enough from `i32::MIN` that
+/// adding a gap score cannot overflow.
+const UNREACHABLE: i32 = i32::MIN / 2;
+
+/// Runs the DP over `seq1` x `seq2`.
+///
+/// Returns `Ok(None)` when either sequence is empty or no cell scores above
+/// zero, i.e. there is no local alignment to trace back.
+fn fill_matrix<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Option<ScoreMatrix>, AlignError> {
+    Ok(fill_scores(seq1, seq2, scoring, constraints)?.filter(|matrix| matrix.max_score > 0))
+}
+
+/// Like `fill_matrix`, but keeps an all-zero matrix; `None` only for empty input.
+fn fill_scores<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Option<ScoreMatrix>, AlignError> {
+    fill_rows(seq1, seq2, scoring, constraints, FillOptions::default())
+}
+
+/// Like `fill_matrix`, but also records the `TIE_*` bits of every cell.
+pub(crate) fn fill_matrix_with_ties<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Option<ScoreMatrix>, AlignError> {
+    let options = FillOptions {
+        record_ties: true,
+        ..FillOptions::default()
+    };
+    Ok(fill_rows(seq1, seq2, scoring, constraints, options)?.filter(|matrix| matrix.max_score > 0))
+}
+
+/// Bookkeeping switches for `fill_rows`; the default is the plain DP.
+#[derive(Clone, Copy, Default)]
+struct FillOptions {
+    /// Record the `TIE_*` bits of every cell.
+    record_ties: bool,
+    /// Keep only the first maximal cell in row-major order.
+    first_max_only: bool,
+}
+
+fn fill_rows<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+    constraints: &Constraints,
+    options: FillOptions,
+) -> Result<Option<ScoreMatrix>, AlignError> {
+    fill_rows_observed(seq1, seq2, scoring, constraints, options, None)
+}
+
+/// `fill_rows` calling `observe` after each row. Without an observer the
+/// only cost is one branch per row.
+fn fill_rows_observed<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &S,
+    constraints: &Constraints,
+    options: FillOptions,
+    mut observe: Option<&mut dyn FnMut(&RowProgress)>,
+) -> Result<Option<ScoreMatrix>, AlignError> {
+    let masked = constraints.masked_columns(seq2.len())?;
+    let rules = ColumnRules {
+        masked_count: masked
+            .as_ref()
+            .map_or(0, |masked| masked.iter().filter(|&&flag| flag).count()),
+        masked,
+        segment_starts: constraints.segment_starts(seq2.len())?,
+        band: constraints.band,
+        band_offset: constraints.band_offset,
+        record_ties: options.record_ties,
+        first_max_only: options.first_max_only,
+    };
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(None);
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+
+    let mut matrix = ScoreMatrix::new(seq2.len() + 1);
+    for (row, &token) in seq1.iter().enumerate() {
+        matrix.push_row(token, seq1.len(), seq2, scoring, &rules);
+        if let Some(observe) = observe.as_mut() {
+            let scores = &matrix.scores[row + 1];
+            // The first cell of the row reaching its best score.
+            let (best_column, best_score) =
+                scores
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .fold(
+                        (0, 0),
+                        |best, cell| if cell.1 > best.1 { cell } else { best },
+                    );
+            observe(&RowProgress {
+                row: row + 1,
+                rows: seq1.len(),
+                best_score,
+                best_column,
+                max_score: matrix.max_score,
+            });
+        }
+    }
+    Ok(Some(matrix))
+}
+
+/// Per-reference-token flags resolved from `Constraints` before the DP.
+#[derive(Default)]
+pub(crate) struct ColumnRules {
+    masked: Option<Vec<bool>>,
+    masked_count: usize,
+    segment_starts: Option<Vec<bool>>,
+    band: Option<usize>,
+    band_offset: isize,
+    record_ties: bool,
+    first_max_only: bool,
+}
+
+impl ScoreMatrix {
+    /// The DP's all-zero first row, before any query token.
+    pub(crate) fn new(cols: usize) -> Self {
+        Self {
+            scores: vec![vec![0i32; cols]],
+            directions: vec![vec![0u8; cols]],
+            runs: vec![Vec::new()],
+            ties: vec![Vec::new()],
+            gap_extends: vec![Vec::new()],
+            up_gaps: Vec::new(),
+            max_score: 0,
+            cells: 0,
+            max_positions: Vec::new(),
+        }
+    }
+
+    /// Computes the row for query token `token` of a `query_len`-token query
+    /// from the last row.
+    ///
+    /// The caller guarantees the widened matrix passes `check_score_range`.
+    pub(crate) fn push_row<T: Token, S: Scoring<T>>(
+        &mut self,
+        token: T,
+        query_len: usize,
+        seq2: &[T],
+        scoring: &S,
+        rules: &ColumnRules,
+    ) {
+        let params = scoring.params();
+        let cols = seq2.len() + 1;
+        let i = self.scores.len();
+        let (gap_up, gap_left) = params.row_gaps(i, query_len);
+        let previous = &self.scores[i - 1];
+        let previous_runs = &self.runs[i - 1];
+        let track_runs = params.run_bonus > 0;
+        let mut scores = vec![0i32; cols];
+        let mut directions = vec![0u8; cols];
+        let mut runs = if track_runs {
+            vec![0u32; cols]
+        } else {
+            Vec::new()
+        };
+        let mut ties = if rules.record_ties {
+            vec![0u8; cols]
+        } else {
+            Vec::new()
+        };
+        let affine = params.is_affine();
+        let (mut gap_extends, mut up_gaps) = if affine {
+            (vec![0u8; cols], vec![UNREACHABLE; cols])
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        // The best path ending in a left move at the current cell.
+        let mut left_gap = UNREACHABLE;
+
+        let (first, last) = match rules.band {
+            Some(band) => {
+                // An empty range (`last < first`) when the band misses the row.
+                // Saturating: a band wider than any input covers every cell.
+                let band = isize::try_from(band).unwrap_or(isize::MAX);
+                let centre = i as isize + rules.band_offset;
+                let first = centre.saturating_sub(band).max(1) as usize;
+                let last = centre.saturating_add(band).clamp(0, cols as isize - 1) as usize;
+                (first, last)
+            }
+            None => (1, cols - 1),
+        };
+
+        let breaks_row = params.is_separator(token);
+        for j in first..=last {
+            if rules.masked.as_ref().is_some_and(|masked| masked[j - 1]) {
+                left_gap = UNREACHABLE;
+                continue;
+            }
+            // A separator on either side leaves the cell at 0 with no
+            // direction, so paths neither cross nor end in it.
+            if breaks_row || params.is_separator(seq2[j - 1]) {
+                left_gap = UNREACHABLE;
+                continue;
+            }
+            let substitution = scoring.substitution(token, seq2[j - 1]);
+            let run_match = track_runs && scoring.is_match(token, seq2[j - 1]);
+            let score_up = if affine {
+                let open = previous[j]
+                    .saturating_add(params.gap_open)
+                    .saturating_add(gap_up);
+                let extend = self.up_gaps.get(j).map_or(UNREACHABLE, |&gap| {
+                    gap.saturating_add(gap_up).max(UNREACHABLE)
+                });
+                if extend > open {
+                    gap_extends[j] |= GAP_UP_EXTENDS;
+                }
+                up_gaps[j] = open.max(extend);
+                up_gaps[j]
+            } else {
+                previous[j] + gap_up
+            };
+            let restarts = rules
+                .segment_starts
+                .as_ref()
+                .is_some_and(|starts| starts[j - 1]);
+            if restarts {
+                // Column `j - 1` belongs to the previous segment: only a fresh
+                // diagonal start or a query gap within this column is allowed.
+                let best = 0i32.max(substitution).max(score_up);
+                left_gap = UNREACHABLE;
+                if best > 0 {
+                    scores[j] = best;
+                    directions[j] = if best == substitution { 4 } else { 2 };
+                    if run_match && directions[j] == 4 {
+                        runs[j] = 1;
+                    }
+                    if rules.record_ties {
+                        ties[j] = tie_bits(best, &[(substitution, TIE_START), (score_up, TIE_UP)]);
+                    }
+                }
+            } else {
+                let run = previous_runs.get(j - 1).copied().unwrap_or(0);
+                let bonus = if run_match {
+                    params.run_bonus * run as i32
+                } else {
+                    0
+                };
+                let score_diag = previous[j - 1] + substitution + bonus;
+                let score_left = if affine {
+                    let open = scores[j - 1]
+                        .saturating_add(params.gap_open)
+                        .saturating_add(gap_left);
+                    let extend = left_gap.saturating_add(gap_left).max(UNREACHABLE);
+                    if extend > open {
+                        gap_extends[j] |= GAP_LEFT_EXTENDS;
+                    }
+                    left_gap = open.max(extend);
+                    left_gap
+                } else {
+                    scores[j - 1] + gap_left
+                };
+
+                let best = 0i32.max(score_diag).max(score_up).max(score_left);
+                if best > 0 {
+                    scores[j] = best;
+                    directions[j] = choose_direction(best, score_diag, score_up, score_left);
+                    if run_match && directions[j] == 1 {
+                        runs[j] = run + 1;
+                    }
+                    if rules.record_ties {
+                        ties[j] = tie_bits(
+                            best,
+                            &[
+                                (score_diag, TIE_DIAG),
+                                (score_up, TIE_UP),
+                                (score_left, TIE_LEFT),
+                            ],
+                        );
+                    }
+                }
+            }
+
+            if scores[j] > self.max_score {
+                self.max_score = scores[j];
+                self.max_positions.clear();
+                self.max_positions.push((i, j));
+            } else if scores[j] == self.max_score && scores[j] > 0 && !rules.first_max_only {
+                self.max_positions.push((i, j));
             }
         }
+
+        // Counted per row from the mask, so the cell loop stays counter-free.
+        self.cells += match rules.band {
+            None => seq2.len() - rules.masked_count,
+            Some(_) => (first..=last)
+                .filter(|&j| !rules.masked.as_ref().is_some_and(|masked| masked[j - 1]))
+                .count(),
+        };
+        self.scores.push(scores);
+        self.directions.push(directions);
+        self.runs.push(runs);
+        self.ties.push(ties);
+        self.gap_extends.push(gap_extends);
+        self.up_gaps = up_gaps;
+    }
+}
+
+fn tie_bits(best: i32, moves: &[(i32, u8)]) -> u8 {
+    moves
+        .iter()
+        .filter(|&&(score, _)| score == best)
+        .fold(0, |bits, &(_, bit)| bits | bit)
+}
+
+/// Rejects inputs whose cell scores could overflow `i32`.
+///
+/// Cells are clamped at zero, so only the upper end matters: every step along a
+/// path adds at most the largest positive score, and a path has at most
+/// `len1 + len2` steps. Bounding that product (plus one more step for the moves
+/// evaluated out of the largest cell) keeps all intermediate sums in range.
+/// A run bonus adds at most `run_bonus * (0 + 1 + ... + min(len1, len2))`.
+pub(crate) fn check_score_range<T: Token, S: Scoring<T>>(
+    len1: usize,
+    len2: usize,
+    scoring: &S,
+) -> Result<(), AlignError> {
+    let params = scoring.params();
+    let step = i64::from(
+        scoring
+            .max_substitution()
+            .max(params.gap_query)
+            .max(params.gap_ref)
+            .max(0),
+    );
+    let steps = (len1 as i64).saturating_add(len2 as i64).saturating_add(1);
+    let longest_run = len1.min(len2) as i64;
+    let run_total = longest_run
+        .saturating_mul(longest_run.saturating_add(1))
+        .saturating_div(2)
+        .saturating_mul(i64::from(params.run_bonus.max(0)));
+    if steps.saturating_mul(step).saturating_add(run_total) > i64::from(i32::MAX) {
+        return Err(AlignError::ScoreOverflow);
+    }
+    Ok(())
+}
+
+pub(crate) fn empty_alignment() -> Alignment {
+    Alignment {
+        score: 0,
+        query_start: 0,
+        query_end: 0,
+        token_start: 0,
+        token_end: 0,
+        matches: 0,
+    }
+}
+
+pub fn align_topk(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    params: ScoreParams,
+    top_k: usize,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    align_topk_with(seq1, seqs, &params, top_k)
+}
+
+pub fn align_topk_with<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    top_k: usize,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    align_topk_tokens(seq1, seqs, scoring, top_k)
+}
+
+/// `align_topk_with` over any token width.
+pub fn align_topk_tokens<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seqs: &[Vec<T>],
+    scoring: &S,
+    top_k: usize,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    align_topk_filtered(seq1, seqs, scoring, top_k, |_| true)
+}
+
+/// `align_topk_with` over only the references whose best alignment matches
+/// at least `min_query_coverage` of the query (see
+/// `Alignment::query_coverage`).
+///
+/// Unlike a `min_score`, this cannot be met by a long reference piling up
+/// score from a partial overlap. References are filtered before ranking, so
+/// up to `top_k` passing references are returned.
+pub fn align_topk_covering<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    top_k: usize,
+    min_query_coverage: f64,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    check_query_coverage(min_query_coverage)?;
+    align_topk_filtered(seq1, seqs, scoring, top_k, |item| {
+        item.query_coverage(seq1.len()) >= min_query_coverage
+    })
+}
+
+/// `align_topk_covering` that also drops references whose best alignment
+/// has more than `max_gaps` gap columns (tokens of either sequence aligned to
+/// a gap), e.g. for near-exact quotes.
+///
+/// The budget is a post-hoc filter on each reference's optimal alignment, not
+/// a constraint in the DP: a reference whose best alignment is over budget is
+/// discarded even if a tighter, lower-scoring alignment exists inside it.
+/// `None` applies no budget and skips the extra traceback.
+///
+/// `min_columns` likewise drops references whose best alignment has fewer
+/// aligned columns, where a column is one step of the path: a token pair
+/// (matched or not) or a token aligned to a gap. Unlike the span or the match
+/// count, this rejects short, dense matches such as a two-token exact hit.
+/// `0` applies no minimum.
+pub fn align_topk_within<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    top_k: usize,
+    min_query_coverage: f64,
+    max_gaps: Option<usize>,
+    min_columns: usize,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    if max_gaps.is_none() && min_columns == 0 {
+        return align_topk_covering(seq1, seqs, scoring, top_k, min_query_coverage);
+    }
+    check_query_coverage(min_query_coverage)?;
+    align_topk_by(seqs, top_k, |index, seq2| {
+        let (alignment, path) = match exact_alignment(seq1, seq2, scoring)? {
+            Some(alignment) => (alignment, vec![Step::Diagonal; seq1.len()]),
+            None => smith_waterman_path(seq1, seq2, scoring, &Constraints::default())?,
+        };
+        let gaps = path.iter().filter(|&&step| step != Step::Diagonal).count();
+        let item = CandidateAlignment::from_alignment(index, &alignment);
+        Ok((max_gaps.is_none_or(|max_gaps| gaps <= max_gaps)
+            && path.len() >= min_columns
+            && item.query_coverage(seq1.len()) >= min_query_coverage)
+            .then_some(item))
+    })
+}
+
+/// Every reference whose best alignment scores at least `min_score`, ordered
+/// like `align_topk` but never truncated, for recall-oriented search that
+/// should not depend on guessing a large enough `top_k`.
+///
+/// With `min_score <= 0` every reference qualifies, including ones sharing
+/// nothing with the query.
+pub fn align_above_threshold<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    min_score: i32,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    align_topk_filtered(seq1, seqs, scoring, seqs.len(), |item| {
+        item.score >= min_score
+    })
+}
+
+fn check_query_coverage(min_query_coverage: f64) -> Result<(), AlignError> {
+    if !(0.0..=1.0).contains(&min_query_coverage) {
+        return Err(AlignError::InvalidArgument(format!(
+            "min_query_coverage must be between 0 and 1, got {min_query_coverage}"
+        )));
+    }
+    Ok(())
+}
+
+fn align_topk_filtered<T: Token, S: Scoring<T>>(
+    seq1: &[T],
+    seqs: &[Vec<T>],
+    scoring: &S,
+    top_k: usize,
+    keep: impl Fn(&CandidateAlignment) -> bool + Sync,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    align_topk_by(seqs, top_k, |index, seq2| {
+        let alignment = match exact_alignment(seq1, seq2, scoring)? {
+            Some(alignment) => alignment,
+            None => smith_waterman_tokens(seq1, seq2, scoring, &Constraints::default())?,
+        };
+        let item = CandidateAlignment::from_alignment(index, &alignment);
+        Ok(keep(&item).then_some(item))
+    })
+}
+
+/// Ranks the candidates `align` returns for each reference, in parallel;
+/// `None` leaves a reference out.
+fn align_topk_by<T: Sync>(
+    seqs: &[Vec<T>],
+    top_k: usize,
+    align: impl Fn(usize, &[T]) -> Result<Option<CandidateAlignment>, AlignError> + Sync,
+) -> Result<Vec<CandidateAlignment>, AlignError> {
+    if seqs.is_empty() || top_k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let results: Vec<CandidateAlignment> = seqs
+        .par_iter()
+        .enumerate()
+        .map(|(index, seq2)| align(index, seq2))
+        .collect::<Result<Vec<_>, AlignError>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    Ok(rank_candidates(results, top_k))
+}
+
+fn rank_candidates(mut results: Vec<CandidateAlignment>, top_k: usize) -> Vec<CandidateAlignment> {
+    results.sort_by(cmp_candidate);
+    results.truncate(top_k.min(results.len()));
+    results
+}
+
+/// Checks `ref_weights` for `rank_weighted`: one finite, non-negative weight
+/// per reference.
+pub fn check_ref_weights(ref_weights: &[f64], refs: usize) -> Result<(), AlignError> {
+    if ref_weights.len() != refs {
+        return Err(AlignError::InvalidArgument(format!(
+            "ref_weights has {} entries for {refs} references",
+            ref_weights.len()
+        )));
+    }
+    if let Some(weight) = ref_weights
+        .iter()
+        .find(|weight| !weight.is_finite() || **weight < 0.0)
+    {
+        return Err(AlignError::InvalidArgument(format!(
+            "ref_weights must be finite and non-negative, got {weight}"
+        )));
+    }
+    Ok(())
+}
+
+/// Keeps the `top_k` of `candidates` by weighted score, `score *
+/// ref_weights[index]`, highest first, e.g. to prefer authoritative sources
+/// when scores are close. Ties fall back to `cmp_candidate`.
+///
+/// The weight only ranks: each candidate keeps its raw `score`, and the DP is
+/// unchanged. Pass every candidate (rank with `top_k = seqs.len()` first), or
+/// a heavily weighted reference just outside the unweighted cut is lost.
+/// `ref_weights` must pass `check_ref_weights`.
+pub fn rank_weighted(
+    mut candidates: Vec<CandidateAlignment>,
+    ref_weights: &[f64],
+    top_k: usize,
+) -> Vec<CandidateAlignment> {
+    let weighted = |item: &CandidateAlignment| f64::from(item.score) * ref_weights[item.index];
+    candidates.sort_by(|left, right| {
+        weighted(right)
+            .total_cmp(&weighted(left))
+            .then_with(|| cmp_candidate(left, right))
+    });
+    candidates.truncate(top_k);
+    candidates
+}
+
+/// `align_topk_with` aligning each distinct reference once, for corpora with
+/// many duplicate references. Also returns how many alignments it ran, the
+/// number of distinct references.
+///
+/// References are grouped by their tokens, and each group's alignment is
+/// fanned out to every index in it, so the ranking equals `align_topk_with`'s
+/// (duplicates tie and break by index). The grouping hashes every reference
+/// once, which pays off only when duplicates are common.
+pub fn align_topk_deduped<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    top_k: usize,
+) -> Result<(Vec<CandidateAlignment>, usize), AlignError> {
+    if seqs.is_empty() || top_k == 0 {
+        return Ok((Vec::new(), 0));
+    }
+    let mut groups: HashMap<&[u32], usize> = HashMap::new();
+    let mut distinct: Vec<&[u32]> = Vec::new();
+    let group_of: Vec<usize> = seqs
+        .iter()
+        .map(|seq2| {
+            *groups.entry(seq2).or_insert_with(|| {
+                distinct.push(seq2);
+                distinct.len() - 1
+            })
+        })
+        .collect();
+
+    let alignments: Vec<Alignment> = distinct
+        .par_iter()
+        .map(|seq2| smith_waterman_with(seq1, seq2, scoring, &Constraints::default()))
+        .collect::<Result<_, AlignError>>()?;
+    let results = group_of
+        .iter()
+        .enumerate()
+        .map(|(index, &group)| CandidateAlignment::from_alignment(index, &alignments[group]))
+        .collect();
+    Ok((rank_candidates(results, top_k), distinct.len()))
+}
+
+/// `align_topk_with` plus how long each reference's DP took, indexed like
+/// `seqs`, for finding the references that dominate latency.
+///
+/// Each reference is timed around its own alignment on whichever worker ran
+/// it, so the durations add up to more than the wall time of a parallel call.
+/// The ranking is exactly `align_topk_with`'s.
+pub fn align_topk_timed<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    top_k: usize,
+) -> Result<(Vec<CandidateAlignment>, Vec<Duration>), AlignError> {
+    let timed: Vec<(CandidateAlignment, Duration)> = seqs
+        .par_iter()
+        .enumerate()
+        .map(|(index, seq2)| {
+            let started = Instant::now();
+            let alignment = smith_waterman_with(seq1, seq2, scoring, &Constraints::default())?;
+            let elapsed = started.elapsed();
+            Ok((
+                CandidateAlignment::from_alignment(index, &alignment),
+                elapsed,
+            ))
+        })
+        .collect::<Result<_, AlignError>>()?;
+    let (results, durations) = timed.into_iter().unzip();
+    Ok((rank_candidates(results, top_k), durations))
+}
+
+/// The top candidate of `align_topk`. Like every top-k search, a reference
+/// containing the query verbatim is resolved by `exact_alignment` without the
+/// DP.
+pub fn align_best(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    params: ScoreParams,
+) -> Result<Option<CandidateAlignment>, AlignError> {
+    Ok(align_topk(seq1, seqs, params, 1)?.into_iter().next())
+}
+
+/// The best candidate and its reference-frame match blocks.
+pub type BestWithBlocks = (CandidateAlignment, Vec<(usize, usize)>);
+
+/// `align_best` plus the winning reference's reference-frame match blocks.
+///
+/// Only the winner is traced back for blocks: the corpus pass scores every
+/// reference as usual, then the winner is aligned once more with
+/// `smith_waterman_match_blocks_with`.
+pub fn align_best_with_blocks<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+) -> Result<Option<BestWithBlocks>, AlignError> {
+    let Some(best) = align_topk_with(seq1, seqs, scoring, 1)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let (_, blocks) = smith_waterman_match_blocks_with(
+        seq1,
+        &seqs[best.index],
+        scoring,
+        &Constraints::default(),
+        BlockOptions::default(),
+    )?;
+    Ok(Some((best, blocks)))
+}
+
+/// `align_best` for every query in `queries`, in query order, computed in
+/// parallel over the queries.
+pub fn align_best_batch(
+    queries: &[Vec<u32>],
+    seqs: &[Vec<u32>],
+    params: ScoreParams,
+) -> Result<Vec<Option<CandidateAlignment>>, AlignError> {
+    queries
+        .par_iter()
+        .map(|query| align_best(query, seqs, params))
+        .collect()
+}
+
+/// Best alignment of every query in `queries` against one `reference`, in
+/// query order, computed in parallel over the queries.
+///
+/// The dual of `align_topk` for the "does this source contain any of these
+/// quotes" workload. Entry `i` equals `smith_waterman_with(&queries[i],
+/// reference, ..)`.
+pub fn align_queries_vs_reference<S: Scoring>(
+    queries: &[Vec<u32>],
+    reference: &[u32],
+    scoring: &S,
+) -> Result<Vec<Alignment>, AlignError> {
+    queries
+        .par_iter()
+        .map(|query| smith_waterman_with(query, reference, scoring, &Constraints::default()))
+        .collect()
+}
+
+/// Best alignment over several forms of one query (e.g. a title with and
+/// without its subtitle) against `reference`, plus the winning variant's
+/// index, or `None` when `variants` is empty.
+///
+/// The dual of `align_best`, varying the query instead of the reference:
+/// variants are aligned in parallel and compared on raw score, so a longer
+/// variant that matches more wins. Ties go to the alignment `cmp_alignment`
+/// ranks first, then to the lower variant index.
+pub fn align_variants_best<S: Scoring>(
+    variants: &[Vec<u32>],
+    reference: &[u32],
+    scoring: &S,
+) -> Result<Option<(Alignment, usize)>, AlignError> {
+    let all = align_queries_vs_reference(variants, reference, scoring)?;
+    Ok(all
+        .into_iter()
+        .enumerate()
+        .min_by(|(left_index, left), (right_index, right)| {
+            cmp_alignment(left, right).then(left_index.cmp(right_index))
+        })
+        .map(|(index, alignment)| (alignment, index)))
+}
+
+/// Best local alignment score of `seq1` in `seq2`, without the traceback.
+///
+/// Keeps only two DP rows, so it costs O(len2) memory instead of a full matrix.
+pub fn local_score<S: Scoring>(seq1: &[u32], seq2: &[u32], scoring: &S) -> Result<i32, AlignError> {
+    let mut max_score = 0i32;
+    two_row_scores(seq1, seq2, scoring, |_, score| {
+        max_score = max_score.max(score)
+    })?;
+    Ok(max_score)
+}
+
+/// Best DP score of any cell in each reference column: entry `j` is the
+/// highest score of an alignment ending on `seq2[j]`, or 0 when none pays
+/// off. Shows which reference positions attract matches.
+///
+/// A byproduct of the same two-row pass as `local_score`, in O(len2) memory.
+/// An empty query gives all zeros.
+pub fn column_maxima<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> Result<Vec<i32>, AlignError> {
+    let mut maxima = vec![0i32; seq2.len()];
+    two_row_scores(seq1, seq2, scoring, |j, score| {
+        maxima[j] = maxima[j].max(score);
+    })?;
+    Ok(maxima)
+}
+
+/// Fills the local DP row by row, keeping only two rows, and hands each
+/// cell's score to `visit` with its reference index `j` (the cell ending on
+/// `seq2[j]`).
+fn two_row_scores<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    mut visit: impl FnMut(usize, i32),
+) -> Result<(), AlignError> {
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(());
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    let params = scoring.params();
+
+    let mut previous = vec![0i32; seq2.len() + 1];
+    let mut current = vec![0i32; seq2.len() + 1];
+    let mut previous_runs = vec![0u32; seq2.len() + 1];
+    let mut current_runs = vec![0u32; seq2.len() + 1];
+    // Affine gap states, as in `ScoreMatrix::push_row`.
+    let affine = params.is_affine();
+    let mut up_gaps = vec![UNREACHABLE; if affine { seq2.len() + 1 } else { 0 }];
+    for (row, &a) in seq1.iter().enumerate() {
+        let (gap_up, gap_left) = params.row_gaps(row + 1, seq1.len());
+        let mut left_gap = UNREACHABLE;
+        let breaks_row = params.is_separator(a);
+        for (j, &b) in seq2.iter().enumerate() {
+            if breaks_row || params.is_separator(b) {
+                current[j + 1] = 0;
+                current_runs[j + 1] = 0;
+                if affine {
+                    up_gaps[j + 1] = UNREACHABLE;
+                }
+                left_gap = UNREACHABLE;
+                visit(j, 0);
+                continue;
+            }
+            let run_match = params.run_bonus > 0 && scoring.is_match(a, b);
+            let bonus = if run_match {
+                params.run_bonus * previous_runs[j] as i32
+            } else {
+                0
+            };
+            let score_diag = previous[j] + scoring.substitution(a, b) + bonus;
+            let (score_up, score_left) = if affine {
+                let open = params.gap_open;
+                up_gaps[j + 1] = previous[j + 1]
+                    .saturating_add(open)
+                    .max(up_gaps[j + 1])
+                    .saturating_add(gap_up)
+                    .max(UNREACHABLE);
+                left_gap = current[j]
+                    .saturating_add(open)
+                    .max(left_gap)
+                    .saturating_add(gap_left)
+                    .max(UNREACHABLE);
+                (up_gaps[j + 1], left_gap)
+            } else {
+                (previous[j + 1] + gap_up, current[j] + gap_left)
+            };
+            let best = 0i32.max(score_diag).max(score_up).max(score_left);
+            current[j + 1] = best;
+            // Same precedence as `choose_direction`: the diagonal wins ties.
+            current_runs[j + 1] = if run_match && best > 0 && best == score_diag {
+                previous_runs[j] + 1
+            } else {
+                0
+            };
+            visit(j, best);
+        }
+        std::mem::swap(&mut previous, &mut current);
+        std::mem::swap(&mut previous_runs, &mut current_runs);
+    }
+    Ok(())
+}
+
+/// Writes the `local_score` of `seq1` against each of `seqs` into `out`,
+/// in reference order, so a hot loop can reuse one buffer across queries.
+///
+/// `out` must hold exactly one slot per reference; on error its contents are
+/// unspecified.
+pub fn align_all_scores_into<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    out: &mut [i32],
+) -> Result<(), AlignError> {
+    if out.len() != seqs.len() {
+        return Err(AlignError::InvalidArgument(format!(
+            "score buffer has {} slots for {} references",
+            out.len(),
+            seqs.len()
+        )));
+    }
+    out.par_iter_mut().zip(seqs).try_for_each(|(slot, seq2)| {
+        *slot = local_score(seq1, seq2, scoring)?;
+        Ok(())
+    })
+}
+
+/// Highest-scoring reference within each score bucket, e.g. one
+/// representative match per histogram bin.
+///
+/// `bucket_edges` must be strictly increasing: bucket `k` holds scores in
+/// `bucket_edges[k]..bucket_edges[k + 1]`, so `n` edges give `n - 1` buckets,
+/// and references scoring outside every bucket are ignored. Scores come from
+/// `align_all_scores_into`; only the references tied for a bucket's top score
+/// are traced back, and ties break like `align_topk`. Empty buckets are `None`.
+pub fn align_best_per_bucket<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    bucket_edges: &[i32],
+    scoring: &S,
+) -> Result<Vec<Option<CandidateAlignment>>, AlignError> {
+    if bucket_edges.len() < 2 || bucket_edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(AlignError::InvalidArgument(
+            "bucket_edges must hold at least two strictly increasing scores".to_string(),
+        ));
+    }
+    let buckets = bucket_edges.len() - 1;
+    let bucket_of = |score: i32| {
+        bucket_edges
+            .partition_point(|&edge| edge <= score)
+            .checked_sub(1)
+            .filter(|&bucket| bucket < buckets)
+    };
+    let mut scores = vec![0i32; seqs.len()];
+    align_all_scores_into(seq1, seqs, scoring, &mut scores)?;
+
+    let mut top_scores: Vec<Option<i32>> = vec![None; buckets];
+    for &score in &scores {
+        if let Some(bucket) = bucket_of(score) {
+            top_scores[bucket] = top_scores[bucket].max(Some(score));
+        }
+    }
+    let tied: Vec<(usize, usize)> = scores
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &score)| {
+            let bucket = bucket_of(score)?;
+            (top_scores[bucket] == Some(score)).then_some((bucket, index))
+        })
+        .collect();
+    let aligned: Vec<(usize, CandidateAlignment)> = tied
+        .par_iter()
+        .map(|&(bucket, index)| {
+            let alignment =
+                smith_waterman_with(seq1, &seqs[index], scoring, &Constraints::default())?;
+            Ok((
+                bucket,
+                CandidateAlignment::from_alignment(index, &alignment),
+            ))
+        })
+        .collect::<Result<_, AlignError>>()?;
+
+    let mut best: Vec<Option<CandidateAlignment>> = vec![None; buckets];
+    for (bucket, candidate) in aligned {
+        if best[bucket]
+            .as_ref()
+            .is_none_or(|current| cmp_candidate(&candidate, current) == Ordering::Less)
+        {
+            best[bucket] = Some(candidate);
+        }
+    }
+    Ok(best)
+}
+
+/// `(cutoff, candidates)`: the `percentile`-th percentile of the corpus's best
+/// scores, and every reference scoring strictly above it, ordered like
+/// `align_topk`, for thresholds that adapt to the corpus.
+///
+/// `percentile` is in `0.0..=100.0`; the cutoff is the nearest-rank
+/// percentile, the smallest score at least `percentile` percent of references
+/// reach, so with distinct scores the 90th percentile keeps the top tenth.
+/// References tied with the cutoff are left out, and 100 keeps none. Scores
+/// come from `align_all_scores_into`; only the kept references are traced
+/// back. An empty corpus gives `(0, [])`.
+pub fn align_above_percentile<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    percentile: f64,
+) -> Result<(i32, Vec<CandidateAlignment>), AlignError> {
+    if !(0.0..=100.0).contains(&percentile) {
+        return Err(AlignError::InvalidArgument(format!(
+            "percentile must be between 0 and 100, got {percentile}"
+        )));
+    }
+    let mut scores = vec![0i32; seqs.len()];
+    align_all_scores_into(seq1, seqs, scoring, &mut scores)?;
+    let mut sorted = scores.clone();
+    sorted.sort_unstable();
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    let Some(&cutoff) = sorted.get(rank.saturating_sub(1)) else {
+        return Ok((0, Vec::new()));
+    };
+
+    let kept: Vec<usize> = (0..seqs.len())
+        .filter(|&index| scores[index] > cutoff)
+        .collect();
+    let candidates: Vec<CandidateAlignment> = kept
+        .par_iter()
+        .map(|&index| {
+            let alignment =
+                smith_waterman_with(seq1, &seqs[index], scoring, &Constraints::default())?;
+            Ok(CandidateAlignment::from_alignment(index, &alignment))
+        })
+        .collect::<Result<_, AlignError>>()?;
+    Ok((cutoff, rank_candidates(candidates, seqs.len())))
+}
+
+/// `(first, scored)`: a reference scoring at least `target_score`, and how
+/// many references were scored, for "first good enough match" searches that
+/// should not align the whole corpus.
+///
+/// References are scored with `local_score` in parallel and the scan stops
+/// once one qualifies; only that one is traced back. With `in_order`, `first`
+/// is the lowest-index qualifying reference; otherwise it is whichever any
+/// worker finds first, which can differ between runs. Either way, workers
+/// already busy when the match turns up finish their references, so `scored`
+/// may exceed the winner's position by up to a few per thread. `first` is
+/// `None` when no reference qualifies, after scoring them all.
+pub fn align_first_above<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+    target_score: i32,
+    in_order: bool,
+) -> Result<(Option<CandidateAlignment>, usize), AlignError> {
+    let scored = AtomicUsize::new(0);
+    let qualifies = |(index, seq2): (usize, &Vec<u32>)| {
+        scored.fetch_add(1, AtomicOrdering::Relaxed);
+        match local_score(seq1, seq2, scoring) {
+            Ok(score) if score >= target_score => Some(Ok(index)),
+            Ok(_) => None,
+            Err(error) => Some(Err(error)),
+        }
+    };
+    let found = if in_order {
+        seqs.par_iter().enumerate().find_map_first(qualifies)
+    } else {
+        seqs.par_iter().enumerate().find_map_any(qualifies)
+    };
+    let first = match found.transpose()? {
+        Some(index) => {
+            let alignment =
+                smith_waterman_with(seq1, &seqs[index], scoring, &Constraints::default())?;
+            Some(CandidateAlignment::from_alignment(index, &alignment))
+        }
+        None => None,
+    };
+    Ok((first, scored.into_inner()))
+}
+
+/// `align_best` plus the runner-up reference's score, for margin checks.
+///
+/// Scores every reference with `local_score` and traces back only the
+/// references tied for the top score. The runner-up is `None` when there is
+/// a single reference; it equals the best score when the top is tied.
+pub fn align_best_with_margin<S: Scoring>(
+    seq1: &[u32],
+    seqs: &[Vec<u32>],
+    scoring: &S,
+) -> Result<(Option<CandidateAlignment>, Option<i32>), AlignError> {
+    let scores: Vec<i32> = seqs
+        .par_iter()
+        .map(|seq2| local_score(seq1, seq2, scoring))
+        .collect::<Result<_, AlignError>>()?;
+    let Some(&max_score) = scores.iter().max() else {
+        return Ok((None, None));
+    };
+
+    let tied: Vec<usize> = (0..seqs.len())
+        .filter(|&index| scores[index] == max_score)
+        .collect();
+    let runner_up = if tied.len() > 1 {
+        Some(max_score)
+    } else {
+        scores
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != tied[0])
+            .map(|(_, &score)| score)
+            .max()
+    };
+
+    let mut best: Option<CandidateAlignment> = None;
+    for index in tied {
+        let alignment = smith_waterman_with(seq1, &seqs[index], scoring, &Constraints::default())?;
+        let candidate = CandidateAlignment::from_alignment(index, &alignment);
+        if best
+            .as_ref()
+            .is_none_or(|current| cmp_candidate(&candidate, current) == Ordering::Less)
+        {
+            best = Some(candidate);
+        }
+    }
+    Ok((best, runner_up))
+}
+
+/// Row-major `n x n` matrix of best local scores between every pair of `seqs`,
+/// with `seqs[i]` as the query and `seqs[j]` as the reference at `[i * n + j]`.
+///
+/// The diagonal holds self-alignment scores. When `scoring` is symmetric (see
+/// `Scoring::is_symmetric`), each unordered pair is scored once, in parallel,
+/// and mirrored; otherwise every ordered pair is scored.
+pub fn pairwise_scores<S: Scoring>(seqs: &[Vec<u32>], scoring: &S) -> Result<Vec<i32>, AlignError> {
+    let n = seqs.len();
+    let symmetric = scoring.is_symmetric();
+    let pairs: Vec<(usize, usize)> = if symmetric {
+        (0..n).flat_map(|i| (i..n).map(move |j| (i, j))).collect()
+    } else {
+        (0..n).flat_map(|i| (0..n).map(move |j| (i, j))).collect()
+    };
+    let scores: Vec<i32> = pairs
+        .par_iter()
+        .map(|&(i, j)| local_score(&seqs[i], &seqs[j], scoring))
+        .collect::<Result<_, AlignError>>()?;
+
+    let mut matrix = vec![0i32; n * n];
+    for (&(i, j), score) in pairs.iter().zip(scores) {
+        matrix[i * n + j] = score;
+        if symmetric {
+            matrix[j * n + i] = score;
+        }
+    }
+    Ok(matrix)
+}
+
+fn choose_direction(best: i32, score_diag: i32, score_up: i32, _score_left: i32) -> u8 {
+    if best == score_diag {
+        return 1;
+    }
+    if best == score_up {
+        return 2;
+    }
+    3
+}
+
+/// Walks the traceback from `(i, j)`, calling `visit` with each step and the
+/// cell it ends in (the step aligns `seq1[i - 1]` and/or `seq2[j - 1]`), and
+/// returns the cell the path starts from.
+///
+/// Under affine gaps a gap move is followed by further gap moves while the
+/// cell's `GAP_*_EXTENDS` bit says the gap continued from its neighbor.
+fn walk_traceback(
+    mut i: usize,
+    mut j: usize,
+    matrix: &ScoreMatrix,
+    mut visit: impl FnMut(Step, usize, usize),
+) -> (usize, usize) {
+    let extends = |i: usize, j: usize, bit: u8| {
+        matrix.gap_extends[i]
+            .get(j)
+            .is_some_and(|&bits| bits & bit != 0)
+    };
+    // 0 while the path is in the score matrix, else the gap direction it is in.
+    let mut in_gap = 0u8;
+    loop {
+        let direction = if in_gap != 0 {
+            in_gap
+        } else if i > 0 && j > 0 && matrix.directions[i][j] != 0 && matrix.scores[i][j] > 0 {
+            matrix.directions[i][j]
+        } else {
+            break;
+        };
+        match direction {
+            direction @ (1 | 4) => {
+                visit(Step::Diagonal, i, j);
+                i -= 1;
+                j -= 1;
+                if direction == 4 {
+                    break;
+                }
+            }
+            2 => {
+                visit(Step::Up, i, j);
+                in_gap = if extends(i, j, GAP_UP_EXTENDS) { 2 } else { 0 };
+                i -= 1;
+            }
+            _ => {
+                visit(Step::Left, i, j);
+                in_gap = if extends(i, j, GAP_LEFT_EXTENDS) {
+                    3
+                } else {
+                    0
+                };
+                j -= 1;
+            }
+        }
+    }
+    (i, j)
+}
+
+/// Walks the traceback from `(i, j)` and returns its steps in forward order.
+fn traceback_path(i: usize, j: usize, matrix: &ScoreMatrix) -> Vec<Step> {
+    let mut path = Vec::new();
+    walk_traceback(i, j, matrix, |step, _, _| path.push(step));
+    path.reverse();
+    path
+}
+
+fn traceback_details<T: Token>(
+    i: usize,
+    j: usize,
+    matrix: &ScoreMatrix,
+    seq1: &[T],
+    seq2: &[T],
+    scoring: &impl Scoring<T>,
+) -> (usize, usize, usize) {
+    let mut matches = 0usize;
+    let (i, j) = walk_traceback(i, j, matrix, |step, i, j| {
+        if step == Step::Diagonal && scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+            matches += 1;
+        }
+    });
+    (i, j, matches)
+}
+
+fn traceback_details_with_match_blocks(
+    i: usize,
+    j: usize,
+    matrix: &ScoreMatrix,
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &impl Scoring,
+    frame: BlockFrame,
+) -> (usize, usize, usize, Vec<(usize, usize)>) {
+    let mut matches = 0usize;
+    let mut match_positions: Vec<usize> = Vec::new();
+    let (i, j) = walk_traceback(i, j, matrix, |step, i, j| {
+        if step == Step::Diagonal && scoring.is_match(seq1[i - 1], seq2[j - 1]) {
+            matches += 1;
+            match_positions.push(match frame {
+                BlockFrame::Query => i - 1,
+                BlockFrame::Reference => j - 1,
+            });
+        }
+    });
+
+    if match_positions.is_empty() {
+        return (i, j, matches, Vec::new());
+    }
+
+    match_positions.reverse();
+    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    let mut start = match_positions[0];
+    let mut prev = start;
+    for pos in match_positions.into_iter().skip(1) {
+        if pos == prev + 1 {
+            prev = pos;
+            continue;
+        }
+        blocks.push((start, prev + 1));
+        start = pos;
+        prev = pos;
+    }
+    blocks.push((start, prev + 1));
+
+    (i, j, matches, blocks)
+}
+
+pub(crate) fn cmp_alignment(left: &Alignment, right: &Alignment) -> Ordering {
+    if left.score != right.score {
+        return right.score.cmp(&left.score);
+    }
+    if left.token_start != right.token_start {
+        return left.token_start.cmp(&right.token_start);
+    }
+
+    let left_span = left.token_end - left.token_start;
+    let right_span = right.token_end - right.token_start;
+    if left_span != right_span {
+        return right_span.cmp(&left_span);
+    }
+
+    if left.query_start != right.query_start {
+        return left.query_start.cmp(&right.query_start);
+    }
+    if left.token_end != right.token_end {
+        return left.token_end.cmp(&right.token_end);
+    }
+    left.query_end.cmp(&right.query_end)
+}
+
+pub(crate) fn cmp_candidate(left: &CandidateAlignment, right: &CandidateAlignment) -> Ordering {
+    if left.score != right.score {
+        return right.score.cmp(&left.score);
+    }
+    if left.token_start != right.token_start {
+        return left.token_start.cmp(&right.token_start);
+    }
+
+    let left_span = left.token_end - left.token_start;
+    let right_span = right.token_end - right.token_start;
+    if left_span != right_span {
+        return right_span.cmp(&left_span);
+    }
+
+    if left.query_start != right.query_start {
+        return left.query_start.cmp(&right.query_start);
+    }
+    if left.index != right.index {
+        return left.index.cmp(&right.index);
+    }
+    if left.token_end != right.token_end {
+        return left.token_end.cmp(&right.token_end);
+    }
+    left.query_end.cmp(&right.query_end)
+}
+
+/// Reorders `candidates` by `key`, falling back to `cmp_candidate` on ties.
+///
+/// Only the order changes: apply it after top-k selection, which is always
+/// by score, so a different key never lets a lower-scoring reference in.
+pub fn sort_candidates(candidates: &mut [CandidateAlignment], key: SortKey) {
+    candidates.sort_by(|left, right| {
+        let primary = match key {
+            SortKey::Score => Ordering::Equal,
+            SortKey::Matches => right.matches.cmp(&left.matches),
+            SortKey::PercentIdentity => {
+                right.percent_identity().total_cmp(&left.percent_identity())
+            }
+            SortKey::TokenStart => left.token_start.cmp(&right.token_start),
+        };
+        primary.then_with(|| cmp_candidate(left, right))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smith_waterman_prefers_earlier_start() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2];
+        let seq2 = vec![1, 2, 1, 2];
+        let alignment = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!(alignment.score, 4);
+        assert_eq!(alignment.token_start, 0);
+        assert_eq!(alignment.token_end, 2);
+        assert_eq!(alignment.matches, 2);
+        assert_eq!(alignment.query_start, 0);
+        assert_eq!(alignment.query_end, 2);
+    }
+
+    #[test]
+    fn smith_waterman_match_blocks_returns_disjoint_blocks() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seq2 = vec![1, 2, 9, 9, 3, 4];
+
+        let (alignment, match_blocks) =
+            smith_waterman_match_blocks(&seq1, &seq2, params, BlockOptions::default()).unwrap();
+        assert_eq!(alignment.score, 6);
+        assert_eq!(alignment.token_start, 0);
+        assert_eq!(alignment.token_end, 6);
+        assert_eq!(alignment.query_start, 0);
+        assert_eq!(alignment.query_end, 4);
+        assert_eq!(alignment.matches, 4);
+        assert_eq!(match_blocks, vec![(0, 2), (4, 6)]);
+    }
+
+    #[test]
+    fn match_block_counts_report_matches_per_block() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seq2 = vec![1, 2, 9, 3, 4];
+        let constraints = Constraints::default();
+
+        let (alignment, blocks) =
+            smith_waterman_match_block_counts_with(&seq1, &seq2, &params, &constraints, 0).unwrap();
+        assert_eq!(alignment.score, 7);
+        assert_eq!(blocks, vec![(0, 2, 2), (3, 5, 2)]);
+
+        let (_, merged) =
+            smith_waterman_match_block_counts_with(&seq1, &seq2, &params, &constraints, 1).unwrap();
+        assert_eq!(merged, vec![(0, 5, 4)]);
+    }
+
+    #[test]
+    fn align_topk_is_deterministic_and_sorted() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2];
+        let seqs = vec![vec![3, 4], vec![1, 2, 1, 2], vec![1, 2], vec![0, 1, 2, 3]];
+        let top = align_topk(&seq1, &seqs, params, 3).unwrap();
+        assert_eq!(top.len(), 3);
+        assert_eq!(top[0].index, 1);
+        assert_eq!(top[1].index, 2);
+        assert_eq!(top[2].index, 3);
+    }
+
+    #[test]
+    fn best_with_margin_reports_runner_up_score() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seqs = vec![vec![1, 2, 3], vec![9, 9], vec![1, 2, 3, 4], vec![1, 2]];
+
+        let (best, runner_up) = align_best_with_margin(&seq1, &seqs, &params).unwrap();
+        let best = best.unwrap();
+        assert_eq!((best.index, best.score), (2, 8));
+        assert_eq!(runner_up, Some(6));
+        for (seq2, expected) in seqs.iter().zip([6, 0, 8, 4]) {
+            assert_eq!(local_score(&seq1, seq2, &params).unwrap(), expected);
+        }
+
+        let (best, runner_up) = align_best_with_margin(&seq1, &seqs[..1], &params).unwrap();
+        assert_eq!(best.unwrap().score, 6);
+        assert_eq!(runner_up, None);
+    }
+
+    #[test]
+    fn all_max_returns_each_symmetric_region() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3];
+        let seq2 = vec![1, 2, 3, 9, 9, 1, 2, 3];
+
+        let all = smith_waterman_all_max(&seq1, &seq2, &params, &Constraints::default()).unwrap();
+        let spans: Vec<_> = all
+            .iter()
+            .map(|item| (item.score, item.token_start, item.token_end))
+            .collect();
+        assert_eq!(spans, vec![(6, 0, 3), (6, 5, 8)]);
+
+        let best = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!((best.token_start, best.token_end), (0, 3));
+        assert!(
+            smith_waterman_all_max(&seq1, &[7, 7], &params, &Constraints::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn queries_vs_reference_match_single_pair_calls() {
+        let params = ScoreParams::new(2, -1, -1);
+        let reference = vec![5, 1, 2, 3, 9, 4, 5, 6, 1, 2];
+        let queries = vec![
+            vec![1, 2, 3],
+            vec![4, 6],
+            vec![],
+            vec![7, 8],
+            vec![2, 9, 4, 5],
+        ];
+
+        let all = align_queries_vs_reference(&queries, &reference, &params).unwrap();
+        assert_eq!(all.len(), queries.len());
+        for (query, alignment) in queries.iter().zip(&all) {
+            let single = smith_waterman(query, &reference, params).unwrap();
+            assert_eq!(
+                (
+                    alignment.score,
+                    alignment.query_start,
+                    alignment.token_start,
+                    alignment.token_end
+                ),
+                (
+                    single.score,
+                    single.query_start,
+                    single.token_start,
+                    single.token_end
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn first_max_only_keeps_one_position_on_repeats() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2];
+        let seq2: Vec<u32> = [1, 2].repeat(50);
+        let options = FillOptions {
+            first_max_only: true,
+            ..FillOptions::default()
+        };
+
+        let all = fill_rows(
+            &seq1,
+            &seq2,
+            &params,
+            &Constraints::default(),
+            FillOptions::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(all.max_positions.len(), 50);
+        let first = fill_rows(&seq1, &seq2, &params, &Constraints::default(), options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.max_positions, vec![(2, 2)]);
+
+        for _ in 0..3 {
+            let alignment =
+                smith_waterman_first_max(&seq1, &seq2, &params, &Constraints::default()).unwrap();
+            assert_eq!(
+                (alignment.score, alignment.token_start, alignment.token_end),
+                (4, 0, 2)
+            );
+        }
+    }
+
+    #[test]
+    fn pairwise_scores_are_symmetric() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seqs = vec![vec![1, 2, 3], vec![2, 3], vec![9], vec![1, 2, 9, 3]];
+        let matrix = pairwise_scores(&seqs, &params).unwrap();
+        let n = seqs.len();
+        for i in 0..n {
+            for j in 0..n {
+                assert_eq!(matrix[i * n + j], matrix[j * n + i]);
+            }
+        }
+        assert_eq!(matrix[0], 6);
+        assert_eq!(matrix[1], 4);
+        assert_eq!(matrix[2 * n + 2], 2);
+        assert_eq!(matrix[3], 5);
+        assert!(pairwise_scores(&[], &params).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pairwise_scores_keep_each_orientation_under_asymmetric_gaps() {
+        let params = ScoreParams {
+            gap_ref: -3,
+            ..ScoreParams::new(2, -1, -1)
+        };
+        let seqs = vec![vec![1, 2, 3], vec![1, 9, 2, 3]];
+        let matrix = pairwise_scores(&seqs, &params).unwrap();
+        // As the query, the shorter sequence must skip the reference's 9 at
+        // `gap_ref`; as the reference, the 9 is a cheaper query gap.
+        assert_eq!(matrix[1], 4);
+        assert_eq!(matrix[2], 5);
+        for i in 0..2 {
+            for j in 0..2 {
+                assert_eq!(
+                    matrix[i * 2 + j],
+                    local_score(&seqs[i], &seqs[j], &params).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pairwise_scores_keep_each_orientation_under_one_way_scoring() {
+        let cross_map = HashMap::from([(100, 1)]);
+        let scoring = crate::scoring::CrossVocabularyScoring {
+            params: ScoreParams::new(2, -1, -1),
+            cross_map: &cross_map,
+        };
+        let seqs = vec![vec![100, 2], vec![1, 2]];
+        let matrix = pairwise_scores(&seqs, &scoring).unwrap();
+        // Only the query side is translated, so 100 matches 1 in one
+        // orientation alone.
+        assert_eq!(matrix[1], 4);
+        assert_eq!(matrix[2], 2);
+    }
+
+    #[test]
+    fn run_bonus_rewards_contiguous_matches() {
+        let base = ScoreParams::new(2, 0, -1);
+        let bonus = ScoreParams {
+            run_bonus: 1,
+            ..base
+        };
+        let query = vec![1, 2, 3, 4, 5, 6, 7];
+        let contiguous = vec![1, 2, 3, 4, 0, 0, 0];
+        let scattered = vec![1, 0, 3, 0, 5, 0, 7];
+
+        let plain = |seq2: &[u32]| smith_waterman(&query, seq2, base).unwrap().score;
+        assert_eq!(plain(&contiguous), 8);
+        assert_eq!(plain(&scattered), 8);
+
+        let run = smith_waterman(&query, &contiguous, bonus).unwrap();
+        assert_eq!(run.score, 8 + 1 + 2 + 3);
+        assert_eq!(run.matches, 4);
+        assert_eq!(smith_waterman(&query, &scattered, bonus).unwrap().score, 8);
+        for seq2 in [&contiguous, &scattered] {
+            let full = smith_waterman(&query, seq2, bonus).unwrap().score;
+            assert_eq!(local_score(&query, seq2, &bonus).unwrap(), full);
+        }
+
+        let negative = ScoreParams {
+            run_bonus: -1,
+            ..base
+        };
+        assert!(negative.validate().is_err());
+    }
+
+    #[test]
+    fn end_gap_discount_moves_gaps_toward_query_ends() {
+        let query = vec![1, 2, 3, 4, 5, 6];
+        // An interior reference insertion (after token 3) comes first; the
+        // second copy has its insertion next to the query's last token.
+        let reference = vec![1, 2, 3, 8, 4, 5, 6, 0, 0, 1, 2, 3, 4, 5, 9, 6];
+
+        let plain = smith_waterman(&query, &reference, ScoreParams::new(2, -1, -1)).unwrap();
+        assert_eq!(
+            (plain.score, plain.token_start, plain.token_end),
+            (11, 0, 7)
+        );
+
+        let discounted = ScoreParams {
+            end_gap_discount: 2,
+            ..ScoreParams::new(2, -1, -1)
+        };
+        assert_eq!(discounted.row_gaps(3, 6), (-1, -1));
+        assert_eq!(discounted.row_gaps(5, 6), (0, 0));
+        let alignment = smith_waterman(&query, &reference, discounted).unwrap();
+        assert_eq!(
+            (alignment.score, alignment.token_start, alignment.token_end),
+            (12, 9, 16)
+        );
+        assert_eq!(local_score(&query, &reference, &discounted).unwrap(), 12);
+    }
+
+    #[test]
+    fn stats_count_every_cell_of_the_full_matrix() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seq2 = vec![1, 2, 9, 3, 4, 7];
+
+        let (alignment, stats) =
+            smith_waterman_stats(&seq1, &seq2, &params, &Constraints::default()).unwrap();
+        assert_eq!(stats.cells_computed, seq1.len() * seq2.len());
+        assert_eq!(alignment.score, 7);
+        // Four diagonal steps plus one left move over the inserted token.
+        assert_eq!(stats.traceback_steps, 5);
+
+        let masked = Constraints {
+            mask: vec![(4, 6)],
+            ..Constraints::default()
+        };
+        let (_, stats) = smith_waterman_stats(&seq1, &seq2, &params, &masked).unwrap();
+        assert_eq!(stats.cells_computed, seq1.len() * 4);
+    }
+
+    #[test]
+    fn checked_distinguishes_empty_input_from_no_match() {
+        let params = ScoreParams::new(2, -1, -1);
+        let none = Constraints::default();
+        assert!(
+            smith_waterman_checked(&[], &[1, 2], &params, &none)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            smith_waterman_checked(&[1, 2], &[], &params, &none)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            smith_waterman_checked(&[], &[], &params, &none)
+                .unwrap()
+                .is_none()
+        );
+
+        let no_match = smith_waterman_checked(&[1], &[2], &params, &none)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (no_match.score, no_match.token_start, no_match.token_end),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn single_token_sequences_give_unit_spans() {
+        let params = ScoreParams::new(2, -1, -1);
+        let query_frame = BlockOptions {
+            frame: BlockFrame::Query,
+            ..BlockOptions::default()
+        };
+        // (seq1, seq2, query span, reference span)
+        let cases = [
+            (vec![5], vec![5], (0, 1), (0, 1)),
+            (vec![5], vec![5, 7, 8], (0, 1), (0, 1)),
+            (vec![5], vec![7, 8, 5], (0, 1), (2, 3)),
+            (vec![5], vec![7, 5, 8], (0, 1), (1, 2)),
+            (vec![7, 8, 5], vec![5], (2, 3), (0, 1)),
+            (vec![5, 7, 8], vec![5], (0, 1), (0, 1)),
+        ];
+        for (seq1, seq2, query, reference) in cases {
+            let (seq1, seq2) = (seq1.as_slice(), seq2.as_slice());
+            let alignment = smith_waterman(seq1, seq2, params).unwrap();
+            assert_eq!(
+                (alignment.score, alignment.matches),
+                (params.match_score, 1),
+                "{seq1:?} in {seq2:?}"
+            );
+            assert_eq!((alignment.query_start, alignment.query_end), query);
+            assert_eq!((alignment.token_start, alignment.token_end), reference);
+            assert_eq!(alignment.inclusive_ends(), Some((query.0, reference.0)));
+
+            let (blocked, blocks) =
+                smith_waterman_match_blocks(seq1, seq2, params, BlockOptions::default()).unwrap();
+            assert_eq!(
+                (blocked.score, blocked.token_start, blocked.token_end),
+                (alignment.score, reference.0, reference.1)
+            );
+            assert_eq!(blocks, vec![reference]);
+            let (_, blocks) = smith_waterman_match_blocks(seq1, seq2, params, query_frame).unwrap();
+            assert_eq!(blocks, vec![query]);
+        }
+
+        // A lone mismatch, on either side, is no alignment at all.
+        for (seq1, seq2) in [(&[5][..], &[7, 8][..]), (&[7, 8][..], &[5][..])] {
+            let (alignment, blocks) =
+                smith_waterman_match_blocks(seq1, seq2, params, BlockOptions::default()).unwrap();
+            assert_eq!((alignment.score, alignment.matches), (0, 0));
+            assert_eq!((alignment.token_start, alignment.token_end), (0, 0));
+            assert!(blocks.is_empty());
+        }
+    }
+
+    #[test]
+    fn wide_tokens_do_not_collide_when_truncation_would() {
+        let params = ScoreParams::new(2, -1, -1);
+        let wide = (1u64 << 32) | 5;
+        assert_eq!(wide as u32, 5);
+
+        let seq1 = vec![wide, 7, 8];
+        let seq2 = vec![5u64, 7, 8];
+        let none = Constraints::default();
+        let alignment = smith_waterman_tokens(&seq1, &seq2, &params, &none).unwrap();
+        assert_eq!((alignment.score, alignment.token_start), (4, 1));
+        assert_eq!(alignment.matches, 2);
+
+        let truncated: Vec<u32> = seq1.iter().map(|&token| token as u32).collect();
+        let collided = smith_waterman(&truncated, &[5, 7, 8], params).unwrap();
+        assert_eq!(collided.score, 6);
+
+        let top = align_topk_tokens(&seq1, &[seq2.clone(), seq1.clone()], &params, 1).unwrap();
+        assert_eq!((top[0].index, top[0].score), (1, 6));
+    }
+
+    #[test]
+    fn byte_tokens_align_like_their_u32_ids() {
+        let params = ScoreParams {
+            wildcard: Some(u32::from(b'?')),
+            ..ScoreParams::new(2, -1, -1)
+        };
+        let (seq1, seq2) = (b"the quick fox", b"a quick brown ?ox");
+        let none = Constraints::default();
+        let bytes = smith_waterman_tokens(seq1, seq2, &params, &none).unwrap();
+        let widened = |seq: &[u8]| seq.iter().map(|&b| u32::from(b)).collect::<Vec<_>>();
+        let ids = smith_waterman_with(&widened(seq1), &widened(seq2), &params, &none).unwrap();
+        assert_eq!(
+            (
+                bytes.score,
+                bytes.token_start,
+                bytes.token_end,
+                bytes.matches
+            ),
+            (ids.score, ids.token_start, ids.token_end, ids.matches)
+        );
+        assert_eq!(bytes.query_start, ids.query_start);
+    }
+
+    #[test]
+    fn coverage_uses_whole_sequence_denominators() {
+        let params = ScoreParams::new(2, -1, -1);
+        let query = vec![1, 2, 3, 8];
+        let reference = vec![0, 0, 1, 2, 9, 3, 0, 0, 0, 0];
+
+        let alignment = smith_waterman(&query, &reference, params).unwrap();
+        assert_eq!(
+            (
+                alignment.matches,
+                alignment.token_start,
+                alignment.token_end
+            ),
+            (3, 2, 6)
+        );
+        assert_eq!(alignment.query_coverage(query.len()), 0.75);
+        assert_eq!(alignment.reference_coverage(reference.len()), 0.4);
+
+        let empty = smith_waterman(&[], &reference, params).unwrap();
+        assert_eq!(empty.query_coverage(0), 0.0);
+        assert_eq!(empty.reference_coverage(0), 0.0);
+    }
+
+    #[test]
+    fn clips_count_tokens_outside_the_alignment() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Only the query's middle [1, 2, 3] is quoted.
+        let query = vec![7, 8, 1, 2, 3, 9];
+        let reference = vec![0, 1, 2, 3, 0, 0];
+
+        let alignment = smith_waterman(&query, &reference, params).unwrap();
+        assert_eq!(alignment.query_clips(query.len()), (2, 1));
+        assert_eq!(alignment.reference_clips(reference.len()), (1, 2));
+
+        let empty = smith_waterman(&query, &[5], params).unwrap();
+        assert_eq!(empty.query_clips(query.len()), (0, 6));
+    }
+
+    #[test]
+    fn inclusive_ends_agree_with_half_open_spans() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3];
+        let seq2 = vec![0, 1, 2, 3, 0];
+
+        let (alignment, blocks) =
+            smith_waterman_match_blocks(&seq1, &seq2, params, BlockOptions::default()).unwrap();
+        assert_eq!((alignment.token_start, alignment.token_end), (1, 4));
+        assert_eq!(alignment.inclusive_ends(), Some((2, 3)));
+        assert_eq!(blocks, vec![(1, 4)]);
+        let (query_last, token_last) = alignment.inclusive_ends().unwrap();
+        assert_eq!(seq1[query_last], seq2[token_last]);
+        assert_eq!(&seq2[alignment.token_start..=token_last], &seq1[..]);
+
+        let empty = smith_waterman(&seq1, &[9], params).unwrap();
+        assert_eq!(empty.inclusive_ends(), None);
+    }
+
+    #[test]
+    fn smith_waterman_rejects_overflowing_scores() {
+        let params = ScoreParams::new(i32::MAX, -1, -1);
+        let result = smith_waterman(&[1, 1], &[1, 1], params);
+        assert_eq!(result.unwrap_err(), AlignError::ScoreOverflow);
+
+        let seqs = vec![vec![1, 1]];
+        assert!(align_topk(&[1, 1], &seqs, params, 1).is_err());
+    }
+
+    #[test]
+    fn score_params_validation() {
+        let valid = ScoreParams::new(2, -1, -1);
+        assert!(valid.validate().is_ok());
+        assert!(valid.degenerate_reason().is_none());
+
+        for match_score in [0, -2] {
+            let params = ScoreParams {
+                match_score,
+                ..valid
+            };
+            assert!(matches!(
+                params.validate(),
+                Err(AlignError::InvalidParams(_))
+            ));
+        }
+
+        let lenient = ScoreParams {
+            mismatch_score: 0,
+            ..valid
+        };
+        assert!(lenient.validate().is_ok());
+        assert!(lenient.degenerate_reason().is_some());
+    }
+
+    #[test]
+    fn presets_align_representative_inputs() {
+        let reference = vec![1, 2, 3, 4, 5, 6];
+        let one_substitution = vec![1, 2, 9, 4, 5, 6];
+        let span = |params: ScoreParams, query: &[u32]| {
+            let alignment = smith_waterman(query, &reference, params).unwrap();
+            (alignment.token_start, alignment.token_end)
+        };
+
+        assert_eq!(span(ScoreParams::citation(), &one_substitution), (0, 6));
+        assert_eq!(span(ScoreParams::lenient(), &[1, 7, 8, 4, 5, 6]), (0, 6));
+        assert_eq!(span(ScoreParams::strict(), &one_substitution), (3, 6));
+        assert_eq!(span(ScoreParams::dna(), &one_substitution), (0, 6));
+        assert_eq!(span(ScoreParams::dna(), &[1, 2, 3, 5]), (0, 3));
+
+        for name in ["citation", "dna", "strict", "lenient"] {
+            let params = ScoreParams::preset(name).unwrap();
+            assert!(params.validate().is_ok());
+            assert!(params.degenerate_reason().is_none());
+        }
+        assert!(ScoreParams::preset("unknown").is_none());
+    }
+
+    #[test]
+    fn wildcard_bridges_differing_token() {
+        let seq1 = vec![1, 99, 3];
+        let seq2 = vec![1, 2, 3];
+
+        let plain = smith_waterman(&seq1, &seq2, ScoreParams::new(2, -1, -1)).unwrap();
+        assert_eq!(plain.score, 3);
+        assert_eq!(plain.matches, 2);
+
+        let params = ScoreParams {
+            wildcard: Some(99),
+            ..ScoreParams::new(2, -1, -1)
+        };
+        let alignment = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!(alignment.score, 6);
+        assert_eq!(alignment.matches, 3);
+        assert_eq!((alignment.token_start, alignment.token_end), (0, 3));
+
+        let (_, blocks) =
+            smith_waterman_match_blocks(&seq1, &seq2, params, BlockOptions::default()).unwrap();
+        assert_eq!(blocks, vec![(0, 3)]);
+    }
+
+    #[test]
+    fn masked_region_is_excluded() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3];
+        let seq2 = vec![1, 2, 3, 9, 1, 2];
+
+        let unmasked = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!((unmasked.score, unmasked.token_start), (6, 0));
+
+        let constraints = Constraints {
+            mask: vec![(0, 3)],
+            ..Constraints::default()
+        };
+        let (alignment, blocks) = smith_waterman_match_blocks_with(
+            &seq1,
+            &seq2,
+            &params,
+            &constraints,
+            BlockOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(alignment.score, 4);
+        assert_eq!((alignment.token_start, alignment.token_end), (4, 6));
+        assert_eq!(blocks, vec![(4, 6)]);
+
+        let out_of_range = Constraints {
+            mask: vec![(2, 7)],
+            ..Constraints::default()
+        };
+        assert!(smith_waterman_with(&seq1, &seq2, &params, &out_of_range).is_err());
+    }
+
+    #[test]
+    fn aligned_tokens_place_gaps_in_query() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seq2 = vec![1, 2, 9, 9, 3, 4];
+
+        let (alignment, path) =
+            smith_waterman_path(&seq1, &seq2, &params, &Constraints::default()).unwrap();
+        let (query, reference) = aligned_tokens(&seq1, &seq2, &alignment, &path);
+        assert_eq!(query.len(), reference.len());
+        assert_eq!(query, vec![Some(1), Some(2), None, None, Some(3), Some(4)]);
+        assert_eq!(
+            reference,
+            vec![Some(1), Some(2), Some(9), Some(9), Some(3), Some(4)]
+        );
+    }
+
+    #[test]
+    fn matched_tokens_follow_diagonal_matches() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![5, 6, 7, 8, 9];
+        let seq2 = vec![1, 5, 6, 0, 8, 9, 2];
+
+        let (alignment, path) =
+            smith_waterman_path(&seq1, &seq2, &params, &Constraints::default()).unwrap();
+        let matched = matched_tokens(&seq1, &seq2, &alignment, &path, &params);
+        assert_eq!(matched, vec![5, 6, 8, 9]);
+        assert_eq!(matched.len(), alignment.matches);
+    }
+
+    #[test]
+    fn matched_span_skips_a_scoring_mismatch_at_the_start() {
+        let scoring = crate::scoring::NumericScoring {
+            params: ScoreParams::new(2, -1, -1),
+            tolerance: 2,
+        };
+        // 1999 against 2000 earns 1 without matching, so the alignment opens
+        // on it but the matches start one column later.
+        let seq1 = vec![1999, 5, 6, 0, 7];
+        let seq2 = vec![3, 2000, 5, 6, 8, 7];
+        let (alignment, path) =
+            smith_waterman_path(&seq1, &seq2, &scoring, &Constraints::default()).unwrap();
+        assert_eq!((alignment.token_start, alignment.token_end), (1, 6));
+        assert_eq!((alignment.query_start, alignment.query_end), (0, 5));
+        assert_eq!(
+            matched_span(&seq1, &seq2, &alignment, &path, &scoring),
+            Some(MatchedSpan {
+                token_start: 2,
+                token_end: 6,
+                query_start: 1,
+                query_end: 5,
+            })
+        );
+        let (empty, path) =
+            smith_waterman_path(&[1], &[2], &scoring, &Constraints::default()).unwrap();
+        assert_eq!(matched_span(&[1], &[2], &empty, &path, &scoring), None);
+    }
+
+    #[test]
+    fn exact_scores_equal_the_dp_with_run_bonus() {
+        let seq1: Vec<u32> = (0..300).collect();
+        let seq2: Vec<u32> = (500..520).chain(0..300).chain(600..610).collect();
+        for run_bonus in [1, 3] {
+            let params = ScoreParams {
+                run_bonus,
+                ..ScoreParams::new(2, -1, -1)
+            };
+            let exact = exact_alignment(&seq1, &seq2, &params).unwrap().unwrap();
+            let dp = smith_waterman(&seq1, &seq2, params).unwrap();
+            assert_eq!(
+                (exact.score, exact.token_start, exact.token_end),
+                (dp.score, dp.token_start, dp.token_end)
+            );
+        }
+    }
+
+    #[test]
+    fn exact_score_past_the_i32_pairs_boundary() {
+        // 46342 * 46341 exceeds i32::MAX, but the whole score fits and
+        // `check_score_range` accepts the inputs.
+        let seq1: Vec<u32> = (0..46_342).collect();
+        let params = ScoreParams {
+            run_bonus: 1,
+            ..ScoreParams::new(1, -1, -1)
+        };
+        let exact = exact_alignment(&seq1, &seq1, &params).unwrap().unwrap();
+        assert_eq!(i64::from(exact.score), 46_342 + 46_342 * 46_341 / 2_i64);
+        assert_eq!((exact.token_start, exact.token_end), (0, 46_342));
+
+        let oversized = ScoreParams {
+            run_bonus: 2,
+            ..params
+        };
+        assert!(matches!(
+            exact_alignment(&seq1, &seq1, &oversized),
+            Err(AlignError::ScoreOverflow)
+        ));
+    }
+
+    #[test]
+    fn exact_occurrence_skips_the_dp() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![4, 5, 6, 7];
+        // Two verbatim copies, after a near miss.
+        let seq2 = vec![4, 5, 9, 7, 1, 4, 5, 6, 7, 2, 4, 5, 6, 7];
+
+        let exact = exact_alignment(&seq1, &seq2, &params).unwrap().unwrap();
+        let dp = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!((exact.token_start, exact.token_end), (5, 9));
+        assert_eq!((exact.query_start, exact.query_end), (0, 4));
+        assert_eq!(
+            (
+                exact.score,
+                exact.token_start,
+                exact.token_end,
+                exact.matches
+            ),
+            (dp.score, dp.token_start, dp.token_end, dp.matches)
+        );
+
+        let bonus = ScoreParams {
+            run_bonus: 1,
+            ..params
+        };
+        let exact = exact_alignment(&seq1, &seq2, &bonus).unwrap().unwrap();
+        assert_eq!(
+            exact.score,
+            smith_waterman(&seq1, &seq2, bonus).unwrap().score
+        );
+
+        let best = align_best(&seq1, &[vec![1, 2], seq2.clone()], params)
+            .unwrap()
+            .unwrap();
+        assert_eq!((best.index, best.score, best.token_start), (1, 8, 5));
+
+        // No verbatim copy, or a wildcard that could tie earlier: use the DP.
+        assert!(
+            exact_alignment(&seq1, &seq2[..9], &params)
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            exact_alignment(&seq1, &seq2[..8], &params)
+                .unwrap()
+                .is_none()
+        );
+        let wildcard = ScoreParams {
+            wildcard: Some(0),
+            ..params
+        };
+        assert!(exact_alignment(&seq1, &seq2, &wildcard).unwrap().is_none());
     }
 
-    if match_positions.is_empty() {
-        return (i, j, matches, Vec::new());
+    #[test]
+    fn asymmetric_gaps_change_the_winner() {
+        let query = vec![1, 2, 3, 4, 5, 6];
+        // Index 0 needs a reference-side gap (left move) over the extra 9,
+        // index 1 a query-side gap (up move) over the missing 4.
+        let seqs = vec![vec![1, 2, 3, 9, 4, 5], vec![1, 2, 3, 5, 6, 7]];
+
+        let symmetric = align_topk(&query, &seqs, ScoreParams::new(2, -1, -1), 2).unwrap();
+        assert_eq!((symmetric[0].score, symmetric[1].score), (9, 9));
+        assert_eq!(symmetric[0].index, 0);
+
+        let costly_ref_gaps = ScoreParams {
+            gap_ref: -3,
+            ..ScoreParams::new(2, -1, -1)
+        };
+        let top = align_topk(&query, &seqs, costly_ref_gaps, 2).unwrap();
+        assert_eq!((top[0].index, top[0].score), (1, 9));
+        assert_eq!((top[1].index, top[1].score), (0, 7));
+
+        let costly_query_gaps = ScoreParams {
+            gap_query: -3,
+            ..ScoreParams::new(2, -1, -1)
+        };
+        let top = align_topk(&query, &seqs, costly_query_gaps, 2).unwrap();
+        assert_eq!((top[0].index, top[0].score), (0, 9));
+        assert_eq!((top[1].index, top[1].score), (1, 7));
     }
 
-    match_positions.reverse();
-    let mut blocks: Vec<(usize, usize)> = Vec::new();
-    let mut start = match_positions[0];
-    let mut prev = start;
-    for pos in match_positions.into_iter().skip(1) {
-        if pos == prev + 1 {
-            prev = pos;
-            continue;
+    #[test]
+    fn score_matrix_matches_hand_computed_values() {
+        let params = ScoreParams::new(2, -1, -1);
+        let (alignment, dense) =
+            smith_waterman_matrix(&[1, 2], &[1, 2, 1, 2], &params, &Constraints::default())
+                .unwrap();
+        assert_eq!((dense.rows, dense.cols), (3, 5));
+        #[rustfmt::skip]
+        assert_eq!(dense.scores, vec![
+            0, 0, 0, 0, 0,
+            0, 2, 1, 2, 1,
+            0, 1, 4, 3, 4,
+        ]);
+        assert_eq!(alignment.score, 4);
+        assert_eq!((alignment.token_start, alignment.token_end), (0, 2));
+    }
+
+    #[test]
+    fn affine_gaps_charge_the_opening_once_per_gap() {
+        let query: Vec<u32> = (1..=8).collect();
+        let one_gap = vec![1, 2, 3, 4, 9, 9, 5, 6, 7, 8];
+        let two_gaps = vec![1, 2, 3, 9, 4, 5, 6, 9, 7, 8];
+        let linear = ScoreParams::new(2, -1, -1);
+        let affine = ScoreParams::affine(2, -1, -3, -1);
+        assert!(affine.validate().is_ok());
+
+        for (reference, linear_score, affine_score) in [(&one_gap, 14, 12), (&two_gaps, 14, 10)] {
+            assert_eq!(
+                smith_waterman(&query, reference, linear).unwrap().score,
+                linear_score
+            );
+            let alignment = smith_waterman(&query, reference, affine).unwrap();
+            assert_eq!(alignment.score, affine_score);
+            assert_eq!((alignment.query_end, alignment.token_end), (8, 10));
+            assert_eq!(
+                local_score(&query, reference, &affine).unwrap(),
+                affine_score
+            );
+
+            let (_, path) =
+                smith_waterman_path(&query, reference, &affine, &Constraints::default()).unwrap();
+            let lefts = path.iter().filter(|step| **step == Step::Left).count();
+            assert_eq!((path.len(), lefts), (10, 2));
         }
-        blocks.push((start, prev + 1));
-        start = pos;
-        prev = pos;
+
+        // Opening costs equal to the extension cost are plain linear gaps.
+        assert!(!ScoreParams::affine(2, -1, -1, -1).is_affine());
+        let positive = ScoreParams {
+            gap_open: 1,
+            ..linear
+        };
+        assert!(positive.validate().is_err());
     }
-    blocks.push((start, prev + 1));
 
-    (i, j, matches, blocks)
-}
+    #[test]
+    fn match_blocks_follow_the_requested_frame() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Query token 9 is a gap in the reference, reference token 8 a gap in
+        // the query; each splits a block only in its own sequence's frame.
+        let seq1 = vec![1, 2, 9, 3, 4, 5, 6];
+        let seq2 = vec![1, 2, 3, 4, 8, 5, 6];
 
-fn cmp_alignment(left: &Alignment, right: &Alignment) -> Ordering {
-    if left.score != right.score {
-        return right.score.cmp(&left.score);
+        let (alignment, reference) =
+            smith_waterman_match_blocks(&seq1, &seq2, params, BlockOptions::default()).unwrap();
+        let (same, query) = smith_waterman_match_blocks(
+            &seq1,
+            &seq2,
+            params,
+            BlockOptions {
+                frame: BlockFrame::Query,
+                ..BlockOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            (alignment.query_end, alignment.token_end),
+            (same.query_end, same.token_end)
+        );
+        assert_eq!((alignment.score, alignment.matches), (10, 6));
+        assert_eq!(reference, vec![(0, 4), (5, 7)]);
+        assert_eq!(query, vec![(0, 2), (3, 7)]);
+        for blocks in [&reference, &query] {
+            let covered: usize = blocks.iter().map(|(start, end)| end - start).sum();
+            assert_eq!(covered, alignment.matches);
+        }
     }
-    if left.token_start != right.token_start {
-        return left.token_start.cmp(&right.token_start);
+
+    #[test]
+    fn query_coverage_rejects_partial_overlaps() {
+        let params = ScoreParams::new(2, -1, -1);
+        let query: Vec<u32> = (1..=10).collect();
+        // An exact run of 8 query tokens outscores 9 matches broken by three
+        // inserted tokens.
+        let partial = vec![40, 1, 2, 3, 4, 5, 6, 7, 8, 41];
+        let near_full = vec![1, 2, 77, 3, 4, 77, 5, 6, 77, 7, 8, 9, 99];
+        let seqs = vec![partial, near_full, vec![50, 51]];
+
+        let top = align_topk(&query, &seqs, params, 2).unwrap();
+        assert_eq!((top[0].index, top[0].score, top[0].matches), (0, 16, 8));
+        assert_eq!((top[1].index, top[1].score), (1, 15));
+
+        let covering = align_topk_covering(&query, &seqs, &params, 3, 0.9).unwrap();
+        assert_eq!(covering.len(), 1);
+        assert_eq!((covering[0].index, covering[0].matches), (1, 9));
+        assert!(
+            align_topk_covering(&query, &seqs[..1], &params, 3, 0.9)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            align_topk_covering(&query, &seqs, &params, 3, 0.0).unwrap(),
+            align_topk(&query, &seqs, params, 3).unwrap()
+        );
+        assert!(align_topk_covering(&query, &seqs, &params, 1, 1.5).is_err());
     }
 
-    let left_span = left.token_end - left.token_start;
-    let right_span = right.token_end - right.token_start;
-    if left_span != right_span {
-        return right_span.cmp(&left_span);
+    #[test]
+    fn is_match_separates_no_overlap_from_a_match_at_zero() {
+        let params = ScoreParams::new(2, -1, -1);
+        let none = smith_waterman(&[1, 2, 3], &[7, 8, 9], params).unwrap();
+        let at_start = smith_waterman(&[1, 2, 3], &[1, 2, 9], params).unwrap();
+        assert_eq!((none.token_start, at_start.token_start), (0, 0));
+        assert!(!none.is_match(0));
+        assert!(at_start.is_match(0));
+        assert!(!at_start.is_match(at_start.score));
     }
 
-    if left.query_start != right.query_start {
-        return left.query_start.cmp(&right.query_start);
+    #[test]
+    fn diagonal_is_the_start_offset_of_a_shifted_match() {
+        let params = ScoreParams::new(2, -1, -1);
+        let shifted = smith_waterman(&[7, 1, 2, 3], &[9, 9, 9, 9, 1, 2, 3], params).unwrap();
+        assert_eq!((shifted.query_start, shifted.token_start), (1, 4));
+        assert_eq!(shifted.diagonal(), 3);
+
+        let behind = smith_waterman(&[7, 7, 7, 1, 2], &[1, 2, 9], params).unwrap();
+        assert_eq!(behind.diagonal(), -3);
+        assert_eq!(smith_waterman(&[1], &[2], params).unwrap().diagonal(), 0);
     }
-    if left.token_end != right.token_end {
-        return left.token_end.cmp(&right.token_end);
+
+    #[test]
+    fn best_with_blocks_traces_only_the_winner() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4, 5];
+        let seqs = vec![vec![1, 2, 9], vec![7, 1, 2, 9, 9, 3, 4, 5], vec![3, 4]];
+
+        let (best, blocks) = align_best_with_blocks(&seq1, &seqs, &params)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(best), align_best(&seq1, &seqs, params).unwrap());
+        assert_eq!(best.index, 1);
+        let (_, expected) =
+            smith_waterman_match_blocks(&seq1, &seqs[1], params, BlockOptions::default()).unwrap();
+        assert_eq!(blocks, expected);
+        assert_eq!(blocks, vec![(1, 3), (5, 8)]);
+        assert!(
+            align_best_with_blocks(&seq1, &[], &params)
+                .unwrap()
+                .is_none()
+        );
     }
-    left.query_end.cmp(&right.query_end)
-}
 
-fn cmp_candidate(left: &CandidateAlignment, right: &CandidateAlignment) -> Ordering {
-    if left.score != right.score {
-        return right.score.cmp(&left.score);
+    #[test]
+    fn end_score_equals_the_local_score() {
+        let mut with_bonus = ScoreParams::new(2, -1, -1);
+        with_bonus.run_bonus = 1;
+        let cases: [(&[u32], &[u32], ScoreParams); 4] = [
+            // Tied maxima at two end cells.
+            (&[1, 2], &[1, 2, 1, 2], ScoreParams::new(2, -1, -1)),
+            (&[1, 2, 3, 4], &[1, 2, 9, 3, 4], with_bonus),
+            (
+                &[1, 2, 3, 4],
+                &[1, 2, 9, 9, 3, 4],
+                ScoreParams::affine(2, -1, -2, -1),
+            ),
+            (&[1, 2], &[3, 4], ScoreParams::new(2, -1, -1)),
+        ];
+        for (seq1, seq2, params) in cases {
+            let (alignment, end_score) =
+                smith_waterman_end_score(seq1, seq2, &params, &Constraints::default()).unwrap();
+            let full = smith_waterman(seq1, seq2, params).unwrap();
+            assert_eq!(
+                (alignment.score, alignment.query_end, alignment.token_end),
+                (full.score, full.query_end, full.token_end)
+            );
+            assert_eq!(end_score, alignment.score);
+        }
     }
-    if left.token_start != right.token_start {
-        return left.token_start.cmp(&right.token_start);
+
+    #[test]
+    fn gap_budget_prefers_a_tighter_reference() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=8).collect();
+        // Every query token, but with three single-token insertions.
+        let gappy = vec![1, 2, 90, 3, 4, 91, 5, 6, 92, 7, 8];
+        // Six of the eight tokens, contiguous.
+        let tight = vec![3, 4, 5, 6, 7, 8];
+        let seqs = vec![gappy, tight];
+
+        let unlimited = align_topk_within(&seq1, &seqs, &params, 2, 0.0, None, 0).unwrap();
+        assert_eq!(
+            unlimited
+                .iter()
+                .map(|c| (c.index, c.score))
+                .collect::<Vec<_>>(),
+            vec![(0, 13), (1, 12)]
+        );
+        let limited = align_topk_within(&seq1, &seqs, &params, 2, 0.0, Some(2), 0).unwrap();
+        assert_eq!(
+            limited
+                .iter()
+                .map(|c| (c.index, c.score))
+                .collect::<Vec<_>>(),
+            vec![(1, 12)]
+        );
+        let roomy = align_topk_within(&seq1, &seqs, &params, 2, 0.0, Some(3), 0).unwrap();
+        assert_eq!(roomy, unlimited);
+        assert!(align_topk_within(&seq1, &seqs, &params, 2, 1.5, Some(3), 0).is_err());
     }
 
-    let left_span = left.token_end - left.token_start;
-    let right_span = right.token_end - right.token_start;
-    if left_span != right_span {
-        return right_span.cmp(&left_span);
+    #[test]
+    fn column_maxima_track_the_best_cell_per_reference_token() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3];
+        let seq2 = vec![9, 1, 2, 3, 1];
+        let maxima = column_maxima(&seq1, &seq2, &params).unwrap();
+        // The run 1, 2, 3 peaks at 6; the trailing 1 is reached by a gap.
+        assert_eq!(maxima, vec![0, 2, 4, 6, 5]);
+        assert_eq!(
+            maxima.iter().max().copied(),
+            Some(local_score(&seq1, &seq2, &params).unwrap())
+        );
+        assert_eq!(column_maxima(&[], &seq2, &params).unwrap(), vec![0; 5]);
     }
 
-    if left.query_start != right.query_start {
-        return left.query_start.cmp(&right.query_start);
+    #[test]
+    fn ref_weights_lift_an_authoritative_reference() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=6).collect();
+        // Raw scores 12, 10 and 8.
+        let seqs = vec![
+            vec![1, 2, 3, 4, 5, 6],
+            vec![1, 2, 3, 4, 5],
+            vec![1, 2, 3, 4],
+        ];
+        let all = align_topk(&seq1, &seqs, params, seqs.len()).unwrap();
+
+        let weights = [1.0, 1.5, 1.0];
+        check_ref_weights(&weights, seqs.len()).unwrap();
+        let top = rank_weighted(all.clone(), &weights, 2);
+        // 10 * 1.5 = 15 outranks 12, and the raw scores are reported.
+        assert_eq!(
+            top.iter().map(|c| (c.index, c.score)).collect::<Vec<_>>(),
+            vec![(1, 10), (0, 12)]
+        );
+        let even = rank_weighted(all.clone(), &[1.0; 3], 3);
+        assert_eq!(even, all);
+
+        assert!(check_ref_weights(&[1.0, 1.0], seqs.len()).is_err());
+        assert!(check_ref_weights(&[1.0, -1.0, 1.0], seqs.len()).is_err());
+        assert!(check_ref_weights(&[1.0, f64::NAN, 1.0], seqs.len()).is_err());
     }
-    if left.index != right.index {
-        return left.index.cmp(&right.index);
+
+    #[test]
+    fn above_threshold_keeps_every_qualifying_reference() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=6).collect();
+        // Scores 4, 12, 6, 8, 0 and 6 against a threshold of 6.
+        let seqs = vec![
+            vec![1, 2],
+            vec![1, 2, 3, 4, 5, 6],
+            vec![4, 5, 6],
+            vec![9, 1, 2, 3, 4],
+            vec![7, 8],
+            vec![1, 2, 3],
+        ];
+        let above = align_above_threshold(&seq1, &seqs, &params, 6).unwrap();
+        assert_eq!(
+            above.iter().map(|c| (c.index, c.score)).collect::<Vec<_>>(),
+            vec![(1, 12), (3, 8), (5, 6), (2, 6)]
+        );
+        assert_eq!(
+            align_above_threshold(&seq1, &seqs, &params, 0)
+                .unwrap()
+                .len(),
+            6
+        );
+        assert!(
+            align_above_threshold(&seq1, &seqs, &params, 13)
+                .unwrap()
+                .is_empty()
+        );
     }
-    if left.token_end != right.token_end {
-        return left.token_end.cmp(&right.token_end);
+
+    #[test]
+    fn percentile_cutoff_keeps_the_top_fraction() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=20).collect();
+        // Reference `k` is the query's first `k + 1` tokens: scores 2 to 40.
+        let seqs: Vec<Vec<u32>> = (1..=20).map(|len| (1..=len).collect()).collect();
+
+        let (cutoff, above) = align_above_percentile(&seq1, &seqs, &params, 90.0).unwrap();
+        assert_eq!(cutoff, 36);
+        assert_eq!(
+            above.iter().map(|c| (c.index, c.score)).collect::<Vec<_>>(),
+            vec![(19, 40), (18, 38)]
+        );
+        assert_eq!(above[0].matches, 20);
+
+        let (cutoff, above) = align_above_percentile(&seq1, &seqs, &params, 50.0).unwrap();
+        assert_eq!((cutoff, above.len()), (20, 10));
+        let (_, above) = align_above_percentile(&seq1, &seqs, &params, 100.0).unwrap();
+        assert!(above.is_empty());
+        assert_eq!(
+            align_above_percentile(&seq1, &[], &params, 90.0).unwrap(),
+            (0, Vec::new())
+        );
+        assert!(align_above_percentile(&seq1, &seqs, &params, 101.0).is_err());
     }
-    left.query_end.cmp(&right.query_end)
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn first_above_stops_after_a_qualifying_reference() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=6).collect();
+        // Only references 2 and 500 reach 8; the rest score 2.
+        let mut seqs = vec![vec![1, 9]; 1_000];
+        seqs[2] = vec![1, 2, 3, 4];
+        seqs[500] = seq1.clone();
+
+        let (first, scored) = crate::parallel::with_thread_pool(Some(1), || {
+            align_first_above(&seq1, &seqs, &params, 8, true)
+        })
+        .unwrap();
+        let first = first.unwrap();
+        assert_eq!((first.index, first.score, first.matches), (2, 8, 4));
+        assert!(scored < seqs.len());
+
+        let (any, _) = align_first_above(&seq1, &seqs, &params, 8, false).unwrap();
+        assert!([2, 500].contains(&any.unwrap().index));
+        let (none, scored) = align_first_above(&seq1, &seqs, &params, 13, true).unwrap();
+        assert_eq!((none, scored), (None, seqs.len()));
+    }
 
     #[test]
-    fn smith_waterman_prefers_earlier_start() {
-        let params = ScoreParams {
-            match_score: 2,
-            mismatch_score: -1,
-            gap_score: -1,
+    fn observer_sees_every_row_once() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 9, 3];
+        let seq2 = vec![7, 1, 2, 3];
+        let mut rows = Vec::new();
+        let alignment =
+            smith_waterman_observed(&seq1, &seq2, &params, &Constraints::default(), |progress| {
+                rows.push(*progress)
+            })
+            .unwrap();
+        let plain = smith_waterman(&seq1, &seq2, params).unwrap();
+        assert_eq!(
+            (alignment.score, alignment.token_start, alignment.token_end),
+            (plain.score, plain.token_start, plain.token_end)
+        );
+        assert_eq!(rows.len(), seq1.len());
+        assert_eq!(
+            rows.iter()
+                .map(|p| (p.row, p.best_score, p.best_column, p.max_score))
+                .collect::<Vec<_>>(),
+            vec![(1, 2, 2, 2), (2, 4, 3, 4), (3, 3, 3, 4), (4, 5, 4, 5)]
+        );
+        assert!(rows.iter().all(|progress| progress.rows == 4));
+
+        let mut calls = 0;
+        smith_waterman_observed(&[], &seq2, &params, &Constraints::default(), |_| calls += 1)
+            .unwrap();
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn sort_key_reorders_without_changing_the_selection() {
+        let params = ScoreParams::new(3, -1, -1);
+        let seq1: Vec<u32> = (1..=8).collect();
+        // Both score 12: four contiguous matches, and five matches with three
+        // mismatches starting one token later.
+        let seqs = vec![
+            vec![1, 2],
+            vec![1, 2, 3, 4],
+            vec![50, 1, 90, 3, 91, 5, 92, 7, 8],
+        ];
+        let mut top = align_topk(&seq1, &seqs, params, 3).unwrap();
+        let order = |top: &[CandidateAlignment]| {
+            top.iter()
+                .map(|c| (c.index, c.score, c.matches))
+                .collect::<Vec<_>>()
         };
+        assert_eq!(order(&top), vec![(1, 12, 4), (2, 12, 5), (0, 6, 2)]);
+
+        sort_candidates(&mut top, SortKey::Matches);
+        assert_eq!(order(&top), vec![(2, 12, 5), (1, 12, 4), (0, 6, 2)]);
+        sort_candidates(&mut top, SortKey::TokenStart);
+        assert_eq!(order(&top), vec![(1, 12, 4), (0, 6, 2), (2, 12, 5)]);
+        sort_candidates(&mut top, SortKey::Score);
+        assert_eq!(order(&top), vec![(1, 12, 4), (2, 12, 5), (0, 6, 2)]);
+    }
+
+    #[test]
+    fn min_columns_rejects_a_short_exact_match() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=6).collect();
+        // A perfect two-column hit, and five columns with one substitution.
+        let short = vec![1, 2];
+        let long = vec![2, 3, 99, 5, 6];
+        let seqs = vec![short, long];
+
+        let unlimited = align_topk_within(&seq1, &seqs, &params, 2, 0.0, None, 0).unwrap();
+        assert_eq!(
+            unlimited
+                .iter()
+                .map(|c| (c.index, c.score))
+                .collect::<Vec<_>>(),
+            vec![(1, 7), (0, 4)]
+        );
+        let limited = align_topk_within(&seq1, &seqs, &params, 2, 0.0, None, 3).unwrap();
+        assert_eq!(
+            limited
+                .iter()
+                .map(|c| (c.index, c.score, c.token_end - c.token_start))
+                .collect::<Vec<_>>(),
+            vec![(1, 7, 5)]
+        );
+        assert!(
+            align_topk_within(&seq1, &seqs, &params, 2, 0.0, None, 6)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn min_anchor_trims_weak_end_blocks() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Lone matches at both ends, each cut off by a mismatch.
+        let seq1 = vec![1, 7, 2, 3, 4, 5, 8, 6];
+        let seq2 = vec![1, 70, 2, 3, 4, 5, 80, 6];
+
+        let (full, blocks) =
+            smith_waterman_match_blocks(&seq1, &seq2, params, BlockOptions::default()).unwrap();
+        assert_eq!(blocks, vec![(0, 1), (2, 6), (7, 8)]);
+        assert_eq!((full.score, full.token_start, full.token_end), (10, 0, 8));
+
+        for frame in [BlockFrame::Reference, BlockFrame::Query] {
+            let (trimmed, blocks) = smith_waterman_match_blocks(
+                &seq1,
+                &seq2,
+                params,
+                BlockOptions {
+                    frame,
+                    min_anchor: 2,
+                    ..BlockOptions::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(blocks, vec![(2, 6)]);
+            assert_eq!(
+                (
+                    trimmed.score,
+                    trimmed.query_start,
+                    trimmed.query_end,
+                    trimmed.token_start,
+                    trimmed.token_end,
+                    trimmed.matches
+                ),
+                (10, 2, 6, 2, 6, 4)
+            );
+        }
+
+        let (none, blocks) = smith_waterman_match_blocks(
+            &seq1,
+            &seq2,
+            params,
+            BlockOptions {
+                min_anchor: 5,
+                ..BlockOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!((none.score, blocks.len()), (0, 0));
+    }
+
+    #[test]
+    fn random_tie_break_is_reproducible_per_seed() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Eight equally good placements of the query.
         let seq1 = vec![1, 2];
-        let seq2 = vec![1, 2, 1, 2];
-        let alignment = smith_waterman(&seq1, &seq2, params);
-        assert_eq!(alignment.score, 4);
-        assert_eq!(alignment.token_start, 0);
-        assert_eq!(alignment.token_end, 2);
-        assert_eq!(alignment.matches, 2);
-        assert_eq!(alignment.query_start, 0);
-        assert_eq!(alignment.query_end, 2);
+        let seq2 = [1, 2].repeat(8);
+        let draw = |seed| {
+            let alignment = smith_waterman_tie_break(
+                &seq1,
+                &seq2,
+                &params,
+                &Constraints::default(),
+                TieBreak::Random(seed),
+            )
+            .unwrap();
+            assert_eq!(alignment.score, 4);
+            alignment.token_start
+        };
+
+        for seed in 0..8 {
+            assert_eq!(draw(seed), draw(seed));
+        }
+        let starts: std::collections::HashSet<usize> = (0..32).map(draw).collect();
+        assert!(starts.len() > 1);
+
+        let canonical = smith_waterman_tie_break(
+            &seq1,
+            &seq2,
+            &params,
+            &Constraints::default(),
+            TieBreak::Canonical,
+        )
+        .unwrap();
+        assert_eq!(canonical.token_start, 0);
     }
 
     #[test]
-    fn smith_waterman_match_blocks_returns_disjoint_blocks() {
-        let params = ScoreParams {
-            match_score: 2,
-            mismatch_score: -1,
-            gap_score: -1,
+    fn merged_windows_cover_both_spans() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq2: Vec<u32> = (0..20).collect();
+        let window = |start: u32, end: u32| {
+            let query: Vec<u32> = (start..end).collect();
+            let mut alignment = smith_waterman(&query, &seq2, params).unwrap();
+            // Back into whole-query coordinates, as a chunked caller would.
+            alignment.query_start += (start - 4) as usize;
+            alignment.query_end += (start - 4) as usize;
+            alignment
         };
-        let seq1 = vec![1, 2, 3, 4];
-        let seq2 = vec![1, 2, 9, 9, 3, 4];
+        // Query tokens 4..10 and 10..16, then 4..12 and 8..16.
+        let (left, right) = (window(4, 10), window(10, 16));
+        let merged = merge_alignments(&left, &right).unwrap();
+        assert_eq!(
+            (
+                merged.score,
+                merged.query_start,
+                merged.query_end,
+                merged.token_start,
+                merged.token_end,
+                merged.matches
+            ),
+            (24, 0, 12, 4, 16, 12)
+        );
 
-        let (alignment, match_blocks) = smith_waterman_match_blocks(&seq1, &seq2, params);
-        assert_eq!(alignment.score, 6);
-        assert_eq!(alignment.token_start, 0);
-        assert_eq!(alignment.token_end, 6);
-        assert_eq!(alignment.query_start, 0);
-        assert_eq!(alignment.query_end, 4);
-        assert_eq!(alignment.matches, 4);
-        assert_eq!(match_blocks, vec![(0, 2), (4, 6)]);
+        let overlapping = merge_alignments(&window(4, 12), &window(8, 16)).unwrap();
+        assert_eq!(
+            (
+                overlapping.query_start,
+                overlapping.query_end,
+                overlapping.token_start,
+                overlapping.token_end,
+                overlapping.matches
+            ),
+            (0, 12, 4, 16, 12)
+        );
+
+        let far = window(14, 16);
+        assert!(merge_alignments(&left, &far).is_err());
     }
 
     #[test]
-    fn align_topk_is_deterministic_and_sorted() {
-        let params = ScoreParams {
-            match_score: 2,
-            mismatch_score: -1,
-            gap_score: -1,
+    fn timed_topk_ranks_like_topk_and_times_every_reference() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (0..200).map(|i| i % 7).collect();
+        let seqs: Vec<Vec<u32>> = vec![
+            (0..20).map(|i| i % 5).collect(),
+            (0..5_000).map(|i| i % 11).collect(),
+            seq1.clone(),
+        ];
+
+        let (top, durations) = align_topk_timed(&seq1, &seqs, &params, 2).unwrap();
+        assert_eq!(top, align_topk(&seq1, &seqs, params, 2).unwrap());
+        assert_eq!(durations.len(), seqs.len());
+        // 250 times the cells on the long reference.
+        assert!(durations[1] > durations[0]);
+    }
+
+    #[test]
+    fn longer_variant_wins_when_it_matches_more() {
+        let params = ScoreParams::new(2, -1, -1);
+        let reference = vec![9, 1, 2, 3, 4, 5, 9];
+        let variants = vec![vec![1, 2, 3], vec![1, 2, 3, 4, 5], vec![1, 2, 3, 7, 8]];
+
+        let (best, index) = align_variants_best(&variants, &reference, &params)
+            .unwrap()
+            .unwrap();
+        assert_eq!(index, 1);
+        assert_eq!((best.score, best.token_start, best.token_end), (10, 1, 6));
+
+        // Equal-scoring variants resolve to the first.
+        let tied = vec![vec![1, 2, 3], vec![1, 2, 3]];
+        let (_, index) = align_variants_best(&tied, &reference, &params)
+            .unwrap()
+            .unwrap();
+        assert_eq!(index, 0);
+        assert!(
+            align_variants_best(&[], &reference, &params)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn max_blocks_merges_the_closest_blocks_first() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=12).collect();
+        let seq2 = vec![1, 2, 50, 4, 51, 52, 7, 8, 9, 53, 11, 12];
+        let capped = |max_blocks| {
+            let options = BlockOptions {
+                max_blocks,
+                ..BlockOptions::default()
+            };
+            smith_waterman_match_blocks(&seq1, &seq2, params, options)
+                .unwrap()
+                .1
         };
-        let seq1 = vec![1, 2];
-        let seqs = vec![vec![3, 4], vec![1, 2, 1, 2], vec![1, 2], vec![0, 1, 2, 3]];
-        let top = align_topk(&seq1, &seqs, params, 3);
-        assert_eq!(top.len(), 3);
-        assert_eq!(top[0].index, 1);
-        assert_eq!(top[1].index, 2);
-        assert_eq!(top[2].index, 3);
+        assert_eq!(capped(None), vec![(0, 2), (3, 4), (6, 9), (10, 12)]);
+        // Gaps of 1, 2 and 1: the leftmost 1 goes first, then the other.
+        assert_eq!(capped(Some(3)), vec![(0, 4), (6, 9), (10, 12)]);
+        assert_eq!(capped(Some(2)), vec![(0, 4), (6, 12)]);
+        assert_eq!(capped(Some(1)), vec![(0, 12)]);
+        let options = BlockOptions {
+            max_blocks: Some(0),
+            ..BlockOptions::default()
+        };
+        assert!(smith_waterman_match_blocks(&seq1, &seq2, params, options).is_err());
+
+        // Every third token differs, leaving a block per pair of matches.
+        let seq1: Vec<u32> = (0..60).collect();
+        let seq2: Vec<u32> = (0..60)
+            .map(|i| if i % 3 == 2 { 100 + i } else { i })
+            .collect();
+        let (_, all) =
+            smith_waterman_match_blocks(&seq1, &seq2, params, BlockOptions::default()).unwrap();
+        assert_eq!(all.len(), 20);
+        let options = BlockOptions {
+            max_blocks: Some(5),
+            ..BlockOptions::default()
+        };
+        let (_, few) = smith_waterman_match_blocks(&seq1, &seq2, params, options).unwrap();
+        assert_eq!(few.len(), 5);
+        assert!(all.iter().all(|&(start, end)| {
+            few.iter()
+                .any(|&(merged_start, merged_end)| merged_start <= start && end <= merged_end)
+        }));
+    }
+
+    #[test]
+    fn score_buffer_is_reused_across_calls() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seqs = vec![vec![1, 2, 3, 4], vec![5, 6, 7], vec![], vec![3, 4, 5, 6]];
+        let mut out = vec![-7; seqs.len()];
+        for query in [vec![1, 2, 3], vec![4, 5, 6, 7]] {
+            align_all_scores_into(&query, &seqs, &params, &mut out).unwrap();
+            let expected: Vec<i32> = seqs
+                .iter()
+                .map(|seq2| local_score(&query, seq2, &params).unwrap())
+                .collect();
+            assert_eq!(out, expected);
+        }
+        assert_eq!(out, vec![2, 6, 0, 6]);
+        assert!(align_all_scores_into(&[1], &seqs, &params, &mut out[..3]).is_err());
+    }
+
+    #[test]
+    fn separator_confines_matches_to_one_side() {
+        let plain = ScoreParams::new(2, -1, -1);
+        let separated = ScoreParams {
+            separator: Some(99),
+            ..plain
+        };
+        let seq1: Vec<u32> = (1..=7).collect();
+        let seq2 = vec![1, 2, 3, 99, 4, 5, 6, 7];
+        let spanning = smith_waterman(&seq1, &seq2, plain).unwrap();
+        assert_eq!(
+            (spanning.score, spanning.token_start, spanning.token_end),
+            (13, 0, 8)
+        );
+
+        let confined = smith_waterman(&seq1, &seq2, separated).unwrap();
+        assert_eq!(
+            (confined.score, confined.token_start, confined.token_end),
+            (8, 4, 8)
+        );
+        assert_eq!((confined.query_start, confined.query_end), (3, 7));
+        assert_eq!(local_score(&seq1, &seq2, &separated).unwrap(), 8);
+
+        // A separator in the query breaks it the same way.
+        let confined = smith_waterman(&seq2, &seq1, separated).unwrap();
+        assert_eq!(
+            (confined.score, confined.query_start, confined.query_end),
+            (8, 4, 8)
+        );
+        let affine = ScoreParams {
+            separator: Some(99),
+            ..ScoreParams::affine(2, -1, -2, -1)
+        };
+        assert_eq!(
+            local_score(&seq2, &seq1, &affine).unwrap(),
+            smith_waterman(&seq2, &seq1, affine).unwrap().score
+        );
+    }
+
+    #[test]
+    fn bucket_representatives_are_the_top_score_in_range() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=6).collect();
+        // Scores 2, 8, 4, 12, 6 and 0 (an empty reference).
+        let seqs = vec![
+            vec![1],
+            vec![1, 2, 3, 4],
+            vec![9, 5, 6],
+            seq1.clone(),
+            vec![2, 3, 4, 9],
+            vec![],
+        ];
+        let best = align_best_per_bucket(&seq1, &seqs, &[1, 5, 9, 12, 20], &params).unwrap();
+        let picked: Vec<Option<(usize, i32)>> = best
+            .iter()
+            .map(|item| item.map(|item| (item.index, item.score)))
+            .collect();
+        assert_eq!(
+            picked,
+            vec![Some((2, 4)), Some((1, 8)), None, Some((3, 12))]
+        );
+        let second = best[1].unwrap();
+        let alignment = smith_waterman(&seq1, &seqs[1], params).unwrap();
+        assert_eq!(
+            (second.token_start, second.token_end, second.matches),
+            (
+                alignment.token_start,
+                alignment.token_end,
+                alignment.matches
+            )
+        );
+
+        assert!(align_best_per_bucket(&seq1, &seqs, &[5], &params).is_err());
+        assert!(align_best_per_bucket(&seq1, &seqs, &[5, 5], &params).is_err());
+    }
+
+    #[test]
+    fn reversed_alignment_maps_back_to_forward_coordinates() {
+        let params = ScoreParams::new(2, -1, -1);
+        let forward_and_reversed = |seq1: &[u32], seq2: &[u32], constraints: &Constraints| {
+            let forward = smith_waterman_with(seq1, seq2, &params, constraints).unwrap();
+            let reversed = align_reversed(seq1, seq2, constraints, |seq1, seq2, constraints| {
+                smith_waterman_with(seq1, seq2, &params, constraints)
+            })
+            .unwrap();
+            (forward, reversed)
+        };
+        let span = |alignment: &Alignment| {
+            (
+                alignment.score,
+                alignment.query_start,
+                alignment.query_end,
+                alignment.token_start,
+                alignment.token_end,
+                alignment.matches,
+            )
+        };
+
+        let seq1 = vec![1, 2, 3, 4, 5, 6];
+        let seq2 = vec![8, 1, 2, 3, 9, 4, 5, 6, 7, 7];
+        let (forward, reversed) = forward_and_reversed(&seq1, &seq2, &Constraints::default());
+        assert_eq!(span(&forward), (11, 0, 6, 1, 8, 6));
+        assert_eq!(span(&reversed), span(&forward));
+
+        // Masks, segments and an offset band are mirrored along with the input.
+        let constraints = Constraints {
+            mask: vec![(1, 3)],
+            boundaries: vec![4],
+            band: Some(2),
+            band_offset: 1,
+        };
+        let (forward, reversed) = forward_and_reversed(&seq1, &seq2, &constraints);
+        assert_eq!(span(&forward), (6, 3, 6, 5, 8, 3));
+        assert_eq!(span(&reversed), span(&forward));
+
+        // Tied copies: forward picks the first, reversed the last.
+        let (forward, reversed) =
+            forward_and_reversed(&[1, 2], &[1, 2, 9, 1, 2], &Constraints::default());
+        assert_eq!((forward.token_start, forward.token_end), (0, 2));
+        assert_eq!((reversed.token_start, reversed.token_end), (3, 5));
+        assert_eq!(
+            (reversed.score, reversed.matches),
+            (forward.score, forward.matches)
+        );
+    }
+
+    #[test]
+    fn query_location_matches_the_full_alignment() {
+        let params = ScoreParams::new(2, -1, -1);
+        let query = vec![7, 7, 1, 2, 3, 9, 4, 5, 8];
+        let window = vec![1, 2, 3, 4, 5];
+        let location = locate_in_query(&query, &window, &params).unwrap();
+        let alignment = smith_waterman(&query, &window, params).unwrap();
+        assert_eq!(
+            location,
+            QueryLocation {
+                query_start: alignment.query_start,
+                query_end: alignment.query_end,
+                matches: alignment.matches,
+            }
+        );
+        assert_eq!((location.query_start, location.query_end), (2, 8));
+        assert_eq!(
+            locate_in_query(&query, &[6], &params).unwrap(),
+            QueryLocation::default()
+        );
+    }
+
+    #[test]
+    fn block_scores_and_gaps_add_up_to_the_total() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=6).collect();
+        // One reference token splits the match into two blocks.
+        let seq2 = vec![1, 2, 3, 9, 4, 5, 6];
+        let (alignment, blocks, scores) = smith_waterman_block_scores(
+            &seq1,
+            &seq2,
+            &params,
+            &Constraints::default(),
+            BlockOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(blocks, vec![(0, 3), (4, 7)]);
+        assert_eq!(scores, vec![6, 6]);
+        assert_eq!(scores.iter().sum::<i32>() + params.gap_ref, alignment.score);
+
+        // A run bonus is earned inside the blocks; a merged block spans the gap.
+        let bonus = ScoreParams {
+            run_bonus: 1,
+            ..params
+        };
+        let (alignment, _, scores) = smith_waterman_block_scores(
+            &seq1,
+            &seq2,
+            &bonus,
+            &Constraints::default(),
+            BlockOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(scores, vec![9, 9]);
+        assert_eq!(alignment.score, 17);
+        let options = BlockOptions {
+            max_blocks: Some(1),
+            ..BlockOptions::default()
+        };
+        let (_, blocks, scores) =
+            smith_waterman_block_scores(&seq1, &seq2, &bonus, &Constraints::default(), options)
+                .unwrap();
+        assert_eq!((blocks, scores), (vec![(0, 7)], vec![17]));
+
+        // In the query frame, with an affine gap in the query.
+        let affine = ScoreParams::affine(2, -1, -2, -1);
+        let seq1 = vec![1, 2, 3, 7, 8, 4, 5, 6];
+        let seq2: Vec<u32> = (1..=6).collect();
+        let options = BlockOptions {
+            frame: BlockFrame::Query,
+            ..BlockOptions::default()
+        };
+        let (alignment, blocks, scores) =
+            smith_waterman_block_scores(&seq1, &seq2, &affine, &Constraints::default(), options)
+                .unwrap();
+        assert_eq!(blocks, vec![(0, 3), (5, 8)]);
+        assert_eq!(scores, vec![6, 6]);
+        assert_eq!(alignment.score, 12 - 3);
+    }
+
+    #[test]
+    fn rare_tokens_outweigh_a_longer_common_block() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Tokens 1 and 2 are rare, 3 to 6 common; one mismatch splits them.
+        let seq1 = vec![1, 2, 9, 3, 4, 5, 6];
+        let seq2 = vec![1, 2, 8, 3, 4, 5, 6];
+        let mut token_idf = vec![0.5; 10];
+        token_idf[1] = 4.0;
+        token_idf[2] = 3.0;
+        let constraints = Constraints::default();
+        let options = BlockOptions::default();
+
+        let (alignment, blocks, scores) =
+            smith_waterman_block_scores(&seq1, &seq2, &params, &constraints, options).unwrap();
+        let (weighted, weighted_blocks, weights) =
+            smith_waterman_block_weights(&seq1, &seq2, &params, &constraints, options, &token_idf)
+                .unwrap();
+        assert_eq!(blocks, vec![(0, 2), (3, 7)]);
+        assert_eq!(scores, vec![4, 8]);
+        assert_eq!(weighted_blocks, blocks);
+        assert_eq!(weights, vec![7.0, 2.0]);
+        assert_eq!(
+            (weighted.score, weighted.matches),
+            (alignment.score, alignment.matches)
+        );
+
+        // A merged block counts only its matches, not the mismatch inside.
+        let merged = BlockOptions {
+            max_blocks: Some(1),
+            ..options
+        };
+        let (_, _, weights) =
+            smith_waterman_block_weights(&seq1, &seq2, &params, &constraints, merged, &token_idf)
+                .unwrap();
+        assert_eq!(weights, vec![9.0]);
+
+        assert!(
+            smith_waterman_block_weights(&seq1, &seq2, &params, &constraints, options, &[1.0])
+                .is_err()
+        );
+        token_idf[0] = f64::NAN;
+        assert!(
+            smith_waterman_block_weights(&seq1, &seq2, &params, &constraints, options, &token_idf)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn deduped_topk_equals_the_naive_ranking() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seqs = vec![
+            vec![9, 1, 2, 9],
+            vec![1, 2, 3, 4],
+            vec![9, 1, 2, 9],
+            vec![3, 4],
+            vec![1, 2, 3, 4],
+            vec![9, 1, 2, 9],
+        ];
+        for top_k in [1, 3, 6, 10] {
+            let (deduped, aligned) = align_topk_deduped(&seq1, &seqs, &params, top_k).unwrap();
+            assert_eq!(deduped, align_topk(&seq1, &seqs, params, top_k).unwrap());
+            assert_eq!(aligned, 3);
+        }
+        let (top, _) = align_topk_deduped(&seq1, &seqs, &params, 3).unwrap();
+        let indices: Vec<usize> = top.iter().map(|item| item.index).collect();
+        assert_eq!(indices, vec![1, 4, 3]);
+        assert_eq!(
+            align_topk_deduped(&seq1, &seqs, &params, 0).unwrap(),
+            (Vec::new(), 0)
+        );
     }
 }
@@ -0,0 +1,86 @@
+//! Alignment against both strands of a nucleotide-style reference.
+
+use std::collections::HashMap;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Alignment, ScoreParams, smith_waterman};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl Strand {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Strand::Forward => "+",
+            Strand::Reverse => "-",
+        }
+    }
+}
+
+/// Reverses `seq` and replaces each token by its complement. Tokens missing
+/// from `complement` are their own complement.
+pub fn reverse_complement(seq: &[u32], complement: &HashMap<u32, u32>) -> Vec<u32> {
+    seq.iter()
+        .rev()
+        .map(|token| complement.get(token).copied().unwrap_or(*token))
+        .collect()
+}
+
+/// Aligns `seq1` against `seq2` and against the reverse complement of `seq2`,
+/// returning the higher-scoring orientation. The forward strand wins ties.
+///
+/// Reference coordinates are always reported on `seq2` as given: a
+/// reverse-strand match spanning `[s, e)` of the reverse complement covers
+/// `[len - e, len - s)` of `seq2`, read right to left. Query coordinates are
+/// unaffected by the strand.
+pub fn smith_waterman_stranded(
+    seq1: &[u32],
+    seq2: &[u32],
+    params: ScoreParams,
+    complement: &HashMap<u32, u32>,
+) -> Result<(Alignment, Strand), AlignError> {
+    let forward = smith_waterman(seq1, seq2, params)?;
+    let reverse = smith_waterman(seq1, &reverse_complement(seq2, complement), params)?;
+    if reverse.score <= forward.score {
+        return Ok((forward, Strand::Forward));
+    }
+    let len = seq2.len();
+    let reverse = Alignment {
+        token_start: len - reverse.token_end,
+        token_end: len - reverse.token_start,
+        ..reverse
+    };
+    Ok((reverse, Strand::Reverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_strand_match_is_reported_on_forward_coordinates() {
+        // A=0, C=1, G=2, T=3.
+        let complement: HashMap<u32, u32> = [(0, 3), (3, 0), (1, 2), (2, 1)].into_iter().collect();
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![0, 0, 1, 2];
+        // AACGTT is its own reverse complement, so the forward strand wins the tie.
+        let seq2 = vec![0, 0, 1, 2, 3, 3];
+        // The query's reverse complement (CGTT) sits at the end.
+        let seq2_rev_only = vec![0, 0, 3, 3, 3, 1, 2, 3, 3];
+        assert!(smith_waterman(&seq1, &seq2_rev_only, params).unwrap().score < 8);
+
+        let (alignment, strand) =
+            smith_waterman_stranded(&seq1, &seq2_rev_only, params, &complement).unwrap();
+        assert_eq!(strand, Strand::Reverse);
+        assert_eq!(alignment.score, 8);
+        assert_eq!(alignment.matches, 4);
+        assert_eq!((alignment.token_start, alignment.token_end), (5, 9));
+        assert_eq!((alignment.query_start, alignment.query_end), (0, 4));
+
+        let (_, strand) = smith_waterman_stranded(&seq1, &seq2, params, &complement).unwrap();
+        assert_eq!(strand, Strand::Forward);
+    }
+}
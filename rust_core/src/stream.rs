@@ -0,0 +1,135 @@
+//! Best alignments of many queries, handed out one query at a time as each
+//! finishes instead of after the whole batch.
+
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{CandidateAlignment, ScoreParams, align_best};
+use crate::{AlignmentDetails, GapScore, candidate_details, parallel, score_params};
+
+/// A query index and its `align_best` result.
+pub type StreamItem = (usize, Result<Option<CandidateAlignment>, AlignError>);
+
+/// `align_best_batch` run on a background thread, yielding each query's
+/// result as soon as a rayon worker finishes it.
+///
+/// Results arrive in completion order, not query order, so each carries its
+/// query index; sort on it to recover the batch order. Dropping the stream
+/// stops workers from starting further queries.
+#[pyclass(name = "AlignStream", frozen)]
+pub struct AlignStream {
+    receiver: Mutex<Receiver<StreamItem>>,
+}
+
+impl AlignStream {
+    pub fn spawn(
+        queries: Vec<Vec<u32>>,
+        seqs: Vec<Vec<u32>>,
+        params: ScoreParams,
+        num_threads: Option<usize>,
+    ) -> Result<Self, AlignError> {
+        if num_threads == Some(0) {
+            return Err(AlignError::InvalidArgument(
+                "num_threads must be at least 1".to_string(),
+            ));
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let fed = parallel::with_thread_pool(num_threads, || {
+                // A failed send means the stream was dropped: stop early.
+                let _ = queries
+                    .par_iter()
+                    .enumerate()
+                    .try_for_each_with(sender.clone(), |sender, (index, query)| {
+                        sender.send((index, align_best(query, &seqs, params)))
+                    });
+                Ok(())
+            });
+            if let Err(error) = fed {
+                let _ = sender.send((0, Err(error)));
+            }
+        });
+        Ok(Self {
+            receiver: Mutex::new(receiver),
+        })
+    }
+
+    /// Blocks until the next query finishes; `None` once every query has
+    /// been handed out.
+    pub fn next_result(&self) -> Option<StreamItem> {
+        self.receiver.lock().ok()?.recv().ok()
+    }
+}
+
+#[pymethods]
+impl AlignStream {
+    #[new]
+    #[pyo3(signature = (queries, seqs, match_score=2, mismatch_score=-1, gap_score=GapScore::Linear(-1), wildcard=None, gap_query=None, gap_ref=None, run_bonus=0, end_gap_discount=0, num_threads=None))]
+    fn py_new(
+        py: Python<'_>,
+        queries: Vec<Vec<u32>>,
+        seqs: Vec<Vec<u32>>,
+        match_score: i32,
+        mismatch_score: i32,
+        gap_score: GapScore,
+        wildcard: Option<u32>,
+        gap_query: Option<i32>,
+        gap_ref: Option<i32>,
+        run_bonus: i32,
+        end_gap_discount: i32,
+        num_threads: Option<usize>,
+    ) -> PyResult<Self> {
+        let params = score_params(
+            py,
+            match_score,
+            mismatch_score,
+            gap_score,
+            wildcard,
+            gap_query,
+            gap_ref,
+            run_bonus,
+            end_gap_discount,
+        )?;
+        Ok(Self::spawn(queries, seqs, params, num_threads)?)
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// `(query_index, details)`, with `details` `None` for an empty corpus.
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<(usize, Option<AlignmentDetails>)>> {
+        let Some((index, best)) = py.detach(|| self.next_result()) else {
+            return Ok(None);
+        };
+        Ok(Some((index, best?.as_ref().map(candidate_details))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::align_best_batch;
+
+    #[test]
+    fn streamed_results_match_the_batch_once_sorted() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seqs: Vec<Vec<u32>> = (0..40u32).map(|n| vec![n % 5, 1, 2, n % 3, 3]).collect();
+        let queries: Vec<Vec<u32>> = (0..30u32).map(|n| vec![n % 4, n % 5, 3]).collect();
+
+        let stream = AlignStream::spawn(queries.clone(), seqs.clone(), params, Some(3)).unwrap();
+        let mut streamed = Vec::new();
+        while let Some((index, best)) = stream.next_result() {
+            streamed.push((index, best.unwrap()));
+        }
+        streamed.sort_by_key(|(index, _)| *index);
+
+        let batch = align_best_batch(&queries, &seqs, params).unwrap();
+        assert_eq!(streamed, batch.into_iter().enumerate().collect::<Vec<_>>());
+        assert!(AlignStream::spawn(queries, seqs, params, Some(0)).is_err());
+    }
+}
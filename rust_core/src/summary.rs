@@ -0,0 +1,123 @@
+//! Per-operation counts of an alignment, tallied from its traceback path.
+
+use pyo3::prelude::*;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{Constraints, Scoring, Step, smith_waterman_path};
+
+/// How the columns of the best alignment break down by operation.
+///
+/// Every column is exactly one of a match, a mismatch, a query gap or a
+/// reference gap, so the four counts add up to `aligned_length`.
+#[pyclass(name = "AlignmentSummary", frozen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AlignmentSummary {
+    #[pyo3(get)]
+    pub score: i32,
+    /// Query tokens aligned to an equal reference token (`Scoring::is_match`).
+    #[pyo3(get)]
+    pub matches: usize,
+    /// Query tokens aligned to a differing reference token.
+    #[pyo3(get)]
+    pub mismatches: usize,
+    /// Reference tokens aligned to a gap in the query.
+    #[pyo3(get)]
+    pub query_gaps: usize,
+    /// Query tokens aligned to a gap in the reference.
+    #[pyo3(get)]
+    pub ref_gaps: usize,
+    /// Columns in the alignment path.
+    #[pyo3(get)]
+    pub aligned_length: usize,
+}
+
+/// Summary of the `smith_waterman_with` alignment of `seq1` in `seq2`; all
+/// zero when nothing aligns.
+pub fn summarize<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<AlignmentSummary, AlignError> {
+    let (alignment, path) = smith_waterman_path(seq1, seq2, scoring, constraints)?;
+    let mut summary = AlignmentSummary {
+        score: alignment.score,
+        aligned_length: path.len(),
+        ..AlignmentSummary::default()
+    };
+    let (mut i, mut j) = (alignment.query_start, alignment.token_start);
+    for step in path {
+        match step {
+            Step::Diagonal => {
+                if scoring.is_match(seq1[i], seq2[j]) {
+                    summary.matches += 1;
+                } else {
+                    summary.mismatches += 1;
+                }
+                i += 1;
+                j += 1;
+            }
+            Step::Up => {
+                summary.ref_gaps += 1;
+                i += 1;
+            }
+            Step::Left => {
+                summary.query_gaps += 1;
+                j += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[pymethods]
+impl AlignmentSummary {
+    fn __repr__(&self) -> String {
+        format!(
+            "AlignmentSummary(score={}, matches={}, mismatches={}, query_gaps={}, ref_gaps={}, aligned_length={})",
+            self.score,
+            self.matches,
+            self.mismatches,
+            self.query_gaps,
+            self.ref_gaps,
+            self.aligned_length
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn summary_counts_add_up_to_the_aligned_length() {
+        let params = ScoreParams::new(2, -1, -1);
+        // One substitution, one reference insertion, one query insertion.
+        let seq1 = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let seq2 = vec![1, 2, 30, 4, 5, 50, 6, 7, 9, 10];
+        let summary = summarize(&seq1, &seq2, &params, &Constraints::default()).unwrap();
+        let alignment = smith_waterman(&seq1, &seq2, params).unwrap();
+
+        assert_eq!(
+            summary,
+            AlignmentSummary {
+                score: alignment.score,
+                matches: 8,
+                mismatches: 1,
+                query_gaps: 1,
+                ref_gaps: 1,
+                aligned_length: 11,
+            }
+        );
+        assert_eq!(summary.matches, alignment.matches);
+        assert_eq!(
+            summary.matches + summary.mismatches + summary.query_gaps + summary.ref_gaps,
+            summary.aligned_length
+        );
+        assert_eq!(
+            summarize(&[1, 2], &[3, 4], &params, &Constraints::default()).unwrap(),
+            AlignmentSummary::default()
+        );
+    }
+}
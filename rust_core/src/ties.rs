@@ -0,0 +1,269 @@
+//! Co-optimal alignments: every path reaching the maximum score, not just the
+//! one the direction priority picks.
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, Constraints, ScoreMatrix, ScoreParams, Scoring, Step, TIE_DIAG, TIE_LEFT, TIE_START,
+    TIE_UP, fill_matrix_with_ties,
+};
+
+/// Cap `count_optimal_alignments` stops counting at. A million tied paths is
+/// already far past any useful ambiguity signal, and repeats can otherwise
+/// push the count towards `u64::MAX`.
+pub const DEFAULT_CO_OPTIMAL_CAP: u64 = 1_000_000;
+
+/// Fills the tie-recording matrix, rejecting `run_bonus` and affine gaps: a
+/// run's length and a gap's open state follow the single priority path, so
+/// other tied paths may not really score the same.
+fn tie_matrix<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+) -> Result<Option<ScoreMatrix>, AlignError> {
+    if scoring.params().run_bonus != 0 || scoring.params().is_affine() {
+        return Err(AlignError::InvalidParams(
+            "run_bonus and gap_open are not supported for co-optimal alignments".to_string(),
+        ));
+    }
+    fill_matrix_with_ties(seq1, seq2, scoring, constraints)
+}
+
+/// The cell each tied move comes from and the step it adds, in the order
+/// paths are enumerated.
+fn moves(bits: u8, i: usize, j: usize) -> impl Iterator<Item = (usize, usize, Step, bool)> {
+    [
+        (TIE_DIAG, i - 1, j - 1, Step::Diagonal, false),
+        (TIE_UP, i - 1, j, Step::Up, false),
+        (TIE_LEFT, i, j - 1, Step::Left, false),
+        (TIE_START, i - 1, j - 1, Step::Diagonal, true),
+    ]
+    .into_iter()
+    .filter(move |&(bit, ..)| bits & bit != 0)
+    .map(|(_, i, j, step, starts)| (i, j, step, starts))
+}
+
+/// Number of distinct optimal paths over all cells tied at the maximum score,
+/// capped at `cap`. 0 when nothing scores above zero.
+///
+/// Unlike `smith_waterman_all_max`, paths sharing both end points but
+/// differing in between (e.g. where a gap goes) count separately. Repeats can
+/// make the true count grow exponentially with length, so every partial count
+/// is clamped to `cap` and a result equal to `cap` means "at least `cap`";
+/// pass `u64::MAX` for no practical limit. `cap` must be positive.
+pub fn count_co_optimal<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    cap: u64,
+) -> Result<u64, AlignError> {
+    if cap == 0 {
+        return Err(AlignError::InvalidArgument(
+            "cap must be positive".to_string(),
+        ));
+    }
+    let Some(matrix) = tie_matrix(seq1, seq2, scoring, constraints)? else {
+        return Ok(0);
+    };
+    // paths[i][j]: optimal paths ending at (i, j); predecessors come earlier in
+    // row-major order, so one forward pass suffices.
+    let mut paths = vec![vec![0u64; seq2.len() + 1]; seq1.len() + 1];
+    for i in 1..=seq1.len() {
+        for j in 1..=seq2.len() {
+            if matrix.scores[i][j] <= 0 {
+                continue;
+            }
+            paths[i][j] =
+                moves(matrix.ties[i][j], i, j).fold(0u64, |total, (pi, pj, _, starts)| {
+                    let from = if starts || matrix.scores[pi][pj] <= 0 {
+                        1
+                    } else {
+                        paths[pi][pj]
+                    };
+                    total.saturating_add(from).min(cap)
+                });
+        }
+    }
+    Ok(matrix.max_positions.iter().fold(0u64, |total, &(i, j)| {
+        total.saturating_add(paths[i][j]).min(cap)
+    }))
+}
+
+/// `count_co_optimal` under plain `params`, as an ambiguity signal: 1 for an
+/// unambiguous best alignment, more the more equally good readings a
+/// repetitive query or reference allows. Callers without a better bound pass
+/// `DEFAULT_CO_OPTIMAL_CAP`.
+pub fn count_optimal_alignments(
+    seq1: &[u32],
+    seq2: &[u32],
+    params: &ScoreParams,
+    constraints: &Constraints,
+    cap: u64,
+) -> Result<u64, AlignError> {
+    count_co_optimal(seq1, seq2, params, constraints, cap)
+}
+
+/// Up to `limit` optimal alignments with their paths, ending cells in
+/// row-major order and, per cell, diagonal moves explored before up and left.
+pub fn co_optimal_paths<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    constraints: &Constraints,
+    limit: usize,
+) -> Result<Vec<(Alignment, Vec<Step>)>, AlignError> {
+    let Some(matrix) = tie_matrix(seq1, seq2, scoring, constraints)? else {
+        return Ok(Vec::new());
+    };
+    let mut found = Vec::new();
+    for &(i_end, j_end) in &matrix.max_positions {
+        // Depth-first over partial paths, each stored backwards from the end.
+        let mut stack = vec![(i_end, j_end, Vec::new())];
+        while let Some((i, j, steps)) = stack.pop() {
+            if found.len() == limit {
+                return Ok(found);
+            }
+            let branches: Vec<_> = moves(matrix.ties[i][j], i, j).collect();
+            for &(pi, pj, step, starts) in branches.iter().rev() {
+                let mut steps = steps.clone();
+                steps.push(step);
+                if starts || matrix.scores[pi][pj] <= 0 {
+                    steps.reverse();
+                    let alignment = Alignment {
+                        score: matrix.max_score,
+                        query_start: pi,
+                        query_end: i_end,
+                        token_start: pj,
+                        token_end: j_end,
+                        matches: path_matches(pi, pj, &steps, seq1, seq2, scoring),
+                    };
+                    found.push((alignment, steps));
+                    if found.len() == limit {
+                        return Ok(found);
+                    }
+                } else {
+                    stack.push((pi, pj, steps));
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn path_matches<S: Scoring>(
+    mut i: usize,
+    mut j: usize,
+    steps: &[Step],
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> usize {
+    let mut matches = 0;
+    for step in steps {
+        match step {
+            Step::Diagonal => {
+                if scoring.is_match(seq1[i], seq2[j]) {
+                    matches += 1;
+                }
+                i += 1;
+                j += 1;
+            }
+            Step::Up => i += 1,
+            Step::Left => j += 1,
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman_path};
+    use Step::{Diagonal, Left};
+
+    #[test]
+    fn counts_both_placements_of_an_ambiguous_gap() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Query 2 can pair with either reference 2; the other becomes the gap.
+        let seq1 = vec![1, 2, 3];
+        let seq2 = vec![1, 2, 2, 3];
+        let constraints = Constraints::default();
+
+        assert_eq!(
+            count_co_optimal(&seq1, &seq2, &params, &constraints, u64::MAX).unwrap(),
+            2
+        );
+        let paths = co_optimal_paths(&seq1, &seq2, &params, &constraints, 10).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].1, vec![Diagonal, Left, Diagonal, Diagonal]);
+        assert_eq!(paths[1].1, vec![Diagonal, Diagonal, Left, Diagonal]);
+        for (alignment, _) in &paths {
+            assert_eq!((alignment.score, alignment.matches), (5, 3));
+        }
+
+        // The default traceback returns one of them.
+        let (_, path) = smith_waterman_path(&seq1, &seq2, &params, &constraints).unwrap();
+        assert!(paths.iter().any(|(_, steps)| *steps == path));
+
+        assert_eq!(
+            count_co_optimal(&seq1, &[9], &params, &constraints, u64::MAX).unwrap(),
+            0
+        );
+        let bonus = ScoreParams {
+            run_bonus: 1,
+            ..params
+        };
+        assert!(count_co_optimal(&seq1, &seq2, &bonus, &constraints, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn counts_each_repeat_of_the_query_up_to_the_cap() {
+        let params = ScoreParams::new(2, -1, -1);
+        let constraints = Constraints::default();
+        let count = |seq2: &[u32], cap: u64| {
+            count_co_optimal(&[1, 2], seq2, &params, &constraints, cap).unwrap()
+        };
+
+        // One exact occurrence per repeat, each a single path.
+        assert_eq!(count(&[1, 2, 1, 2, 1, 2], u64::MAX), 3);
+        assert_eq!(count(&[7, 1, 2, 8], u64::MAX), 1);
+        assert_eq!(count(&[1, 2, 1, 2, 1, 2], 2), 2);
+        assert!(count_co_optimal(&[1, 2], &[1, 2], &params, &constraints, 0).is_err());
+    }
+
+    #[test]
+    fn default_cap_saturates_on_tied_gap_placements() {
+        let params = ScoreParams::new(2, -1, -1);
+        // Each unit of the reference repeats its 2, and the query's 2 can pair
+        // with either copy; the last unit ends on its first 2, so 22 units give
+        // 2^21 optimal paths.
+        let seq1: Vec<u32> = [1, 2].repeat(22);
+        let seq2: Vec<u32> = [1, 2, 2].repeat(22);
+        let constraints = Constraints::default();
+        let count = |seq1: &[u32], seq2: &[u32], cap| {
+            count_optimal_alignments(seq1, seq2, &params, &constraints, cap).unwrap()
+        };
+        assert_eq!(count(&seq1, &seq2, u64::MAX), 1 << 21);
+        assert_eq!(
+            count(&seq1, &seq2, DEFAULT_CO_OPTIMAL_CAP),
+            DEFAULT_CO_OPTIMAL_CAP
+        );
+        assert_eq!(
+            count(&[1, 2, 3], &[7, 1, 2, 3, 8], DEFAULT_CO_OPTIMAL_CAP),
+            1
+        );
+
+        let masked = Constraints {
+            mask: vec![(0, 3)],
+            ..Constraints::default()
+        };
+        let seq2 = [1, 2, 9, 1, 2, 9, 1, 2];
+        assert_eq!(count(&[1, 2], &seq2, DEFAULT_CO_OPTIMAL_CAP), 3);
+        assert_eq!(
+            count_optimal_alignments(&[1, 2], &seq2, &params, &masked, DEFAULT_CO_OPTIMAL_CAP)
+                .unwrap(),
+            2
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{HashMap, HashSet};
+
+/// Splits `text` into maximal runs of alphanumeric characters and maps each
+/// run to its id in `vocab`.
+///
+/// Every other character acts as a separator and is dropped. Lookups are exact,
+/// so the vocabulary must be built with the same normalization (e.g. lowercasing)
+/// the caller wants applied. Words missing from `vocab` map to `unknown_id`.
+pub fn tokenize(text: &str, vocab: &HashMap<String, u32>, unknown_id: u32) -> Vec<u32> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| vocab.get(word).copied().unwrap_or(unknown_id))
+        .collect()
+}
+
+/// Replaces each id in `tokens` with `canonical[id]`, e.g. to fold
+/// capitalization variants onto one id. Ids past the end of the table are
+/// kept as they are.
+///
+/// A dense table indexed by id; for sparse remappings use
+/// `scoring::EquivalenceScoring` instead.
+pub fn canonicalize(tokens: &[u32], canonical: &[u32]) -> Vec<u32> {
+    tokens
+        .iter()
+        .map(|&token| canonical.get(token as usize).copied().unwrap_or(token))
+        .collect()
+}
+
+/// Id statistics over a set of sequences, see `analyze_tokens`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenStats {
+    /// Number of distinct ids.
+    pub distinct: usize,
+    /// Smallest and largest id, `None` when there are no tokens.
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+    /// Whether the ids are exactly `0..distinct`, with none missing.
+    pub dense: bool,
+}
+
+/// Pre-flight check for id-indexed features such as a dense substitution
+/// matrix, which needs `max + 1` rows however few ids are used.
+///
+/// Alignment itself only compares ids for equality, so sparse ids are fine
+/// there; when `dense` is false, remapping the ids to `0..distinct` first
+/// (e.g. by rank) gives the smallest matrix. No tokens count as dense.
+pub fn analyze_tokens(seqs: &[Vec<u32>]) -> TokenStats {
+    let ids: HashSet<u32> = seqs.iter().flatten().copied().collect();
+    let (min, max) = (ids.iter().min().copied(), ids.iter().max().copied());
+    TokenStats {
+        distinct: ids.len(),
+        min,
+        max,
+        dense: max.is_none_or(|max| max as usize + 1 == ids.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn tokenize_sentence_aligns_against_itself() {
+        let vocab: HashMap<String, u32> = ["the", "cat", "sat", "on", "mat"]
+            .iter()
+            .enumerate()
+            .map(|(id, word)| (word.to_string(), id as u32 + 1))
+            .collect();
+        let tokens = tokenize("The cat sat on the mat.", &vocab, 0);
+        assert_eq!(tokens, vec![0, 2, 3, 4, 1, 5]);
+
+        let params = ScoreParams::new(2, -1, -1);
+        let alignment = smith_waterman(&tokens, &tokens, params).unwrap();
+        assert_eq!(alignment.score, 2 * tokens.len() as i32);
+        assert_eq!(alignment.matches, tokens.len());
+    }
+
+    #[test]
+    fn canonical_table_folds_case_variants() {
+        // 0 "the", 1 "cat", 2 "The", 3 "Cat"; 4 "sat" is past the table.
+        let canonical = [0, 1, 0, 1];
+        let query = vec![2, 3, 4];
+        let reference = vec![0, 1, 4];
+        let params = ScoreParams::new(2, -1, -1);
+        assert_eq!(
+            smith_waterman(&query, &reference, params).unwrap().matches,
+            1
+        );
+
+        let query = canonicalize(&query, &canonical);
+        assert_eq!(query, vec![0, 1, 4]);
+        let reference = canonicalize(&reference, &canonical);
+        let alignment = smith_waterman(&query, &reference, params).unwrap();
+        assert_eq!((alignment.score, alignment.matches), (6, 3));
+    }
+
+    #[test]
+    fn token_stats_tell_dense_ids_from_sparse() {
+        let dense = analyze_tokens(&[vec![0, 2, 1], vec![3, 0]]);
+        assert_eq!(
+            dense,
+            TokenStats {
+                distinct: 4,
+                min: Some(0),
+                max: Some(3),
+                dense: true,
+            }
+        );
+
+        let sparse = analyze_tokens(&[vec![7, 1_000_000], vec![7, 42]]);
+        assert_eq!(
+            sparse,
+            TokenStats {
+                distinct: 3,
+                min: Some(7),
+                max: Some(1_000_000),
+                dense: false,
+            }
+        );
+        // Starting at 1 leaves id 0 unused.
+        assert!(!analyze_tokens(&[vec![1, 2, 3]]).dense);
+
+        let empty = analyze_tokens(&[vec![]]);
+        assert_eq!((empty.distinct, empty.max, empty.dense), (0, None, true));
+    }
+}
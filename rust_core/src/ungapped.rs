@@ -0,0 +1,257 @@
+//! Gap-free local alignment for inputs where indels cannot occur.
+
+use std::cmp::Ordering;
+
+use crate::error::AlignError;
+use crate::smith_waterman::{
+    Alignment, Constraints, Scoring, check_score_range, cmp_alignment, empty_alignment,
+    smith_waterman_with,
+};
+
+/// Best contiguous local alignment of `seq1` in `seq2` with no gaps.
+///
+/// Each diagonal (fixed offset between query and reference) is scanned once,
+/// keeping a running score clamped at zero exactly as the Smith-Waterman
+/// diagonal would. Each offset costs one linear pass and no matrix is kept.
+/// Ties resolve like `smith_waterman`, and the score never exceeds the gapped
+/// score for the same scheme.
+pub fn smith_waterman_ungapped<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> Result<Alignment, AlignError> {
+    if seq1.is_empty() || seq2.is_empty() {
+        return Ok(empty_alignment());
+    }
+    check_score_range(seq1.len(), seq2.len(), scoring)?;
+    let run_bonus = scoring.params().run_bonus;
+
+    let mut best: Option<Alignment> = None;
+    // `offset` is `j - i`, from the query's last token against the reference's
+    // first through the query's first against the reference's last.
+    for offset in -(seq1.len() as isize - 1)..seq2.len() as isize {
+        let i_first = (-offset).max(0) as usize;
+        let j_first = offset.max(0) as usize;
+        let mut score = 0i32;
+        let mut matches = 0usize;
+        let mut run = 0i32;
+        let mut start = 0usize;
+        let diagonal = seq1[i_first..].iter().zip(&seq2[j_first..]);
+        for (step, (&a, &b)) in diagonal.enumerate() {
+            let is_match = scoring.is_match(a, b);
+            let bonus = if is_match { run_bonus * run } else { 0 };
+            let extended = score + scoring.substitution(a, b) + bonus;
+            if extended <= 0 {
+                score = 0;
+                matches = 0;
+                run = 0;
+                start = step + 1;
+                continue;
+            }
+            score = extended;
+            if is_match {
+                matches += 1;
+                run += 1;
+            } else {
+                run = 0;
+            }
+
+            let candidate = Alignment {
+                score,
+                query_start: i_first + start,
+                query_end: i_first + step + 1,
+                token_start: j_first + start,
+                token_end: j_first + step + 1,
+                matches,
+            };
+            if best
+                .as_ref()
+                .is_none_or(|current| cmp_alignment(&candidate, current) == Ordering::Less)
+            {
+                best = Some(candidate);
+            }
+        }
+    }
+    Ok(best.unwrap_or_else(empty_alignment))
+}
+
+/// Cheap `(lower, upper)` bounds on the `smith_waterman` score of `seq1` in
+/// `seq2`, for pruning before committing to the full DP.
+///
+/// `lower` is the `smith_waterman_ungapped` score: a gap-free path is one of
+/// the paths the gapped DP considers. `upper` assumes every token of the
+/// shorter sequence pairs at the best substitution score, plus the largest
+/// possible `run_bonus`; gaps never add score.
+pub fn score_bounds<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+) -> Result<(i32, i32), AlignError> {
+    let lower = smith_waterman_ungapped(seq1, seq2, scoring)?.score;
+    // In range: the ungapped pass ran `check_score_range`, whose bound is larger.
+    let longest = seq1.len().min(seq2.len()) as i32;
+    let run_total = scoring.params().run_bonus * (longest * (longest - 1).max(0) / 2);
+    let upper = longest * scoring.max_substitution() + run_total;
+    Ok((lower, upper))
+}
+
+/// Approximate `smith_waterman` for short, dense matches: full DP only within
+/// `band` of the `probes` diagonals holding the most exact matches.
+///
+/// The match count of every diagonal is one linear scan, as in
+/// `smith_waterman_ungapped`; diagonals tie-break towards the lower offset
+/// `j - i`. The answer is exact when the best alignment stays within `band`
+/// of a probed diagonal, and otherwise a lower-scoring alignment (never a
+/// higher one) is returned.
+pub fn smith_waterman_diagonal_prefilter<S: Scoring>(
+    seq1: &[u32],
+    seq2: &[u32],
+    scoring: &S,
+    probes: usize,
+    band: usize,
+) -> Result<Alignment, AlignError> {
+    if seq1.is_empty() || seq2.is_empty() || probes == 0 {
+        return Ok(empty_alignment());
+    }
+    let first_offset = -(seq1.len() as isize - 1);
+    let mut diagonals: Vec<(usize, isize)> = (first_offset..seq2.len() as isize)
+        .map(|offset| {
+            let i_first = (-offset).max(0) as usize;
+            let j_first = offset.max(0) as usize;
+            let matches = seq1[i_first..]
+                .iter()
+                .zip(&seq2[j_first..])
+                .filter(|&(&a, &b)| scoring.is_match(a, b))
+                .count();
+            (matches, offset)
+        })
+        .collect();
+    diagonals.sort_by_key(|&(matches, offset)| (std::cmp::Reverse(matches), offset));
+
+    let mut best: Option<Alignment> = None;
+    for &(_, offset) in diagonals.iter().take(probes) {
+        let constraints = Constraints {
+            band: Some(band),
+            band_offset: offset,
+            ..Constraints::default()
+        };
+        let candidate = smith_waterman_with(seq1, seq2, scoring, &constraints)?;
+        if best
+            .as_ref()
+            .is_none_or(|current| cmp_alignment(&candidate, current) == Ordering::Less)
+        {
+            best = Some(candidate);
+        }
+    }
+    Ok(best.unwrap_or_else(empty_alignment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smith_waterman::{ScoreParams, smith_waterman};
+
+    #[test]
+    fn ungapped_never_beats_gapped() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1 = vec![1, 2, 3, 4];
+        let seq2 = vec![1, 2, 9, 3, 4];
+
+        let gapped = smith_waterman(&seq1, &seq2, params).unwrap();
+        let ungapped = smith_waterman_ungapped(&seq1, &seq2, &params).unwrap();
+        assert_eq!(gapped.score, 7);
+        assert_eq!(ungapped.score, 4);
+        assert_eq!((ungapped.token_start, ungapped.token_end), (0, 2));
+        assert_eq!((ungapped.query_start, ungapped.query_end), (0, 2));
+
+        let cases: [(&[u32], &[u32]); 3] = [
+            (&[1, 2], &[1, 2, 1, 2]),
+            (&[5, 1, 2, 7, 3], &[1, 2, 8, 3, 9]),
+            (&[1, 2, 3], &[4, 5, 6]),
+        ];
+        for (seq1, seq2) in cases {
+            let gapped = smith_waterman(seq1, seq2, params).unwrap();
+            let ungapped = smith_waterman_ungapped(seq1, seq2, &params).unwrap();
+            assert!(ungapped.score <= gapped.score);
+        }
+        let same = smith_waterman_ungapped(&[1, 2], &[1, 2, 1, 2], &params).unwrap();
+        assert_eq!((same.score, same.token_start, same.token_end), (4, 0, 2));
+    }
+
+    #[test]
+    fn bounds_contain_the_gapped_score() {
+        let params = ScoreParams::new(2, -1, -1);
+        let bonus = ScoreParams {
+            run_bonus: 1,
+            ..params
+        };
+        // Small-alphabet pseudo-random inputs, so matches and gaps both occur.
+        let mut state = 0x2545_f491u32;
+        let mut next = |len: usize| -> Vec<u32> {
+            (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state % 4
+                })
+                .collect()
+        };
+        for case in 0..50 {
+            let seq1 = next(1 + case % 7);
+            let seq2 = next(3 + case % 11);
+            for scheme in [params, bonus] {
+                let (lower, upper) = score_bounds(&seq1, &seq2, &scheme).unwrap();
+                let score = smith_waterman(&seq1, &seq2, scheme).unwrap().score;
+                assert!(lower <= score && score <= upper, "{seq1:?} {seq2:?}");
+            }
+        }
+        assert_eq!(
+            score_bounds(&[1, 2, 3], &[1, 2, 3], &params).unwrap(),
+            (6, 6)
+        );
+        assert_eq!(score_bounds(&[], &[1], &params).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn prefilter_recovers_a_dominant_diagonal() {
+        let params = ScoreParams::new(2, -1, -1);
+        let seq1: Vec<u32> = (1..=20).collect();
+        // The query embedded at offset 7 with one substitution and one
+        // inserted token, among unrelated tokens.
+        let mut seq2: Vec<u32> = (100..107).collect();
+        seq2.extend(1..=9);
+        seq2.push(50);
+        seq2.extend(10..=20);
+        seq2[10] = 60;
+        seq2.extend(200..210);
+
+        let full = smith_waterman(&seq1, &seq2, params).unwrap();
+        let approx = smith_waterman_diagonal_prefilter(&seq1, &seq2, &params, 2, 2).unwrap();
+        assert_eq!(
+            (
+                approx.score,
+                approx.query_start,
+                approx.query_end,
+                approx.token_start,
+                approx.token_end,
+                approx.matches
+            ),
+            (
+                full.score,
+                full.query_start,
+                full.query_end,
+                full.token_start,
+                full.token_end,
+                full.matches
+            )
+        );
+        assert_eq!(full.score, 36);
+
+        // A band too narrow to follow the insertion loses score, never gains.
+        let narrow = smith_waterman_diagonal_prefilter(&seq1, &seq2, &params, 1, 0).unwrap();
+        assert!(narrow.score < full.score);
+        let none = smith_waterman_diagonal_prefilter(&seq1, &seq2, &params, 0, 2).unwrap();
+        assert_eq!(none.score, 0);
+    }
+}
@@ -1,47 +1,1052 @@
 from __future__ import annotations
 
-from typing import Sequence
+from typing import Callable, Literal, Sequence
 
+import numpy as np
+from numpy.typing import NDArray
+
+def score_preset(
+    name: Literal["citation", "dna", "strict", "lenient"],
+) -> tuple[int, int, int]: ...
 def align_pair(
     seq1: Sequence[int],
     seq2: Sequence[int],
     match_score: int = ...,
     mismatch_score: int = ...,
-    gap_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    release_gil: bool = ...,
+) -> tuple[int, int, int]: ...
+def align_pair_str(
+    text1: str,
+    text2: str,
+    vocab: dict[str, int],
+    unknown_id: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
 ) -> tuple[int, int, int]: ...
+def tokenize(
+    text: str,
+    vocab: dict[str, int],
+    unknown_id: int = ...,
+) -> list[int]: ...
+def analyze_tokens(
+    seqs: Sequence[Sequence[int]],
+) -> tuple[int, int | None, int | None, bool]: ...
 def align_pair_details(
     seq1: Sequence[int],
     seq2: Sequence[int],
     match_score: int = ...,
     mismatch_score: int = ...,
-    gap_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+    equiv: dict[int, int] | None = ...,
+    inclusive_ends: bool = ...,
+    release_gil: bool = ...,
 ) -> tuple[int, int, int, int, int, int]: ...
 def align_pair_blocks_details(
     seq1: Sequence[int],
     seq2: Sequence[int],
     match_score: int = ...,
     mismatch_score: int = ...,
-    gap_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+    inclusive_ends: bool = ...,
+    frame: Literal["query", "reference"] = ...,
+    min_anchor: int = ...,
+    max_blocks: int | None = ...,
+    release_gil: bool = ...,
 ) -> tuple[int, int, int, int, int, int, list[tuple[int, int]]]: ...
+def align_pair_block_scores(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+    frame: Literal["query", "reference"] = ...,
+    min_anchor: int = ...,
+    max_blocks: int | None = ...,
+) -> tuple[int, int, int, int, int, int, list[tuple[int, int]], list[int]]: ...
+def align_pair_block_weights(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    token_idf: Sequence[float],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+    frame: Literal["query", "reference"] = ...,
+    min_anchor: int = ...,
+    max_blocks: int | None = ...,
+) -> tuple[int, int, int, int, int, int, list[tuple[int, int]], list[float]]: ...
+def align_pair_ungapped(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def score_bounds(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int]: ...
+def align_best_diagonal_prefilter(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    probes: int = ...,
+    band: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_pair_cfg(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    config: AlignConfig,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_topk_cfg(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    config: AlignConfig,
+    top_k: int = ...,
+) -> list[tuple[int, int, int, int, int, int, int]]: ...
+def align_pair_banded(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    band: int,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[tuple[int, int, int, int, int, int], bool]: ...
+def align_pair_clips(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[tuple[int, int, int, int, int, int], tuple[int, int, int, int]]: ...
+def align_pair_is_match(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    floor: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[tuple[int, int, int, int, int, int], bool]: ...
+def align_pair_diagonal(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[tuple[int, int, int, int, int, int], int]: ...
+def merge_alignments(
+    a: tuple[int, int, int, int, int, int],
+    b: tuple[int, int, int, int, int, int],
+) -> tuple[int, int, int, int, int, int]: ...
+def align_pair_coverage(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[tuple[int, int, int, int, int, int], float, float]: ...
+def align_pair_checked(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    on_empty: Literal["none", "raise"] = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int] | None: ...
+def count_optimal_alignments(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+    cap: int | None = ...,
+) -> int: ...
+def align_pair_all_max(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+) -> list[tuple[int, int, int, int, int, int]]: ...
+def align_pair_min_positive(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int] | None: ...
+def align_pair_nth(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    n: int,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int] | None: ...
+def align_pair_stats(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+) -> tuple[tuple[int, int, int, int, int, int], tuple[int, int]]: ...
+def align_pair_observed(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    on_row: Callable[[int, int, int, int, int], object],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_pair_matrix(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[tuple[int, int, int, int, int, int], NDArray[np.int32]]: ...
+def column_maxima(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> NDArray[np.int32]: ...
+def align_pair_end_score(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[tuple[int, int, int, int, int, int], int]: ...
+def pairwise_score_matrix(
+    seqs: Sequence[Sequence[int]],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> NDArray[np.int32]: ...
+def align_pair_summary(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> AlignmentSummary: ...
+def align_pair_aligned_tokens(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[list[int | None], list[int | None]]: ...
+def align_pair_matched_tokens(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> list[int]: ...
+def classify_match(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    min_score: int = ...,
+    strong_identity: float = ...,
+    strong_coverage: float = ...,
+    partial_coverage: float = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[
+    Literal["exact", "strong", "partial", "none"], tuple[int, int, int, int, int, int]
+]: ...
+def align_pair_matched_span(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    numeric_tolerance: int = ...,
+) -> tuple[int, int, int, int] | None: ...
+def align_pair_stranded(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    complement: dict[int, int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int, Literal["+", "-"]]: ...
 def align_best(
     seq1: Sequence[int],
     seqs: Sequence[Sequence[int]],
     match_score: int = ...,
     mismatch_score: int = ...,
-    gap_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+    min_query_coverage: float = ...,
+    max_gaps: int | None = ...,
+    min_columns: int = ...,
+    release_gil: bool = ...,
 ) -> tuple[int, int, int, int] | None: ...
 def align_best_details(
     seq1: Sequence[int],
     seqs: Sequence[Sequence[int]],
     match_score: int = ...,
     mismatch_score: int = ...,
-    gap_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+    min_query_coverage: float = ...,
+    max_gaps: int | None = ...,
+    min_columns: int = ...,
+    release_gil: bool = ...,
 ) -> tuple[int, int, int, int, int, int, int] | None: ...
+def align_best_blocks_details(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+    release_gil: bool = ...,
+) -> tuple[int, int, int, int, int, int, int, list[tuple[int, int]]] | None: ...
+def align_best_batch(
+    queries: Sequence[Sequence[int]],
+    seqs: Sequence[Sequence[int]],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> list[tuple[int, int, int, int, int, int, int] | None]: ...
+def align_best_json(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> str: ...
+def align_above_threshold(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    min_score: int,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> list[tuple[int, int, int, int, int, int, int]]: ...
+def align_above_percentile(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    percentile: float,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> tuple[int, list[tuple[int, int, int, int, int, int, int]]]: ...
+def align_first_above(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    target_score: int,
+    in_order: bool = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> tuple[tuple[int, int, int, int, int, int, int] | None, int]: ...
+def align_topk_deduped(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    top_k: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> tuple[list[tuple[int, int, int, int, int, int, int]], int]: ...
+def align_topk_packed(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    top_k: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> NDArray[np.int64]: ...
+def align_topk_timed(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    top_k: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> tuple[list[tuple[int, int, int, int, int, int, int]], list[float]]: ...
 def align_topk_details(
     seq1: Sequence[int],
     seqs: Sequence[Sequence[int]],
     top_k: int = ...,
     match_score: int = ...,
     mismatch_score: int = ...,
-    gap_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+    stopwords: set[int] | frozenset[int] | None = ...,
+    stopword_match_score: int = ...,
+    min_query_coverage: float = ...,
+    drop_zero: bool = ...,
+    max_gaps: int | None = ...,
+    min_columns: int = ...,
+    sort_key: Literal["score", "matches", "percent_identity", "token_start"] = ...,
+    ref_weights: Sequence[float] | None = ...,
+    release_gil: bool = ...,
+) -> list[tuple[int, int, int, int, int, int, int]]: ...
+def align_pair_submatrix(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    matrix: NDArray[np.int32],
+    gap_score: int | tuple[int, int] = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_pair_bytes(
+    seq1: bytes,
+    seq2: bytes,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_pair_u64(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_topk_u64(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    top_k: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> list[tuple[int, int, int, int, int, int, int]]: ...
+def extend_right(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    query_pos: int,
+    token_pos: int,
+    band: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+) -> tuple[int, int, int, int, int]: ...
+def extend_left(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    query_pos: int,
+    token_pos: int,
+    band: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+) -> tuple[int, int, int, int, int]: ...
+def align_pair_anchored(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    anchor_i: int,
+    anchor_j: int,
+    band: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+) -> tuple[int, int, int, int, int]: ...
+def locate_in_query(
+    query: Sequence[int],
+    reference_window: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int]: ...
+def align_pair_convex(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    gap_open: int = ...,
+    gap_extend: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    wildcard: int | None = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_best_over_windows(
+    query: Sequence[int],
+    reference: Sequence[int],
+    window_len: int,
+    step: int,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> tuple[tuple[int, int, int, int, int, int], int]: ...
+def align_pair_chained(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    seed_len: int = ...,
+    max_gap: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+) -> tuple[int, int, int, int, int, int]: ...
+def align_overlap(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+) -> tuple[int, int, int]: ...
+def global_identity(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+) -> float: ...
+def align_variants_best(
+    variants: Sequence[Sequence[int]],
+    reference: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> tuple[tuple[int, int, int, int, int, int], int] | None: ...
+def align_queries_vs_reference(
+    queries: Sequence[Sequence[int]],
+    reference: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> list[tuple[int, int, int, int, int, int]]: ...
+def minhash_signatures(
+    seqs: Sequence[Sequence[int]],
+    kmer_size: int = ...,
+    num_hashes: int = ...,
+    num_threads: int | None = ...,
+) -> list[list[int]]: ...
+def align_topk_approx(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    signatures: Sequence[Sequence[int]],
+    top_k: int = ...,
+    candidate_budget: int = ...,
+    kmer_size: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
 ) -> list[tuple[int, int, int, int, int, int, int]]: ...
+def align_topk_regions(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    top_k: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> list[tuple[int, int, int, int, int, int, int]]: ...
+def align_pair_segmented(
+    seq1: Sequence[int],
+    corpus: Sequence[int],
+    boundaries: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+) -> tuple[int, int, int, int, int, int, int]: ...
+def align_pair_f64(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    match_score: float = ...,
+    mismatch_score: float = ...,
+    gap_score: float = ...,
+    gap_query: float | None = ...,
+    gap_ref: float | None = ...,
+    substitution: dict[tuple[int, int], float] | None = ...,
+) -> tuple[float, int, int, int, int, int]: ...
+def align_all_scores_into(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    out: NDArray[np.int32],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> None: ...
+def align_best_per_bucket(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    bucket_edges: Sequence[int],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> list[tuple[int, int, int, int, int, int, int] | None]: ...
+def align_best_with_margin(
+    seq1: Sequence[int],
+    seqs: Sequence[Sequence[int]],
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    num_threads: int | None = ...,
+) -> tuple[tuple[int, int, int, int, int, int, int] | None, int | None]: ...
+def align_pair_block_counts_details(
+    seq1: Sequence[int],
+    seq2: Sequence[int],
+    merge_gap: int = ...,
+    match_score: int = ...,
+    mismatch_score: int = ...,
+    gap_score: int | tuple[int, int] = ...,
+    wildcard: int | None = ...,
+    gap_query: int | None = ...,
+    gap_ref: int | None = ...,
+    run_bonus: int = ...,
+    end_gap_discount: int = ...,
+    mask: Sequence[tuple[int, int]] | None = ...,
+    inclusive_ends: bool = ...,
+) -> tuple[int, int, int, int, int, int, list[tuple[int, int, int]]]: ...
+
+class ReferenceIndex:
+    def __init__(
+        self,
+        seqs: Sequence[Sequence[int]],
+        kmer_size: int | None = ...,
+    ) -> None: ...
+    def __len__(self) -> int: ...
+    def align_topk(
+        self,
+        seq1: Sequence[int],
+        top_k: int = ...,
+        match_score: int = ...,
+        mismatch_score: int = ...,
+        gap_score: int | tuple[int, int] = ...,
+        wildcard: int | None = ...,
+        gap_query: int | None = ...,
+        gap_ref: int | None = ...,
+        run_bonus: int = ...,
+        end_gap_discount: int = ...,
+        num_threads: int | None = ...,
+        release_gil: bool = ...,
+    ) -> list[tuple[int, int, int, int, int, int, int]]: ...
+    def align_best(
+        self,
+        seq1: Sequence[int],
+        match_score: int = ...,
+        mismatch_score: int = ...,
+        gap_score: int | tuple[int, int] = ...,
+        wildcard: int | None = ...,
+        gap_query: int | None = ...,
+        gap_ref: int | None = ...,
+        run_bonus: int = ...,
+        end_gap_discount: int = ...,
+        num_threads: int | None = ...,
+        release_gil: bool = ...,
+    ) -> tuple[int, int, int, int, int, int, int] | None: ...
+    def candidates(self, seq1: Sequence[int]) -> list[int]: ...
+
+class IncrementalAligner:
+    def __init__(
+        self,
+        reference: Sequence[int],
+        match_score: int = ...,
+        mismatch_score: int = ...,
+        gap_score: int | tuple[int, int] = ...,
+        wildcard: int | None = ...,
+        gap_query: int | None = ...,
+        gap_ref: int | None = ...,
+        run_bonus: int = ...,
+    ) -> None: ...
+    def __len__(self) -> int: ...
+    def push_query_token(
+        self, token: int, release_gil: bool = ...
+    ) -> tuple[int, int, int, int, int, int]: ...
+    def best(self) -> tuple[int, int, int, int, int, int]: ...
+
+class AlignConfig:
+    band: int | None
+    min_score: int
+    min_matches: int
+    num_threads: int | None
+    first_max_only: bool
+    numeric_tolerance: int
+    tie_seed: int | None
+    reverse: bool
+    hamming_threshold: int
+    require_query_start_zero: bool
+    restart_floor: int
+    inclusive_ends: bool
+    release_gil: bool
+    def __init__(
+        self,
+        match_score: int = ...,
+        mismatch_score: int = ...,
+        gap_score: int | tuple[int, int] = ...,
+        wildcard: int | None = ...,
+        gap_query: int | None = ...,
+        gap_ref: int | None = ...,
+        run_bonus: int = ...,
+        end_gap_discount: int = ...,
+        separator: int | None = ...,
+        band: int | None = ...,
+        min_score: int = ...,
+        min_matches: int = ...,
+        num_threads: int | None = ...,
+        first_max_only: bool = ...,
+        canonical: Sequence[int] | None = ...,
+        numeric_tolerance: int = ...,
+        tie_seed: int | None = ...,
+        reverse: bool = ...,
+        hamming_threshold: int = ...,
+        cross_map: dict[int, int] | None = ...,
+        require_query_start_zero: bool = ...,
+        restart_floor: int = ...,
+        mask: Sequence[tuple[int, int]] | None = ...,
+        equiv: dict[int, int] | None = ...,
+        inclusive_ends: bool = ...,
+        release_gil: bool = ...,
+    ) -> None: ...
+
+class AlignStream:
+    def __init__(
+        self,
+        queries: Sequence[Sequence[int]],
+        seqs: Sequence[Sequence[int]],
+        match_score: int = ...,
+        mismatch_score: int = ...,
+        gap_score: int | tuple[int, int] = ...,
+        wildcard: int | None = ...,
+        gap_query: int | None = ...,
+        gap_ref: int | None = ...,
+        run_bonus: int = ...,
+        end_gap_discount: int = ...,
+        num_threads: int | None = ...,
+    ) -> None: ...
+    def __iter__(self) -> AlignStream: ...
+    def __next__(
+        self,
+    ) -> tuple[int, tuple[int, int, int, int, int, int, int] | None]: ...
+
+class AlignmentSummary:
+    score: int
+    matches: int
+    mismatches: int
+    query_gaps: int
+    ref_gaps: int
+    aligned_length: int
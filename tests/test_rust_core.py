@@ -0,0 +1,1115 @@
+"""Tests for the Rust extension API beyond the Python aligner parity surface."""
+
+import json
+import warnings
+from types import ModuleType
+
+import pytest
+
+from .conftest import requires_rust
+
+
+@requires_rust
+def test_align_best_json_matches_align_best_details(rust_core: ModuleType) -> None:
+    """Verify the JSON payload carries the same fields as align_best_details."""
+    claim = [1, 2, 3]
+    candidates = [[3, 4], [0, 1, 2, 3]]
+
+    payload = json.loads(rust_core.align_best_json(claim, candidates, 2, -1, -1))
+    details = rust_core.align_best_details(claim, candidates, 2, -1, -1)
+
+    assert details is not None
+    keys = (
+        "score",
+        "index",
+        "token_start",
+        "token_end",
+        "query_start",
+        "query_end",
+        "matches",
+    )
+    assert tuple(payload[key] for key in keys) == details
+    assert payload["percent_identity"] == 100.0
+
+
+@requires_rust
+def test_align_best_json_empty_candidates_is_null(rust_core: ModuleType) -> None:
+    """Verify an empty candidate list serializes as JSON null."""
+    assert json.loads(rust_core.align_best_json([1], [], 2, -1, -1)) is None
+
+
+@requires_rust
+def test_score_overflow_raises_value_error(rust_core: ModuleType) -> None:
+    """Verify an overflowing scoring scheme raises instead of aborting."""
+    with pytest.raises(ValueError, match="overflow"):
+        rust_core.align_pair([1, 1], [1, 1], 2**31 - 1, -1, -1)
+    with pytest.raises(ValueError, match="overflow"):
+        rust_core.align_topk_details([1, 1], [[1, 1]], 1, 2**31 - 1, -1, -1)
+
+
+@requires_rust
+def test_non_positive_match_score_is_rejected(rust_core: ModuleType) -> None:
+    """Verify schemes that can never score positively raise ValueError."""
+    for match_score in (0, -2):
+        with pytest.raises(ValueError, match="match_score must be positive"):
+            rust_core.align_pair([1, 2], [1, 2], match_score, -1, -1)
+        with pytest.raises(ValueError, match="match_score must be positive"):
+            rust_core.align_best_details([1, 2], [[1, 2]], match_score, -1, -1)
+
+
+@requires_rust
+def test_non_negative_penalty_warns(rust_core: ModuleType) -> None:
+    """Verify degenerate but usable schemes warn and still align."""
+    with pytest.warns(UserWarning, match="mismatch_score"):
+        result = rust_core.align_pair([1, 2], [1, 2], 2, 0, -1)
+    assert result == (4, 0, 2)
+
+
+@requires_rust
+def test_valid_scheme_does_not_warn(rust_core: ModuleType) -> None:
+    """Verify the default scheme is accepted silently."""
+    with warnings.catch_warnings():
+        warnings.simplefilter("error")
+        assert rust_core.align_pair([1, 2], [1, 2], 2, -1, -1) == (4, 0, 2)
+
+
+@requires_rust
+def test_reference_index_matches_stateless_functions(rust_core: ModuleType) -> None:
+    """Verify repeated queries against a ReferenceIndex match stateless calls."""
+    candidates = [[3, 4], [1, 2, 1, 2], [1, 2], [0, 1, 2, 3]]
+    index = rust_core.ReferenceIndex(candidates, kmer_size=2)
+    assert len(index) == len(candidates)
+
+    for claim in ([1, 2], [2, 3, 4]):
+        assert index.align_topk(claim, 3) == rust_core.align_topk_details(
+            claim, candidates, 3
+        )
+        assert index.align_best(claim) == rust_core.align_best_details(
+            claim, candidates
+        )
+
+    assert index.candidates([2, 3, 4]) == [0, 3]
+
+
+@requires_rust
+def test_score_preset_round_trips_into_align_pair(rust_core: ModuleType) -> None:
+    """Verify presets return usable score tuples and unknown names are rejected."""
+    assert rust_core.score_preset("citation") == (2, -1, -1)
+    match_score, mismatch_score, gap_score = rust_core.score_preset("strict")
+    score, _, _ = rust_core.align_pair(
+        [1, 2, 3], [1, 2, 3], match_score, mismatch_score, gap_score
+    )
+    assert score == 3 * match_score
+
+    with pytest.raises(ValueError, match="unknown preset"):
+        rust_core.score_preset("fuzzy")
+
+
+@requires_rust
+@pytest.mark.parametrize(("seq1", "seq2"), [([], [1, 2]), ([1, 2], []), ([], [])])
+def test_align_pair_checked_flags_empty_input(
+    rust_core: ModuleType, seq1: list[int], seq2: list[int]
+) -> None:
+    """Verify empty input returns None or raises, depending on on_empty."""
+    assert rust_core.align_pair_checked(seq1, seq2) is None
+    with pytest.raises(ValueError, match="empty"):
+        rust_core.align_pair_checked(seq1, seq2, on_empty="raise")
+
+
+@requires_rust
+def test_align_pair_checked_keeps_no_match_distinct(rust_core: ModuleType) -> None:
+    """Verify a genuine no-match on non-empty input is a zero result, not None."""
+    result = rust_core.align_pair_checked([1], [2], on_empty="raise")
+    assert result == (0, 0, 0, 0, 0, 0)
+
+
+@requires_rust
+def test_align_pair_u64_keeps_wide_ids_distinct(rust_core: ModuleType) -> None:
+    """Verify ids above 2**32 do not match their truncated u32 value."""
+    wide = (1 << 32) | 5
+    score, *_ = rust_core.align_pair_u64([wide, 7, 8], [5, 7, 8])
+    assert score == 4
+
+
+@requires_rust
+def test_align_pair_details_equiv_matches_aliases(rust_core: ModuleType) -> None:
+    """Verify ids mapped to the same representative align as matches."""
+    score, *_ = rust_core.align_pair_details([1, 10, 2], [1, 11, 2])
+    assert score == 3
+    score, _, _, _, _, matches = rust_core.align_pair_details(
+        [1, 10, 2], [1, 11, 2], equiv={11: 10}
+    )
+    assert (score, matches) == (6, 3)
+
+
+@requires_rust
+def test_align_pair_banded_flags_too_narrow_band(rust_core: ModuleType) -> None:
+    """Verify a band that cuts off the optimum is flagged and a wide one is not."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seq2 = [9, 1, 2, 3, 8, 4, 5, 6, 9]
+    full = rust_core.align_pair_details(seq1, seq2)
+
+    narrow, clipped = rust_core.align_pair_banded(seq1, seq2, 1)
+    assert clipped
+    assert narrow[0] < full[0]
+
+    wide, clipped = rust_core.align_pair_banded(seq1, seq2, 4)
+    assert not clipped
+    assert wide == full
+
+
+@requires_rust
+def test_align_config_matches_positional_calls(rust_core: ModuleType) -> None:
+    """Verify a config with non-default scores equals the keyword call."""
+    seq1 = [1, 2, 3, 4]
+    seqs = [[1, 2, 3, 4], [9, 1, 2, 9], [1, 2, 9, 4]]
+    config = rust_core.AlignConfig(match_score=3, gap_score=-2, num_threads=1)
+    for seq2 in seqs:
+        assert rust_core.align_pair_cfg(seq1, seq2, config) == (
+            rust_core.align_pair_details(seq1, seq2, match_score=3, gap_score=-2)
+        )
+    assert rust_core.align_topk_cfg(seq1, seqs, config, top_k=3) == (
+        rust_core.align_topk_details(seq1, seqs, top_k=3, match_score=3, gap_score=-2)
+    )
+
+    filtered = rust_core.AlignConfig(match_score=3, gap_score=-2, min_matches=3)
+    kept = rust_core.align_topk_cfg(seq1, seqs, filtered, top_k=3)
+    assert [item[1] for item in kept] == [0, 2]
+
+
+@requires_rust
+def test_align_pair_all_max_returns_tied_regions(rust_core: ModuleType) -> None:
+    """Verify both equally scoring copies of the query are reported."""
+    seq1 = [1, 2, 3]
+    seq2 = [1, 2, 3, 9, 9, 1, 2, 3]
+    all_max = rust_core.align_pair_all_max(seq1, seq2)
+    assert [(item[0], item[1], item[2]) for item in all_max] == [(6, 0, 3), (6, 5, 8)]
+    assert all_max[0] == rust_core.align_pair_details(seq1, seq2)
+
+
+@requires_rust
+def test_align_config_canonical_folds_variants(rust_core: ModuleType) -> None:
+    """Verify ids sharing a canonical entry align as matches."""
+    config = rust_core.AlignConfig(canonical=[0, 1, 0, 1])
+    *_, matches = rust_core.align_pair_details([2, 3, 4], [0, 1, 4])
+    assert matches == 1
+    score, *_, matches = rust_core.align_pair_cfg([2, 3, 4], [0, 1, 4], config)
+    assert (score, matches) == (6, 3)
+    assert rust_core.align_topk_cfg([2, 3, 4], [[9], [0, 1, 4]], config) == [
+        (6, 1, 0, 3, 0, 3, 3)
+    ]
+
+
+@requires_rust
+def test_align_config_mask_equiv_and_inclusive_ends(rust_core: ModuleType) -> None:
+    """Verify the pair keywords on a config equal the align_pair_details call."""
+    seq1 = [1, 2]
+    seq2 = [1, 2, 9, 5, 2]
+    options = {"mask": [(0, 2)], "equiv": {5: 1}, "inclusive_ends": True}
+    config = rust_core.AlignConfig(**options)
+    assert config.inclusive_ends
+    details = rust_core.align_pair_cfg(seq1, seq2, config)
+    assert details == rust_core.align_pair_details(seq1, seq2, **options)
+    assert details == (4, 3, 4, 0, 1, 2)
+    assert rust_core.align_topk_cfg(seq1, [seq2], config) == [(4, 0, 3, 4, 0, 1, 2)]
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(equiv={5: 1}, numeric_tolerance=1)
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(mask=[(0, 2)], restart_floor=1)
+
+
+@requires_rust
+def test_align_pair_clips_counts_unaligned_ends(rust_core: ModuleType) -> None:
+    """Verify clip counts on a query whose middle alone is quoted."""
+    query = [7, 8, 1, 2, 3, 9]
+    reference = [0, 1, 2, 3, 0, 0]
+    details, clips = rust_core.align_pair_clips(query, reference)
+    assert details == rust_core.align_pair_details(query, reference)
+    assert clips == (2, 1, 1, 2)
+
+
+@requires_rust
+def test_align_topk_approx_recovers_best_match(rust_core: ModuleType) -> None:
+    """Verify a generous candidate budget finds the exact best reference."""
+    seqs = [[(n * 7 + t * 13) % 500 for t in range(30)] for n in range(200)]
+    query = list(range(1000, 1012))
+    seqs[137][8:8] = query
+
+    signatures = rust_core.minhash_signatures(seqs, kmer_size=3, num_hashes=64)
+    approx = rust_core.align_topk_approx(
+        query, seqs, signatures, top_k=1, candidate_budget=20, kmer_size=3
+    )
+    assert approx == rust_core.align_topk_details(query, seqs, top_k=1)
+    assert approx[0][1] == 137
+
+
+@requires_rust
+def test_count_optimal_alignments_counts_tied_gap_placements(
+    rust_core: ModuleType,
+) -> None:
+    """Verify both placements of an ambiguous gap are counted."""
+    assert rust_core.count_optimal_alignments([1, 2, 3], [1, 2, 2, 3]) == 2
+    assert rust_core.count_optimal_alignments([1, 2, 3], [1, 2, 3]) == 1
+    assert rust_core.count_optimal_alignments([1, 2, 3], [9]) == 0
+
+
+@requires_rust
+def test_count_optimal_alignments_counts_repeats_up_to_cap(
+    rust_core: ModuleType,
+) -> None:
+    """Verify each repeat of the query counts once and cap clamps the total."""
+    assert rust_core.count_optimal_alignments([1, 2], [1, 2] * 3) == 3
+    assert rust_core.count_optimal_alignments([1, 2], [7, 1, 2, 8]) == 1
+    assert rust_core.count_optimal_alignments([1, 2], [1, 2] * 3, cap=2) == 2
+    masked = rust_core.count_optimal_alignments([1, 2], [1, 2] * 3, mask=[(0, 2)])
+    assert masked == 2
+    with pytest.raises(ValueError):
+        rust_core.count_optimal_alignments([1, 2], [1, 2], cap=0)
+
+
+@requires_rust
+def test_count_optimal_alignments_default_cap(rust_core: ModuleType) -> None:
+    """Verify the default cap saturates on repeats and a unique match is 1."""
+    seq1, seq2 = [1, 2] * 22, [1, 2, 2] * 22
+    assert rust_core.count_optimal_alignments(seq1, seq2, cap=None) == 2**21
+    assert rust_core.count_optimal_alignments(seq1, seq2) == 1_000_000
+    assert rust_core.count_optimal_alignments(seq1, seq2, cap=10) == 10
+    assert rust_core.count_optimal_alignments([1, 2, 3], [7, 1, 2, 3, 8]) == 1
+
+
+@requires_rust
+def test_inclusive_ends_are_one_less_than_half_open(rust_core: ModuleType) -> None:
+    """Verify inclusive and half-open coordinates describe the same alignment."""
+    seq1 = [1, 2, 3]
+    seq2 = [0, 1, 2, 3, 0, 1]
+    half_open = rust_core.align_pair_blocks_details(seq1, seq2)
+    inclusive = rust_core.align_pair_blocks_details(seq1, seq2, inclusive_ends=True)
+    score, token_start, token_end, query_start, query_end, matches, blocks = half_open
+    assert inclusive == (
+        score,
+        token_start,
+        token_end - 1,
+        query_start,
+        query_end - 1,
+        matches,
+        [(start, end - 1) for start, end in blocks],
+    )
+    assert seq2[token_start:token_end] == seq2[inclusive[1] : inclusive[2] + 1]
+
+    empty = rust_core.align_pair_details(seq1, [9], inclusive_ends=True)
+    assert empty == (0, 0, 0, 0, 0, 0)
+
+
+@requires_rust
+def test_align_queries_vs_reference_matches_pair_calls(rust_core: ModuleType) -> None:
+    """Verify each query's result equals a single align_pair_details call."""
+    reference = [5, 1, 2, 3, 9, 4, 5, 6, 1, 2]
+    queries = [[1, 2, 3], [4, 6], [], [7, 8], [2, 9, 4, 5]]
+    results = rust_core.align_queries_vs_reference(queries, reference, num_threads=2)
+    assert results == [rust_core.align_pair_details(q, reference) for q in queries]
+
+
+@requires_rust
+def test_align_config_first_max_only_on_repeats(rust_core: ModuleType) -> None:
+    """Verify first_max_only returns the first of many tied copies."""
+    seq1 = [1, 2]
+    seq2 = [1, 2] * 50
+    config = rust_core.AlignConfig(first_max_only=True)
+    assert config.first_max_only
+    assert rust_core.align_pair_cfg(seq1, seq2, config) == (4, 0, 2, 0, 2, 2)
+
+
+@requires_rust
+def test_extend_left_and_right_compose_around_seed(rust_core: ModuleType) -> None:
+    """Verify both extensions plus the seed reproduce the full alignment."""
+    seq1 = [1, 2, 3, 4, 5, 6, 7]
+    seq2 = [9, 1, 2, 3, 4, 8, 5, 6, 7, 9]
+    left = rust_core.extend_left(seq1, seq2, 3, 4, band=2)
+    right = rust_core.extend_right(seq1, seq2, 4, 5, band=2)
+    assert left == (6, 1, 4, 0, 3)
+    assert right == (5, 5, 9, 4, 7)
+
+    score, token_start, token_end, query_start, query_end, _ = (
+        rust_core.align_pair_details(seq1, seq2)
+    )
+    assert left[0] + 2 + right[0] == score
+    assert (left[1], right[2], left[3], right[4]) == (
+        token_start,
+        token_end,
+        query_start,
+        query_end,
+    )
+
+
+@requires_rust
+def test_gap_score_accepts_scalar_or_open_extend_pair(rust_core: ModuleType) -> None:
+    """Verify a scalar gap stays linear and an (open, extend) pair is affine."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seq2 = [1, 2, 3, 9, 9, 9, 4, 5, 6]
+    linear = rust_core.align_pair_details(seq1, seq2, gap_score=-1)
+    assert linear == (9, 0, 9, 0, 6, 6)
+    assert rust_core.align_pair_details(seq1, seq2, gap_score=(-1, -1)) == linear
+
+    # One three-token gap: -3 to open, then -1 for each further token.
+    affine = rust_core.align_pair_details(seq1, seq2, gap_score=(-3, -1))
+    assert affine == (7, 0, 9, 0, 6, 6)
+
+    # Opening costs more than the second half is worth.
+    costly = rust_core.align_pair_details(seq1, seq2, gap_score=(-6, -1))
+    assert costly == (6, 0, 3, 0, 3, 3)
+
+    with pytest.raises(ValueError):
+        rust_core.align_pair_details(seq1, seq2, gap_score=(1, -1))
+
+
+@requires_rust
+def test_score_bounds_bracket_the_gapped_score(rust_core: ModuleType) -> None:
+    """Verify the cheap bounds contain align_pair's score."""
+    seq1 = [1, 2, 3, 4]
+    seq2 = [1, 2, 9, 3, 4]
+    assert rust_core.score_bounds(seq1, seq2) == (4, 8)
+    score = rust_core.align_pair_details(seq1, seq2)[0]
+    assert 4 <= score <= 8
+    assert rust_core.score_bounds([], seq2) == (0, 0)
+
+
+@requires_rust
+def test_align_pair_blocks_details_query_frame(rust_core: ModuleType) -> None:
+    """Verify frame selects which sequence the match blocks index."""
+    seq1 = [1, 2, 9, 3, 4, 5, 6]
+    seq2 = [1, 2, 3, 4, 8, 5, 6]
+    reference = rust_core.align_pair_blocks_details(seq1, seq2)
+    query = rust_core.align_pair_blocks_details(seq1, seq2, frame="query")
+    assert reference[:6] == query[:6] == (10, 0, 7, 0, 7, 6)
+    assert reference[6] == [(0, 4), (5, 7)]
+    assert query[6] == [(0, 2), (3, 7)]
+
+    with pytest.raises(ValueError):
+        rust_core.align_pair_blocks_details(seq1, seq2, frame="both")
+
+
+@requires_rust
+def test_align_stream_yields_every_batch_result(rust_core: ModuleType) -> None:
+    """Verify the streamed results, sorted by query index, equal the batch."""
+    seqs = [[n % 5, 1, 2, n % 3, 3] for n in range(40)]
+    queries = [[n % 4, n % 5, 3] for n in range(30)] + [[]]
+    batch = rust_core.align_best_batch(queries, seqs)
+    streamed = list(rust_core.AlignStream(queries, seqs, num_threads=3))
+    assert sorted(index for index, _ in streamed) == list(range(len(queries)))
+    assert [best for _, best in sorted(streamed, key=lambda item: item[0])] == batch
+    assert batch[0] == rust_core.align_best_details(queries[0], seqs)
+
+
+@requires_rust
+def test_min_query_coverage_rejects_partial_overlap(rust_core: ModuleType) -> None:
+    """Verify a high-scoring partial overlap fails a 0.9 coverage threshold."""
+    query = list(range(1, 11))
+    partial = [40, 1, 2, 3, 4, 5, 6, 7, 8, 41]
+    near_full = [1, 2, 77, 3, 4, 77, 5, 6, 77, 7, 8, 9, 99]
+    seqs = [partial, near_full]
+    assert rust_core.align_best_details(query, seqs)[1] == 0
+
+    best = rust_core.align_best_details(query, seqs, min_query_coverage=0.9)
+    assert best is not None
+    assert (best[0], best[1], best[6]) == (15, 1, 9)
+    assert rust_core.align_best(query, [partial], min_query_coverage=0.9) is None
+    top = rust_core.align_topk_details(query, seqs, top_k=2, min_query_coverage=0.9)
+    assert [item[1] for item in top] == [1]
+
+    with pytest.raises(ValueError):
+        rust_core.align_best(query, seqs, min_query_coverage=1.5)
+
+
+@requires_rust
+def test_align_pair_is_match_flags_no_overlap(rust_core: ModuleType) -> None:
+    """Verify is_match separates no overlap from a match starting at 0."""
+    details, is_match = rust_core.align_pair_is_match([1, 2, 3], [7, 8, 9])
+    assert details == (0, 0, 0, 0, 0, 0)
+    assert not is_match
+
+    details, is_match = rust_core.align_pair_is_match([1, 2, 3], [1, 2, 9])
+    assert details == (4, 0, 2, 0, 2, 2)
+    assert is_match
+    assert not rust_core.align_pair_is_match([1, 2, 3], [1, 2, 9], floor=4)[1]
+
+
+@requires_rust
+def test_align_pair_bytes_matches_list_of_byte_ids(rust_core: ModuleType) -> None:
+    """Verify byte input scores exactly like the same ids as a list."""
+    seq1 = b"the quick fox"
+    seq2 = b"a quick brown fox"
+    expected = rust_core.align_pair_details(list(seq1), list(seq2))
+    assert rust_core.align_pair_bytes(seq1, seq2) == expected
+    assert rust_core.align_pair_bytes(seq1, seq2)[0] > 0
+    assert rust_core.align_pair_bytes(b"", seq2) == (0, 0, 0, 0, 0, 0)
+
+
+@requires_rust
+def test_align_pair_diagonal_of_shifted_match(rust_core: ModuleType) -> None:
+    """Verify the diagonal is token_start minus query_start."""
+    seq1 = [7, 1, 2, 3]
+    seq2 = [9, 9, 9, 9, 1, 2, 3]
+    details, diagonal = rust_core.align_pair_diagonal(seq1, seq2)
+    assert details == (6, 4, 7, 1, 4, 3)
+    assert diagonal == 3
+    assert rust_core.align_pair_diagonal([7, 7, 7, 1, 2], [1, 2, 9])[1] == -3
+
+
+@requires_rust
+def test_numeric_tolerance_gives_near_years_partial_credit(
+    rust_core: ModuleType,
+) -> None:
+    """Verify a near-numeric token scores between a match and a mismatch."""
+    query = [1, 1999, 3]
+    reference = [1, 2000, 3]
+    config = rust_core.AlignConfig(numeric_tolerance=2)
+    assert config.numeric_tolerance == 2
+    assert rust_core.align_pair_details(query, reference)[0] == 3
+    details = rust_core.align_pair_cfg(query, reference, config)
+    assert details == (5, 0, 3, 0, 3, 2)
+    far = rust_core.align_pair_cfg([1, 1990, 3], reference, config)
+    assert far[0] == 3
+
+
+@requires_rust
+def test_align_best_blocks_details_matches_pair_blocks(rust_core: ModuleType) -> None:
+    """Verify the winner's blocks equal align_pair_blocks_details on it."""
+    seq1 = [1, 2, 3, 4, 5]
+    seqs = [[1, 2, 9], [7, 1, 2, 9, 9, 3, 4, 5], [3, 4]]
+    best = rust_core.align_best_blocks_details(seq1, seqs)
+    assert best is not None
+    assert best[:7] == rust_core.align_best_details(seq1, seqs)
+    assert best[1] == 1
+    assert best[7] == rust_core.align_pair_blocks_details(seq1, seqs[1])[6]
+    assert rust_core.align_best_blocks_details(seq1, []) is None
+
+
+@requires_rust
+def test_align_topk_details_drop_zero(rust_core: ModuleType) -> None:
+    """Verify drop_zero turns all-zero results into an empty list."""
+    seqs = [[4, 5], [6, 7, 8], [1, 2]]
+    disjoint = rust_core.align_topk_details([9, 10], seqs, top_k=3)
+    assert [item[0] for item in disjoint] == [0, 0, 0]
+    assert rust_core.align_topk_details([9, 10], seqs, top_k=3, drop_zero=True) == []
+
+    kept = rust_core.align_topk_details([1, 2], seqs, top_k=3, drop_zero=True)
+    assert [(item[0], item[1]) for item in kept] == [(4, 2)]
+
+
+@requires_rust
+def test_align_pair_anchored_passes_through_anchor(rust_core: ModuleType) -> None:
+    """Verify the anchored result contains the anchor cell, not the optimum."""
+    seq1 = [1, 2, 3, 4]
+    seq2 = [1, 2, 3, 4, 9, 5, 2, 6]
+    assert rust_core.align_pair_details(seq1, seq2)[:3] == (8, 0, 4)
+
+    score, token_start, token_end, query_start, query_end = (
+        rust_core.align_pair_anchored(seq1, seq2, 1, 6)
+    )
+    assert score == 2
+    assert query_start <= 1 < query_end
+    assert token_start <= 6 < token_end
+    assert rust_core.align_pair_anchored(seq1, seq2, 1, 1) == (8, 0, 4, 0, 4)
+
+    with pytest.raises(ValueError):
+        rust_core.align_pair_anchored(seq1, seq2, 4, 0)
+
+
+@requires_rust
+def test_align_overlap_reports_suffix_prefix_join(rust_core: ModuleType) -> None:
+    """Verify a three-token suffix/prefix join is reported as length 3."""
+    assert rust_core.align_overlap([5, 6, 7, 1, 2, 3], [1, 2, 3, 8, 9]) == (6, 3, 3)
+    assert rust_core.align_overlap([1, 2, 3, 9], [1, 2, 3]) == (5, 4, 3)
+    assert rust_core.align_overlap([1, 2], [3, 4]) == (0, 0, 0)
+
+
+@requires_rust
+def test_align_best_diagonal_prefilter_matches_full_dp(rust_core: ModuleType) -> None:
+    """Verify the prefilter finds the full-DP alignment on a dominant diagonal."""
+    seq1 = list(range(1, 21))
+    seq2 = [*range(100, 107), *range(1, 10), 50, *range(10, 21), *range(200, 210)]
+    seq2[10] = 60
+    full = rust_core.align_pair_details(seq1, seq2)
+    assert rust_core.align_best_diagonal_prefilter(seq1, seq2, probes=2, band=2) == full
+    assert rust_core.align_best_diagonal_prefilter(seq1, seq2, band=0)[0] < full[0]
+    assert rust_core.align_best_diagonal_prefilter(seq1, seq2, probes=0)[0] == 0
+
+
+@requires_rust
+def test_align_pair_end_score_equals_local_score(rust_core: ModuleType) -> None:
+    """Verify the end cell's DP score equals the reported local score."""
+    for seq1, seq2 in [([1, 2], [1, 2, 1, 2]), ([1, 2, 3], [1, 9, 2, 3]), ([1], [2])]:
+        details, end_score = rust_core.align_pair_end_score(seq1, seq2)
+        assert details == rust_core.align_pair_details(seq1, seq2)
+        assert end_score == details[0]
+
+
+@requires_rust
+def test_max_gaps_rejects_gap_heavy_reference(rust_core: ModuleType) -> None:
+    """Verify a gap budget passes over a higher-scoring gappy reference."""
+    seq1 = list(range(1, 9))
+    seqs = [[1, 2, 90, 3, 4, 91, 5, 6, 92, 7, 8], [3, 4, 5, 6, 7, 8]]
+    assert rust_core.align_best(seq1, seqs) == (13, 0, 0, 11)
+    assert rust_core.align_best(seq1, seqs, max_gaps=2) == (12, 1, 0, 6)
+    assert rust_core.align_best_details(seq1, seqs, max_gaps=3)[:2] == (13, 0)
+    top = rust_core.align_topk_details(seq1, seqs, top_k=2, max_gaps=0)
+    assert [(item[0], item[1]) for item in top] == [(12, 1)]
+
+
+@requires_rust
+def test_min_columns_rejects_short_exact_match(rust_core: ModuleType) -> None:
+    """Verify a two-column perfect match gives way to a longer alignment."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seqs = [[1, 2], [2, 3, 99, 5, 6]]
+    top = rust_core.align_topk_details(seq1, seqs, top_k=2)
+    assert [(item[0], item[1]) for item in top] == [(7, 1), (4, 0)]
+    top = rust_core.align_topk_details(seq1, seqs, top_k=2, min_columns=3)
+    assert [(item[0], item[1]) for item in top] == [(7, 1)]
+    assert rust_core.align_best(seq1, [[1, 2]], min_columns=3) is None
+
+
+@requires_rust
+def test_align_pair_summary_counts_every_column(rust_core: ModuleType) -> None:
+    """Verify the operation counts add up to the aligned length."""
+    seq1 = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+    seq2 = [1, 2, 30, 4, 5, 50, 6, 7, 9, 10]
+    summary = rust_core.align_pair_summary(seq1, seq2)
+    assert summary.score == rust_core.align_pair_details(seq1, seq2)[0]
+    assert (summary.matches, summary.mismatches) == (8, 1)
+    assert (summary.query_gaps, summary.ref_gaps) == (1, 1)
+    total = summary.matches + summary.mismatches
+    assert total + summary.query_gaps + summary.ref_gaps == summary.aligned_length
+
+
+@requires_rust
+def test_min_anchor_trims_weak_leading_block(rust_core: ModuleType) -> None:
+    """Verify a lone leading match is trimmed and the start advances."""
+    seq1 = [1, 7, 2, 3, 4, 5]
+    seq2 = [1, 70, 2, 3, 4, 5]
+    full = rust_core.align_pair_blocks_details(seq1, seq2)
+    assert full == (9, 0, 6, 0, 6, 5, [(0, 1), (2, 6)])
+    trimmed = rust_core.align_pair_blocks_details(seq1, seq2, min_anchor=2)
+    assert trimmed == (9, 2, 6, 2, 6, 4, [(2, 6)])
+
+
+@requires_rust
+def test_align_pair_submatrix_aligns_distinct_ids(rust_core: ModuleType) -> None:
+    """Verify a dense substitution matrix lets two different ids align."""
+    np = pytest.importorskip("numpy")
+    matrix = np.array([[2, -1, -1], [-1, 2, 1], [-1, -1, 2]], dtype=np.int32)
+    assert rust_core.align_pair_submatrix([0, 1], [0, 2], matrix) == (3, 0, 2, 0, 2, 1)
+    assert rust_core.align_pair_submatrix([0, 2], [0, 1], matrix)[0] == 2
+
+    with pytest.raises(ValueError):
+        rust_core.align_pair_submatrix([0, 3], [0, 1], matrix)
+
+
+@requires_rust
+def test_tie_seed_is_reproducible(rust_core: ModuleType) -> None:
+    """Verify a seeded tie-break repeats per seed and varies across seeds."""
+    seq1 = [1, 2]
+    seq2 = [1, 2] * 8
+    for seed in range(8):
+        config = rust_core.AlignConfig(tie_seed=seed)
+        first = rust_core.align_pair_cfg(seq1, seq2, config)
+        assert first == rust_core.align_pair_cfg(seq1, seq2, config)
+        assert first[0] == 4
+    starts = {
+        rust_core.align_pair_cfg(seq1, seq2, rust_core.AlignConfig(tie_seed=seed))[1]
+        for seed in range(32)
+    }
+    assert len(starts) > 1
+    assert rust_core.align_pair_details(seq1, seq2)[1] == 0
+
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(tie_seed=1, first_max_only=True)
+
+
+@requires_rust
+def test_merge_alignments_joins_adjacent_windows(rust_core: ModuleType) -> None:
+    """Verify two adjacent window alignments merge into one span."""
+    left = (12, 4, 10, 0, 6, 6)
+    right = (12, 10, 16, 6, 12, 6)
+    assert rust_core.merge_alignments(left, right) == (24, 4, 16, 0, 12, 12)
+    assert rust_core.merge_alignments(right, left) == (24, 4, 16, 0, 12, 12)
+
+    with pytest.raises(ValueError):
+        rust_core.merge_alignments(left, (4, 14, 16, 10, 12, 2))
+
+
+@requires_rust
+def test_align_topk_timed_times_every_reference(rust_core: ModuleType) -> None:
+    """Verify timings cover every reference and leave the ranking unchanged."""
+    seq1 = [i % 7 for i in range(200)]
+    seqs = [[i % 5 for i in range(20)], [i % 11 for i in range(5000)], seq1]
+    top, seconds = rust_core.align_topk_timed(seq1, seqs, top_k=2)
+    assert top == rust_core.align_topk_details(seq1, seqs, top_k=2)
+    assert len(seconds) == len(seqs)
+    assert seconds[1] > seconds[0]
+
+
+@requires_rust
+def test_analyze_tokens_reports_id_density(rust_core: ModuleType) -> None:
+    """Verify id statistics on dense and sparse inputs."""
+    assert rust_core.analyze_tokens([[0, 2, 1], [3, 0]]) == (4, 0, 3, True)
+    assert rust_core.analyze_tokens([[7, 1_000_000], [7, 42]]) == (
+        3,
+        7,
+        1_000_000,
+        False,
+    )
+    assert rust_core.analyze_tokens([[]]) == (0, None, None, True)
+
+
+@requires_rust
+def test_align_variants_best_prefers_longer_match(rust_core: ModuleType) -> None:
+    """Verify a longer variant that matches more beats a shorter one."""
+    reference = [9, 1, 2, 3, 4, 5, 9]
+    variants = [[1, 2, 3], [1, 2, 3, 4, 5], [1, 2, 3, 7, 8]]
+    details, index = rust_core.align_variants_best(variants, reference)
+    assert index == 1
+    assert details == rust_core.align_pair_details(variants[1], reference)
+    assert details[:3] == (10, 1, 6)
+    assert rust_core.align_variants_best([], reference) is None
+
+
+@requires_rust
+def test_max_blocks_caps_interleaved_blocks(rust_core: ModuleType) -> None:
+    """Verify capped blocks stay within the limit and cover every match."""
+    seq1 = list(range(60))
+    seq2 = [100 + i if i % 3 == 2 else i for i in range(60)]
+    blocks = rust_core.align_pair_blocks_details(seq1, seq2)[6]
+    assert len(blocks) == 20
+    capped = rust_core.align_pair_blocks_details(seq1, seq2, max_blocks=5)[6]
+    assert len(capped) == 5
+    for start, end in blocks:
+        assert any(lo <= start and end <= hi for lo, hi in capped)
+
+
+@requires_rust
+def test_global_identity_counts_end_gaps(rust_core: ModuleType) -> None:
+    """Verify global identity divides matches by the full aligned length."""
+    seq1 = list(range(1, 11))
+    seq2 = seq1[:4] + [99] + seq1[5:]
+    assert rust_core.global_identity(seq1, seq2) == pytest.approx(0.9)
+    assert rust_core.global_identity([1, 2, 3], [1, 2, 3, 4, 5]) == pytest.approx(0.6)
+    assert rust_core.global_identity(seq1, seq1) == 1.0
+    with pytest.raises(ValueError):
+        rust_core.global_identity(seq1, seq2, gap_score=(-3, -1))
+
+
+@requires_rust
+def test_align_all_scores_into_reuses_buffer(rust_core: ModuleType) -> None:
+    """Verify a reused score buffer is overwritten correctly on each call."""
+    np = pytest.importorskip("numpy")
+    seqs = [[1, 2, 3, 4], [5, 6, 7], [], [3, 4, 5, 6]]
+    out = np.full(len(seqs), -7, dtype=np.int32)
+    for query in ([1, 2, 3], [4, 5, 6, 7]):
+        rust_core.align_all_scores_into(query, seqs, out)
+        expected = [rust_core.align_pair_details(query, seq)[0] for seq in seqs]
+        assert out.tolist() == expected
+    assert out.tolist() == [2, 6, 0, 6]
+    with pytest.raises(ValueError):
+        rust_core.align_all_scores_into([1], seqs, out[:3])
+
+
+@requires_rust
+def test_align_config_separator_breaks_alignment(rust_core: ModuleType) -> None:
+    """Verify a separator token confines a match to one side of it."""
+    seq1 = [1, 2, 3, 4, 5, 6, 7]
+    seq2 = [1, 2, 3, 99, 4, 5, 6, 7]
+    assert rust_core.align_pair_details(seq1, seq2)[:3] == (13, 0, 8)
+    config = rust_core.AlignConfig(separator=99)
+    assert rust_core.align_pair_cfg(seq1, seq2, config) == (8, 4, 8, 3, 7, 4)
+
+
+@requires_rust
+def test_align_best_per_bucket_picks_top_in_range(rust_core: ModuleType) -> None:
+    """Verify each bucket's representative is its highest-scoring reference."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seqs = [[1], [1, 2, 3, 4], [9, 5, 6], seq1, [2, 3, 4, 9]]
+    best = rust_core.align_best_per_bucket(seq1, seqs, [1, 5, 9, 12, 20])
+    assert [item and item[:2] for item in best] == [(4, 2), (8, 1), None, (12, 3)]
+    assert best[1] == (8, 1, *rust_core.align_pair_details(seq1, seqs[1])[1:])
+    with pytest.raises(ValueError):
+        rust_core.align_best_per_bucket(seq1, seqs, [5, 5])
+
+
+@requires_rust
+def test_align_pair_reverse_reports_forward_coordinates(rust_core: ModuleType) -> None:
+    """Verify reversed alignment maps its coordinates back to the forward frame."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seq2 = [8, 1, 2, 3, 9, 4, 5, 6, 7, 7]
+    forward = rust_core.align_pair_details(seq1, seq2)
+    assert forward == (11, 1, 8, 0, 6, 6)
+    config = rust_core.AlignConfig(reverse=True)
+    assert config.reverse
+    assert rust_core.align_pair_cfg(seq1, seq2, config) == forward
+    tied = rust_core.align_pair_cfg([1, 2], [1, 2, 9, 1, 2], config)
+    assert tied == (4, 3, 5, 0, 2, 2)
+
+
+@requires_rust
+def test_locate_in_query_matches_pair_details(rust_core: ModuleType) -> None:
+    """Verify the located query span equals the full alignment's."""
+    query = [7, 7, 1, 2, 3, 9, 4, 5, 8]
+    window = [1, 2, 3, 4, 5]
+    _, _, _, query_start, query_end, matches = rust_core.align_pair_details(
+        query, window
+    )
+    located = rust_core.locate_in_query(query, window)
+    assert located == (query_start, query_end, matches) == (2, 8, 5)
+
+
+@requires_rust
+def test_align_pair_convex_bridges_long_gap(rust_core: ModuleType) -> None:
+    """Verify convex gaps bridge an insertion that linear gaps cannot."""
+    seq1 = list(range(1, 11))
+    seq2 = [1, 2, 3, 4, 5, *range(100, 110), 6, 7, 8, 9, 10]
+    assert rust_core.align_pair_details(seq1, seq2, gap_score=-2)[:3] == (10, 0, 5)
+    bridged = rust_core.align_pair_convex(seq1, seq2, gap_open=-3, gap_extend=-2)
+    assert bridged == (12, 0, 20, 0, 10, 10)
+    with pytest.raises(ValueError):
+        rust_core.align_pair_convex(seq1, seq2, gap_extend=1)
+
+
+@requires_rust
+def test_align_pair_block_scores_sum_with_gaps(rust_core: ModuleType) -> None:
+    """Verify per-block scores plus the gap between them equal the total."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seq2 = [1, 2, 3, 9, 4, 5, 6]
+    *details, blocks, scores = rust_core.align_pair_block_scores(seq1, seq2)
+    assert tuple(details) == rust_core.align_pair_details(seq1, seq2)
+    assert blocks == [(0, 3), (4, 7)]
+    assert scores == [6, 6]
+    assert sum(scores) - 1 == details[0]
+
+
+@requires_rust
+def test_align_pair_block_weights_rank_rare_tokens(rust_core: ModuleType) -> None:
+    """Verify a short block of rare tokens outweighs a longer common one."""
+    seq1 = [1, 2, 9, 3, 4, 5, 6]
+    seq2 = [1, 2, 8, 3, 4, 5, 6]
+    token_idf = [0.5, 4.0, 3.0] + [0.5] * 7
+    *details, blocks, weights = rust_core.align_pair_block_weights(
+        seq1, seq2, token_idf
+    )
+    *_, scores = rust_core.align_pair_block_scores(seq1, seq2)
+    assert tuple(details) == rust_core.align_pair_details(seq1, seq2)
+    assert blocks == [(0, 2), (3, 7)]
+    assert scores == [4, 8]
+    assert weights == [7.0, 2.0]
+    with pytest.raises(ValueError):
+        rust_core.align_pair_block_weights(seq1, seq2, [1.0])
+
+
+@requires_rust
+def test_hamming_threshold_matches_near_codes(rust_core: ModuleType) -> None:
+    """Verify codes one bit apart align as matches at threshold 1."""
+    query = [0b1010_0000, 0b0110_1100, 0b1111_0000]
+    reference = [0b1010_0001, 0b0100_1100, 0b1111_0000, 0b1010_0111]
+    assert rust_core.align_pair_details(query, reference)[0] == 2
+    config = rust_core.AlignConfig(hamming_threshold=1)
+    assert config.hamming_threshold == 1
+    details = rust_core.align_pair_cfg(query, reference, config)
+    assert details == (6, 0, 3, 0, 3, 3)
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(hamming_threshold=1, numeric_tolerance=1)
+
+
+@requires_rust
+def test_align_topk_deduped_matches_naive(rust_core: ModuleType) -> None:
+    """Verify deduplicated top-k equals the naive ranking with fewer alignments."""
+    seq1 = [1, 2, 3, 4]
+    seqs = [[9, 1, 2, 9], [1, 2, 3, 4], [9, 1, 2, 9], [3, 4], [1, 2, 3, 4]]
+    top, aligned = rust_core.align_topk_deduped(seq1, seqs, top_k=5)
+    assert top == rust_core.align_topk_details(seq1, seqs, top_k=5)
+    assert aligned == 3
+
+
+@requires_rust
+def test_sort_key_orders_equal_scores_by_matches(rust_core: ModuleType) -> None:
+    """Verify sort_key reorders the top-k without changing which are kept."""
+    seq1 = list(range(1, 9))
+    seqs = [[1, 2], [1, 2, 3, 4], [50, 1, 90, 3, 91, 5, 92, 7, 8]]
+    kwargs = {"top_k": 2, "match_score": 3}
+    top = rust_core.align_topk_details(seq1, seqs, **kwargs)
+    assert [(item[0], item[1], item[6]) for item in top] == [(12, 1, 4), (12, 2, 5)]
+    top = rust_core.align_topk_details(seq1, seqs, sort_key="matches", **kwargs)
+    assert [(item[0], item[1], item[6]) for item in top] == [(12, 2, 5), (12, 1, 4)]
+    with pytest.raises(ValueError):
+        rust_core.align_topk_details(seq1, seqs, sort_key="span")
+
+
+@requires_rust
+def test_align_pair_chained_finds_quote_in_long_text(rust_core: ModuleType) -> None:
+    """Verify chained seeds recover a lightly edited quote in a long text."""
+    quote = list(range(1, 61))
+    cited = quote[:20] + [999] + quote[21:40] + quote[41:]
+    text = list(range(10_000, 15_000)) + cited + list(range(20_000, 25_000))
+    result = rust_core.align_pair_chained(quote, text)
+    assert result[1:5] == (5_000, 5_000 + len(cited), 0, 60)
+    assert result == rust_core.align_pair_details(quote, text)
+    assert rust_core.align_pair_chained(quote, text, seed_len=70)[0] == 0
+
+
+@requires_rust
+def test_release_gil_false_gives_identical_results(rust_core: ModuleType) -> None:
+    """Verify holding the GIL changes nothing over many tiny alignments."""
+    pairs = [
+        ([i % 5, (i + 1) % 7, 3], [(i * 3) % 5, 3, (i + 2) % 7, 1])
+        for i in range(2_000)
+    ]
+    for seq1, seq2 in pairs:
+        held = rust_core.align_pair(seq1, seq2, release_gil=False)
+        assert held == rust_core.align_pair(seq1, seq2)
+        held = rust_core.align_pair_details(seq1, seq2, release_gil=False)
+        assert held == rust_core.align_pair_details(seq1, seq2)
+        held = rust_core.align_pair_blocks_details(seq1, seq2, release_gil=False)
+        assert held == rust_core.align_pair_blocks_details(seq1, seq2)
+    seqs = [seq2 for _, seq2 in pairs[:50]]
+    query = pairs[0][0]
+    for name in ("align_best", "align_best_details", "align_best_blocks_details"):
+        align = getattr(rust_core, name)
+        assert align(query, seqs, release_gil=False) == align(query, seqs)
+    held_topk = rust_core.align_topk_details(query, seqs, top_k=5, release_gil=False)
+    assert held_topk == rust_core.align_topk_details(query, seqs, top_k=5)
+
+    index = rust_core.ReferenceIndex(seqs)
+    held_topk = index.align_topk(query, top_k=5, release_gil=False)
+    assert held_topk == index.align_topk(query, top_k=5)
+    assert index.align_best(query, release_gil=False) == index.align_best(query)
+    config = rust_core.AlignConfig(release_gil=False)
+    assert not config.release_gil
+    assert rust_core.align_pair_cfg(query, seqs[1], config) == (
+        rust_core.align_pair_details(query, seqs[1])
+    )
+    held_topk = rust_core.align_topk_cfg(query, seqs, config, top_k=5)
+    default = rust_core.AlignConfig()
+    assert held_topk == rust_core.align_topk_cfg(query, seqs, default, top_k=5)
+    held = rust_core.IncrementalAligner(seqs[1])
+    released = rust_core.IncrementalAligner(seqs[1])
+    for token in query:
+        assert held.push_query_token(token, release_gil=False) == (
+            released.push_query_token(token)
+        )
+
+
+@requires_rust
+def test_column_maxima_scores_each_reference_token(rust_core: ModuleType) -> None:
+    """Verify the best score ending on each reference token."""
+    maxima = rust_core.column_maxima([1, 2, 3], [9, 1, 2, 3, 1])
+    assert [int(score) for score in maxima] == [0, 2, 4, 6, 5]
+    assert max(maxima) == rust_core.align_pair([1, 2, 3], [9, 1, 2, 3, 1])[0]
+
+
+@requires_rust
+def test_ref_weights_rank_weighted_reference_first(rust_core: ModuleType) -> None:
+    """Verify a weighted reference outranks a higher raw score."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seqs = [[1, 2, 3, 4, 5, 6], [1, 2, 3, 4, 5], [1, 2, 3, 4]]
+    top = rust_core.align_topk_details(seq1, seqs, top_k=2)
+    assert [(item[0], item[1]) for item in top] == [(12, 0), (10, 1)]
+    weights = [1.0, 1.5, 1.0]
+    top = rust_core.align_topk_details(seq1, seqs, top_k=2, ref_weights=weights)
+    assert [(item[0], item[1]) for item in top] == [(10, 1), (12, 0)]
+    top = rust_core.align_topk_details(seq1, seqs, top_k=1, ref_weights=[0, 0, 1])
+    assert [(item[0], item[1]) for item in top] == [(8, 2)]
+    with pytest.raises(ValueError):
+        rust_core.align_topk_details(seq1, seqs, ref_weights=[1.0, 1.0])
+
+
+@requires_rust
+def test_single_token_sequences_give_unit_spans(rust_core: ModuleType) -> None:
+    """Verify 1 x n and n x 1 inputs report one-token spans and one match."""
+    cases = [
+        ([5], [5], 0, 0),
+        ([5], [7, 8, 5], 0, 2),
+        ([5], [7, 5, 8], 0, 1),
+        ([7, 8, 5], [5], 2, 0),
+        ([5, 7, 8], [5], 0, 0),
+    ]
+    for seq1, seq2, qs, ts in cases:
+        expected = (2, ts, ts + 1, qs, qs + 1, 1)
+        assert rust_core.align_pair_details(seq1, seq2) == expected
+        blocks = rust_core.align_pair_blocks_details(seq1, seq2)
+        assert blocks == (*expected, [(ts, ts + 1)])
+    assert rust_core.align_pair_details([5], [7, 8]) == (0, 0, 0, 0, 0, 0)
+
+
+@requires_rust
+def test_align_pair_min_positive_returns_weaker_match(rust_core: ModuleType) -> None:
+    """Verify the weaker of two embedded matches is returned."""
+    seq1 = [1, 2, 3, 4]
+    seq2 = [9, 1, 2, 9, 9, 1, 2, 3, 4, 9]
+    assert rust_core.align_pair_details(seq1, seq2) == (8, 5, 9, 0, 4, 4)
+    assert rust_core.align_pair_min_positive(seq1, seq2) == (4, 1, 3, 0, 2, 2)
+    assert rust_core.align_pair_min_positive(seq1, [7, 8]) is None
+
+
+@requires_rust
+def test_align_best_over_windows_reports_window(rust_core: ModuleType) -> None:
+    """Verify the winning window's offset points at the embedded quote."""
+    query = [1, 2, 3, 4, 5]
+    reference = list(range(100, 200))
+    reference[47:52] = query
+    details, window = rust_core.align_best_over_windows(query, reference, 20, 10)
+    assert window == 40
+    assert details == (10, 47, 52, 0, 5, 5)
+    assert details == rust_core.align_pair_details(query, reference)
+    with pytest.raises(ValueError):
+        rust_core.align_best_over_windows(query, reference, 20, 0)
+
+
+@requires_rust
+def test_align_above_threshold_returns_all_qualifying(rust_core: ModuleType) -> None:
+    """Verify exactly the references at or above the threshold come back."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seqs = [[1, 2], [1, 2, 3, 4, 5, 6], [4, 5, 6], [9, 1, 2, 3, 4], [7, 8], [1, 2, 3]]
+    above = rust_core.align_above_threshold(seq1, seqs, 6)
+    assert [(item[0], item[1]) for item in above] == [(12, 1), (8, 3), (6, 5), (6, 2)]
+    expected = rust_core.align_topk_details(seq1, seqs, top_k=len(seqs))[:4]
+    assert above == expected
+    assert rust_core.align_above_threshold(seq1, seqs, 13) == []
+
+
+@requires_rust
+def test_align_pair_observed_calls_back_once_per_row(rust_core: ModuleType) -> None:
+    """Verify the row callback fires once per query token, in order."""
+    seq1 = [1, 2, 9, 3]
+    seq2 = [7, 1, 2, 3]
+    rows = []
+    result = rust_core.align_pair_observed(seq1, seq2, lambda *row: rows.append(row))
+    assert result == rust_core.align_pair_details(seq1, seq2)
+    assert rows == [(1, 4, 2, 2, 2), (2, 4, 4, 3, 4), (3, 4, 3, 3, 4), (4, 4, 5, 4, 5)]
+
+    def fail(*row: int) -> None:
+        raise RuntimeError(row)
+
+    with pytest.raises(RuntimeError):
+        rust_core.align_pair_observed(seq1, seq2, fail)
+
+
+@requires_rust
+def test_align_topk_packed_matches_details(rust_core: ModuleType) -> None:
+    """Verify the packed array holds the top-k tuples row by row."""
+    np = pytest.importorskip("numpy")
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seqs = [[1, 2], [1, 2, 3, 4, 5, 6], [4, 5, 6], [9, 1, 2, 3, 4], [7, 8]]
+    packed = rust_core.align_topk_packed(seq1, seqs, top_k=3)
+    assert packed.dtype == np.int64
+    assert packed.shape == (3, 7)
+    expected = rust_core.align_topk_details(seq1, seqs, top_k=3)
+    assert packed.tolist() == [list(item) for item in expected]
+    assert rust_core.align_topk_packed(seq1, [], top_k=3).shape == (0, 7)
+
+
+@requires_rust
+def test_align_config_require_query_start_zero(rust_core: ModuleType) -> None:
+    """Verify the anchored alignment keeps a mismatched first query token."""
+    seq1 = [9, 1, 2, 3, 4, 5]
+    seq2 = [7, 1, 2, 3, 4, 5, 9, 8]
+    assert rust_core.align_pair_details(seq1, seq2) == (10, 1, 6, 1, 6, 5)
+    config = rust_core.AlignConfig(require_query_start_zero=True)
+    assert config.require_query_start_zero
+    anchored = rust_core.align_pair_cfg(seq1, seq2, config)
+    assert anchored == (9, 0, 6, 0, 6, 5)
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(require_query_start_zero=True, reverse=True)
+
+
+@requires_rust
+def test_align_config_cross_map(rust_core: ModuleType) -> None:
+    """Verify query ids match reference ids only through the cross map."""
+    query = [100, 101, 3]
+    reference = [7, 8, 3]
+    assert rust_core.align_pair_details(query, reference) == (2, 2, 3, 2, 3, 1)
+    config = rust_core.AlignConfig(cross_map={100: 7, 101: 8})
+    mapped = rust_core.align_pair_cfg(query, reference, config)
+    assert mapped == (6, 0, 3, 0, 3, 3)
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(cross_map={100: 7}, hamming_threshold=1)
+
+
+@requires_rust
+def test_align_above_percentile_keeps_top_fraction(rust_core: ModuleType) -> None:
+    """Verify the 90th-percentile cutoff keeps the top tenth of the corpus."""
+    seq1 = list(range(1, 21))
+    seqs = [list(range(1, length + 1)) for length in range(1, 21)]
+    cutoff, above = rust_core.align_above_percentile(seq1, seqs, 90)
+    assert cutoff == 36
+    assert [(item[0], item[1]) for item in above] == [(40, 19), (38, 18)]
+    assert above == rust_core.align_topk_details(seq1, seqs, top_k=2)
+    with pytest.raises(ValueError):
+        rust_core.align_above_percentile(seq1, seqs, 101)
+
+
+@requires_rust
+def test_align_first_above_stops_early(rust_core: ModuleType) -> None:
+    """Verify the scan returns a qualifying match without scoring every reference."""
+    seq1 = [1, 2, 3, 4, 5, 6]
+    seqs = [[1, 9]] * 1000
+    seqs[2] = [1, 2, 3, 4]
+    seqs[500] = seq1
+    first, scored = rust_core.align_first_above(seq1, seqs, 8, num_threads=1)
+    assert first == (8, 2, 0, 4, 0, 4, 4)
+    assert scored < len(seqs)
+    assert rust_core.align_first_above(seq1, seqs, 13) == (None, len(seqs))
+
+
+@requires_rust
+def test_align_pair_matched_span_inside_alignment(rust_core: ModuleType) -> None:
+    """Verify the matched span skips an opening column that scores without matching."""
+    seq1 = [1999, 5, 6, 0, 7]
+    seq2 = [3, 2000, 5, 6, 8, 7]
+    config = rust_core.AlignConfig(numeric_tolerance=2)
+    details = rust_core.align_pair_cfg(seq1, seq2, config)
+    assert details[1:5] == (1, 6, 0, 5)
+    span = rust_core.align_pair_matched_span(seq1, seq2, numeric_tolerance=2)
+    assert span == (2, 6, 1, 5)
+    assert rust_core.align_pair_matched_span(seq1, seq2) == (2, 6, 1, 5)
+    assert rust_core.align_pair_matched_span([1], [2]) is None
+
+
+@requires_rust
+def test_align_config_restart_floor_bridges(rust_core: ModuleType) -> None:
+    """Verify a restart floor joins two regions split by a short bad stretch."""
+    seq1 = [1, 2, 3, 20, 21, 22, 23, 24, 25, 4, 5, 6]
+    seq2 = [1, 2, 3, 40, 41, 42, 43, 44, 45, 4, 5, 6]
+    assert rust_core.align_pair_details(seq1, seq2) == (6, 0, 3, 0, 3, 3)
+    config = rust_core.AlignConfig(restart_floor=2)
+    assert config.restart_floor == 2
+    bridged = rust_core.align_pair_cfg(seq1, seq2, config)
+    assert bridged == (8, 0, 12, 0, 12, 6)
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(restart_floor=-1)
+    with pytest.raises(ValueError):
+        rust_core.AlignConfig(restart_floor=2, require_query_start_zero=True)
+
+
+@requires_rust
+def test_classify_match_labels(rust_core: ModuleType) -> None:
+    """Verify each label at its boundary, including none for disjoint inputs."""
+    query = list(range(1, 11))
+    substituted = query[:5] + [99] + query[6:]
+    label, details = rust_core.classify_match(query, [50, *query, 51])
+    assert (label, details) == ("exact", (20, 1, 11, 0, 10, 10))
+    assert rust_core.classify_match(query, substituted)[0] == "strong"
+    stricter = rust_core.classify_match(query, substituted, strong_identity=91)
+    assert stricter[0] == "partial"
+    assert rust_core.classify_match(query, [1, 2, 3])[0] == "partial"
+    assert rust_core.classify_match(query, [1, 2])[0] == "none"
+    assert rust_core.classify_match(query, [20, 21, 22]) == ("none", (0,) * 6)
+    with pytest.raises(ValueError):
+        rust_core.classify_match(query, query, partial_coverage=1.5)
+
+
+@requires_rust
+def test_align_best_details_exact_occurrence(rust_core: ModuleType) -> None:
+    """Verify a verbatim occurrence is reported as the full alignment would."""
+    query = [1, 2, 3, 4]
+    candidates = [[1, 2, 9, 3, 4], [7, 7, 1, 2, 3, 4, 8], [4, 3, 2, 1]]
+    details = rust_core.align_best_details(query, candidates, 2, -1, -1)
+    assert details == (8, 1, 2, 6, 0, 4, 4)
+    pair = rust_core.align_pair_details(query, candidates[1], 2, -1, -1)
+    assert details[:1] + details[2:] == pair